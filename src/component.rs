@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
-use bevy::prelude::{Component, Deref, Handle, Name, Reflect, ReflectComponent};
+use bevy::{
+    prelude::{Component, Deref, Handle, Name, Reflect, ReflectComponent},
+    utils::HashMap,
+};
 
 use crate::StyleSheetAsset;
 
@@ -106,6 +109,64 @@ impl Class {
     }
 }
 
+/// Sets the entities attributes to be matched by attribute selectors on `css`, like `[key=value]`.
+///
+/// This works like [`data-*`](https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/data-*)
+/// attributes on HTML, so any key and value can be used.
+///
+/// # Examples
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecss::prelude::*;
+/// fn system(mut commands: Commands) {
+///     // This entity can be selected by either "[rarity]", "[rarity=epic]"
+///     // or even "[level>=10]"
+///     commands.spawn(CssAttributes::default().with("rarity", "epic").with("level", "12"));
+/// }
+/// ```
+#[derive(Debug, Reflect, Component, Default, Clone)]
+#[reflect(Component)]
+pub struct CssAttributes(HashMap<String, String>);
+
+impl CssAttributes {
+    /// Returns this component with the given attribute set.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    /// Returns the value of the given attribute, if it exists.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Sets the value of the given attribute.
+    ///
+    /// Changing this component will automatically trigger the style system to reapply the style sheet,
+    /// if any attribute selector is used.
+    ///
+    /// This method returns `true` if the attribute was modified, `false` otherwise.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> bool {
+        let value = value.into();
+        let key = key.into();
+
+        if self.get(&key) == Some(value.as_str()) {
+            return false;
+        }
+
+        self.0.insert(key, value);
+        true
+    }
+
+    /// Removes the given attribute.
+    ///
+    /// This method returns `true` if the attribute was removed, `false` otherwise.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.0.remove(key).is_some()
+    }
+}
+
 /// Applies a [`StyleSheetAsset`] on the entity which has this component.
 ///
 /// Note that style rules are applied only once when the component is added, or if the asset is changed
@@ -193,6 +254,32 @@ pub(crate) trait MatchSelectorElement {
     fn matches(&self, element: &str) -> bool;
 }
 
+/// Marks an entity as disabled, so it can be matched by `:disabled` pseudo-class.
+///
+/// Disabled entities are never matched by `:hover` nor `:active` pseudo-classes.
+/// Adding or removing this component automatically reapplies the style sheet.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Disabled;
+
+/// Marks an entity as checked, like a toggle or a check box, so it can be matched by `:checked` pseudo-class.
+/// Adding or removing this component automatically reapplies the style sheet.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Checked;
+
+/// Marks an entity as selected, like an item on a list, so it can be matched by `:selected` pseudo-class.
+/// Adding or removing this component automatically reapplies the style sheet.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Selected;
+
+/// Marks an entity as focused, so it can be matched by `:focus` pseudo-class.
+/// Adding or removing this component automatically reapplies the style sheet.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct Focused;
+
 impl MatchSelectorElement for Class {
     fn matches(&self, element: &str) -> bool {
         self.matches(element)
@@ -230,4 +317,18 @@ mod tests {
         assert!(!class.set("blue-button enabled"));
         assert_eq!(class.0, "blue-button enabled");
     }
+
+    #[test]
+    fn modify_attributes() {
+        let mut attributes = CssAttributes::default().with("rarity", "epic");
+        assert_eq!(attributes.get("rarity"), Some("epic"));
+
+        assert!(!attributes.set("rarity", "epic"));
+        assert!(attributes.set("rarity", "common"));
+        assert_eq!(attributes.get("rarity"), Some("common"));
+
+        assert!(attributes.remove("rarity"));
+        assert!(!attributes.remove("rarity"));
+        assert_eq!(attributes.get("rarity"), None);
+    }
 }
@@ -0,0 +1,328 @@
+use bevy::{
+    asset::AssetId,
+    ecs::{reflect::ReflectComponent, system::SystemParam},
+    prelude::{
+        Assets, Changed, Component, DetectChanges, Entity, Or, Parent, Query, Reflect,
+        RemovedComponents, Res, ResMut, Resource, With,
+    },
+    ui::Node,
+    utils::HashMap,
+};
+use smallvec::SmallVec;
+
+use crate::{
+    media::{FeatureContext, MediaValue},
+    StyleSheet, StyleSheetAsset,
+};
+
+/// Marks an entity as a query container, so it's descendants can be styled by `@container` rules,
+/// like `@container (min-width: 300px)`, evaluated against it's [`Node`] size.
+///
+/// Entities are matched against their nearest ancestor container, never against themselves.
+///
+/// This component can also be set by `container-type` property.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum ContainerType {
+    /// Isn't a query container.
+    #[default]
+    Normal,
+    /// Both width and height can be queried.
+    Size,
+    /// Only the width can be queried.
+    InlineSize,
+}
+
+/// Values used to evaluate container queries, which is the size of a container on each axis which can be queried.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct ContainerContext {
+    width: Option<f32>,
+    height: Option<f32>,
+}
+
+impl ContainerContext {
+    fn new(node: &Node, container_type: ContainerType) -> Self {
+        let size = node.size();
+        match container_type {
+            ContainerType::Normal => Self::default(),
+            ContainerType::Size => Self {
+                width: Some(size.x),
+                height: Some(size.y),
+            },
+            ContainerType::InlineSize => Self {
+                width: Some(size.x),
+                height: None,
+            },
+        }
+    }
+
+    /// Returns the value of the given size feature, if it's supported and the container can be queried on it's axis.
+    fn feature(&self, name: &str) -> Option<MediaValue> {
+        let value = match name {
+            "width" | "inline-size" => MediaValue::Length(self.width?),
+            "height" | "block-size" => MediaValue::Length(self.height?),
+            "aspect-ratio" if self.height? > 0.0 => MediaValue::Ratio(self.width? / self.height?),
+            "orientation" if self.height? >= self.width? => MediaValue::Ident("portrait".into()),
+            "orientation" => MediaValue::Ident("landscape".into()),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+impl FeatureContext for ContainerContext {
+    fn any_value(&self, name: &str, predicate: impl Fn(&MediaValue) -> bool) -> bool {
+        self.feature(name).is_some_and(|value| predicate(&value))
+    }
+}
+
+/// Parameters used to find the [`ContainerContext`] of each styled entity.
+#[derive(SystemParam)]
+pub(crate) struct ContainerParam<'w, 's> {
+    containers: Query<'w, 's, (&'static Node, &'static ContainerType)>,
+    parents: Query<'w, 's, &'static Parent>,
+}
+
+impl ContainerParam<'_, '_> {
+    /// Builds the [`ContainerContext`] of the nearest ancestor container of the given entity.
+    /// When there is none, no size feature can be queried.
+    pub(crate) fn context(&self, entity: Entity) -> ContainerContext {
+        let mut ancestor = self.parents.get(entity).ok().map(Parent::get);
+        while let Some(entity) = ancestor {
+            if let Ok((node, &container_type)) = self.containers.get(entity) {
+                if container_type != ContainerType::Normal {
+                    return ContainerContext::new(node, container_type);
+                }
+            }
+            ancestor = self.parents.get(entity).ok().map(Parent::get);
+        }
+
+        ContainerContext::default()
+    }
+
+    /// Evaluates all container conditions of the given rule for the given entity.
+    pub(crate) fn matches(&self, entity: Entity, sheet: &StyleSheetAsset, rule: usize) -> bool {
+        let Some(rule) = sheet.get(rule) else {
+            return false;
+        };
+
+        let context = self.context(entity);
+        rule.containers
+            .iter()
+            .all(|condition| condition.matches(&context))
+    }
+}
+
+/// The result of the container conditions of a rule for an entity, on the last time it's style sheet was applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ContainerResult {
+    pub entity: Entity,
+    pub sheet: AssetId<StyleSheetAsset>,
+    pub rule: usize,
+    pub matches: bool,
+}
+
+/// Results of all container queries of each [`StyleSheet`] root, on the last time it was applied.
+#[derive(Debug, Default, Resource)]
+pub(crate) struct ContainerQueryState(pub HashMap<Entity, SmallVec<[ContainerResult; 8]>>);
+
+/// Applies again all [`StyleSheet`] which have any container query which result changed since the last
+/// time it was applied, like when a container is resized past a breakpoint.
+///
+/// Container queries are evaluated again only when some container was changed, resized or removed.
+#[allow(clippy::type_complexity)]
+pub(crate) fn watch_containers(
+    containers: ContainerParam,
+    assets: Res<Assets<StyleSheetAsset>>,
+    mut state: ResMut<ContainerQueryState>,
+    mut q_sheets: Query<&mut StyleSheet>,
+    q_changed: Query<
+        (),
+        (
+            With<ContainerType>,
+            Or<(Changed<Node>, Changed<ContainerType>)>,
+        ),
+    >,
+    mut removed: RemovedComponents<ContainerType>,
+) {
+    let any_removed = removed.read().count() > 0;
+    if state.0.is_empty() || (!state.is_changed() && q_changed.is_empty() && !any_removed) {
+        return;
+    }
+
+    let changed = state
+        .0
+        .iter()
+        .filter(|(root, results)| {
+            !q_sheets.contains(**root)
+                || results.iter().any(|result| {
+                    let matches = assets
+                        .get(result.sheet)
+                        .is_some_and(|sheet| containers.matches(result.entity, sheet, result.rule));
+                    matches != result.matches
+                })
+        })
+        .map(|(root, _)| *root)
+        .collect::<SmallVec<[_; 8]>>();
+
+    if changed.is_empty() {
+        return;
+    }
+
+    for root in changed {
+        state.0.remove(&root);
+        if let Ok(mut sheet) = q_sheets.get_mut(root) {
+            bevy::log::debug!(
+                "Refreshing sheet {:?} due to changed container queries",
+                sheet
+            );
+            sheet.refresh();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cssparser::{Parser, ParserInput};
+
+    use super::*;
+    use crate::{media::parse_media_condition, Class};
+
+    fn matches(condition: &str, context: &ContainerContext) -> bool {
+        let mut input = ParserInput::new(condition);
+        let mut parser = Parser::new(&mut input);
+        parse_media_condition(&mut parser)
+            .expect("Should be a valid container condition")
+            .matches(context)
+    }
+
+    #[test]
+    fn evaluate_container_queries() {
+        let card = ContainerContext {
+            width: Some(300.0),
+            height: Some(400.0),
+        };
+        assert!(matches("(min-width: 300px)", &card));
+        assert!(matches("(200px < inline-size < 320px)", &card));
+        assert!(matches(
+            "(block-size > 300px) and (orientation: portrait)",
+            &card
+        ));
+        assert!(matches("(aspect-ratio < 1)", &card));
+        assert!(!matches("(min-width: 301px)", &card));
+        assert!(
+            !matches("(prefers-color-scheme: light)", &card),
+            "Only size features can be queried"
+        );
+
+        let sidebar = ContainerContext {
+            width: Some(200.0),
+            height: None,
+        };
+        assert!(matches("(max-width: 250px)", &sidebar));
+        assert!(!matches("(height >= 0px)", &sidebar));
+        assert!(!matches("(orientation: landscape)", &sidebar));
+
+        let none = ContainerContext::default();
+        assert!(!matches("(width >= 0px)", &none));
+        assert!(!matches("(width < 0px) or (height < 0px)", &none));
+    }
+
+    #[test]
+    fn apply_container_queries() {
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), crate::EcssPlugin));
+
+        // Without layout, all nodes have zero size.
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #sidebar { container-type: inline-size; }
+                #grid, .card { container-type: size; }
+                .card { width: 10px; }
+                @container (max-width: 100px) {
+                    .card { width: 20px; }
+                    @container (height <= 0px) {
+                        .card { height: 30px; }
+                    }
+                }
+                "#,
+            ));
+
+        let spawn_card = |app: &mut App, parent: Entity| {
+            let card = app
+                .world_mut()
+                .spawn((NodeBundle::default(), Class::new("card")))
+                .id();
+            app.world_mut().entity_mut(parent).add_child(card);
+            card
+        };
+
+        let root = app
+            .world_mut()
+            .spawn((NodeBundle::default(), StyleSheet::new(handle)))
+            .id();
+        let sidebar = app
+            .world_mut()
+            .spawn((NodeBundle::default(), Name::new("sidebar")))
+            .set_parent(root)
+            .id();
+        let grid = app
+            .world_mut()
+            .spawn((NodeBundle::default(), Name::new("grid")))
+            .set_parent(root)
+            .id();
+        let panel = app
+            .world_mut()
+            .spawn(NodeBundle::default())
+            .set_parent(root)
+            .id();
+        let sidebar_card = spawn_card(&mut app, sidebar);
+        let grid_card = spawn_card(&mut app, grid);
+        let panel_card = spawn_card(&mut app, panel);
+        let nested_card = spawn_card(&mut app, grid_card);
+
+        let size = |app: &mut App, entity: Entity| {
+            // Containers set by `container-type` are only evaluated on the next frame.
+            app.update();
+            app.update();
+            app.update();
+            let style = app.world().get::<Style>(entity).unwrap();
+            (style.width, style.height)
+        };
+
+        assert_eq!(
+            size(&mut app, sidebar_card),
+            (Val::Px(20.0), Val::Auto),
+            "Height can't be queried on inline-size containers"
+        );
+        assert_eq!(size(&mut app, grid_card), (Val::Px(20.0), Val::Px(30.0)));
+        assert_eq!(
+            size(&mut app, nested_card),
+            (Val::Px(20.0), Val::Px(30.0)),
+            "Should be matched against the nearest container"
+        );
+        assert_eq!(size(&mut app, panel_card), (Val::Px(10.0), Val::Auto));
+
+        app.world_mut()
+            .entity_mut(panel)
+            .insert(ContainerType::InlineSize);
+        assert_eq!(
+            size(&mut app, panel_card),
+            (Val::Px(20.0), Val::Auto),
+            "Should apply again when a container is added"
+        );
+
+        app.world_mut().entity_mut(panel).remove::<ContainerType>();
+        assert_eq!(
+            size(&mut app, panel_card),
+            (Val::Px(10.0), Val::Auto),
+            "Should apply again when a container is removed"
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use bevy::{
+    input::{
+        gamepad::{GamepadButton, GamepadButtonType},
+        keyboard::KeyCode,
+        ButtonInput,
+    },
+    math::Vec2,
+    prelude::{
+        Children, Commands, Component, Entity, GlobalTransform, Parent, Query, Reflect,
+        ReflectComponent, Res, ResMut, Resource, With, Without,
+    },
+    ui::{Interaction, Node},
+};
+use smallvec::SmallVec;
+
+use crate::component::{Disabled, Focused};
+
+/// Makes an entity focusable by keyboard and gamepad navigation.
+///
+/// The value mimics the [`tabindex`](https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/tabindex)
+/// HTML attribute:
+/// - Negative values makes the entity focusable only by mouse or by [`EcssFocus::focus`];
+/// - `0` makes the entity focusable on it's hierarchy order;
+/// - Positive values are focused first, in ascending order, before any `0` entity.
+///
+/// This component can also be set by `tab-index` property.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TabIndex(pub i32);
+
+/// Defines how arrow keys and gamepad d-pad moves the focus among the focusable descendants of an entity.
+///
+/// The nearest ancestor of the focused entity with this component is used. When there is none,
+/// [`FocusOrder::Geometry`] is used.
+///
+/// This component can also be set by `focus-order` property.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum FocusOrder {
+    /// Moves the focus to the nearest focusable entity on the pressed direction, using [`Node`] geometry.
+    #[default]
+    Geometry,
+    /// Moves the focus to the previous or next focusable entity, the same way as `Tab` key does.
+    TabIndex,
+}
+
+/// Marks the focused entity when the focus was moved by keyboard or gamepad, so it can be matched
+/// by `:focus-visible` pseudo-class.
+#[derive(Debug, Reflect, Component, Default, Clone, Copy)]
+#[reflect(Component)]
+pub struct FocusVisible;
+
+/// Holds which entity is focused.
+///
+/// The focused entity has the [`Focused`] component, and also the [`FocusVisible`] component when the
+/// focus was moved by keyboard or gamepad. Focus can be moved by:
+/// - Pressing a focusable entity with mouse or touch;
+/// - Pressing `Tab` or `Shift + Tab` keys, following the [`TabIndex`] order;
+/// - Pressing arrow keys or gamepad d-pad, following the [`FocusOrder`], when enabled by
+///   [`EcssFocus::set_directional_navigation`];
+/// - Calling [`EcssFocus::focus`] or [`EcssFocus::clear`].
+#[derive(Debug, Default, Resource)]
+pub struct EcssFocus {
+    focused: Option<Entity>,
+    visible: bool,
+    request: Option<Option<Entity>>,
+    directional: bool,
+}
+
+impl EcssFocus {
+    /// Returns the focused entity, if any.
+    pub fn focused(&self) -> Option<Entity> {
+        self.focused
+    }
+
+    /// Returns `true` if the focused entity matches `:focus-visible` pseudo-class.
+    pub fn is_visible(&self) -> bool {
+        self.focused.is_some() && self.visible
+    }
+
+    /// Moves the focus to the given entity. The focus is changed on the next update.
+    ///
+    /// The focus visibility is kept, so if the user was navigating with keyboard, the entity will also
+    /// match `:focus-visible` pseudo-class.
+    pub fn focus(&mut self, entity: Entity) {
+        self.request = Some(Some(entity));
+    }
+
+    /// Clears the focus. The focus is changed on the next update.
+    pub fn clear(&mut self) {
+        self.request = Some(None);
+    }
+
+    /// Returns `true` if arrow keys and gamepad d-pad moves the focus.
+    pub fn directional_navigation(&self) -> bool {
+        self.directional
+    }
+
+    /// Enables or disables moving the focus with arrow keys and gamepad d-pad.
+    ///
+    /// It's disabled by default, so those inputs are left to the app, like for moving a character.
+    pub fn set_directional_navigation(&mut self, enabled: bool) {
+        self.directional = enabled;
+    }
+}
+
+/// A navigation direction, given by arrow keys or gamepad d-pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The unit vector on UI coordinates, where `y` grows downwards.
+    fn vector(self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2::NEG_Y,
+            Direction::Down => Vec2::Y,
+            Direction::Left => Vec2::NEG_X,
+            Direction::Right => Vec2::X,
+        }
+    }
+
+    /// Whether this direction moves forward on tab order.
+    fn is_forward(self) -> bool {
+        matches!(self, Direction::Down | Direction::Right)
+    }
+}
+
+/// A navigation request read from input devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Navigation {
+    Previous,
+    Next,
+    Direction(Direction),
+}
+
+/// Reads the navigation request from keyboard and gamepad, if any.
+/// Arrow keys and gamepad d-pad are only read when `directional` is `true`.
+fn read_navigation(
+    keys: Option<&ButtonInput<KeyCode>>,
+    gamepad_buttons: Option<&ButtonInput<GamepadButton>>,
+    directional: bool,
+) -> Option<Navigation> {
+    if let Some(keys) = keys {
+        if keys.just_pressed(KeyCode::Tab) {
+            return if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+                Some(Navigation::Previous)
+            } else {
+                Some(Navigation::Next)
+            };
+        }
+    }
+
+    if !directional {
+        return None;
+    }
+
+    if let Some(keys) = keys {
+        let direction = [
+            (KeyCode::ArrowUp, Direction::Up),
+            (KeyCode::ArrowDown, Direction::Down),
+            (KeyCode::ArrowLeft, Direction::Left),
+            (KeyCode::ArrowRight, Direction::Right),
+        ]
+        .into_iter()
+        .find_map(|(key, direction)| keys.just_pressed(key).then_some(direction));
+
+        if let Some(direction) = direction {
+            return Some(Navigation::Direction(direction));
+        }
+    }
+
+    gamepad_buttons.and_then(|buttons| {
+        buttons
+            .get_just_pressed()
+            .find_map(|button| match button.button_type {
+                GamepadButtonType::DPadUp => Some(Navigation::Direction(Direction::Up)),
+                GamepadButtonType::DPadDown => Some(Navigation::Direction(Direction::Down)),
+                GamepadButtonType::DPadLeft => Some(Navigation::Direction(Direction::Left)),
+                GamepadButtonType::DPadRight => Some(Navigation::Direction(Direction::Right)),
+                _ => None,
+            })
+    })
+}
+
+/// A focusable entity candidate, with it's position on hierarchy order and it's geometry.
+#[derive(Debug, Clone, Copy)]
+struct Focusable {
+    entity: Entity,
+    tab_index: i32,
+    order: usize,
+    center: Vec2,
+    size: Vec2,
+}
+
+/// Sorts the focusable entities on tab order, skipping the ones with negative [`TabIndex`].
+fn sort_tab_order(focusables: &[Focusable]) -> SmallVec<[Focusable; 8]> {
+    let mut sorted = focusables
+        .iter()
+        .filter(|f| f.tab_index >= 0)
+        .copied()
+        .collect::<SmallVec<[Focusable; 8]>>();
+    sorted.sort_by_key(|f| (f.tab_index == 0, f.tab_index, f.order));
+    sorted
+}
+
+/// Returns the previous or next focusable entity on tab order, wrapping around.
+/// If nothing is focused, the first or last entity is returned.
+fn next_in_tab_order(
+    focusables: &[Focusable],
+    current: Option<Entity>,
+    forward: bool,
+) -> Option<Entity> {
+    let sorted = sort_tab_order(focusables);
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let len = sorted.len();
+    let position = current.and_then(|current| sorted.iter().position(|f| f.entity == current));
+    let next = match (position, forward) {
+        (Some(position), true) => (position + 1) % len,
+        (Some(position), false) => (position + len - 1) % len,
+        (None, true) => 0,
+        (None, false) => len - 1,
+    };
+
+    Some(sorted[next].entity)
+}
+
+/// Returns the nearest focusable entity on the given direction, using it's geometry.
+///
+/// Only entities which are entirely past the current entity edge on the given direction are considered.
+/// The distance along the direction is favored over the distance across it.
+fn nearest_in_direction(
+    focusables: &[Focusable],
+    current: &Focusable,
+    direction: Direction,
+) -> Option<Entity> {
+    let axis = direction.vector();
+    let cross = axis.perp();
+
+    focusables
+        .iter()
+        .filter(|f| f.entity != current.entity && f.tab_index >= 0)
+        .filter_map(|f| {
+            let delta = f.center - current.center;
+            // Distance between edges along the direction
+            let gap = delta.dot(axis) - (f.size + current.size).dot(axis.abs()) / 2.0;
+            if gap < -0.5 {
+                return None;
+            }
+            let offset = delta.dot(cross).abs();
+            Some((f.entity, gap.max(0.0) + offset * 2.0))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+/// Collects all focusable entities, with their hierarchy order.
+fn collect_focusables(
+    q_roots: &Query<Entity, (With<Node>, Without<Parent>)>,
+    q_children: &Query<&Children, With<Node>>,
+    q_focusables: &Query<(&TabIndex, &Node, &GlobalTransform), Without<Disabled>>,
+) -> Vec<Focusable> {
+    let mut roots = q_roots.iter().collect::<SmallVec<[Entity; 8]>>();
+    roots.sort();
+
+    let mut focusables = vec![];
+    let mut stack = roots.into_iter().rev().collect::<Vec<_>>();
+    let mut order = 0;
+    while let Some(entity) = stack.pop() {
+        if let Ok((tab_index, node, transform)) = q_focusables.get(entity) {
+            focusables.push(Focusable {
+                entity,
+                tab_index: tab_index.0,
+                order,
+                center: transform.translation().truncate(),
+                size: node.size(),
+            });
+            order += 1;
+        }
+
+        if let Ok(children) = q_children.get(entity) {
+            stack.extend(children.iter().rev());
+        }
+    }
+
+    focusables
+}
+
+/// Returns the [`FocusOrder`] of the nearest ancestor of the given entity, including itself.
+fn get_focus_order(
+    entity: Entity,
+    q_parents: &Query<&Parent>,
+    q_focus_orders: &Query<&FocusOrder>,
+) -> FocusOrder {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if let Ok(order) = q_focus_orders.get(entity) {
+            return *order;
+        }
+        current = q_parents.get(entity).ok().map(Parent::get);
+    }
+    FocusOrder::default()
+}
+
+/// Moves the focus based on mouse interaction, keyboard, gamepad and focus requests.
+/// Keeps [`Focused`] and [`FocusVisible`] components in sync with [`EcssFocus`] resource.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn update_focus(
+    mut focus: ResMut<EcssFocus>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    gamepad_buttons: Option<Res<ButtonInput<GamepadButton>>>,
+    q_nodes: Query<(), (With<Node>, Without<Disabled>)>,
+    q_interactions: Query<(Entity, &Interaction), (With<TabIndex>, Without<Disabled>)>,
+    q_roots: Query<Entity, (With<Node>, Without<Parent>)>,
+    q_children: Query<&Children, With<Node>>,
+    q_parents: Query<&Parent>,
+    q_focusables: Query<(&TabIndex, &Node, &GlobalTransform), Without<Disabled>>,
+    q_focus_orders: Query<&FocusOrder>,
+    q_focused: Query<Entity, With<Focused>>,
+    q_visible: Query<Entity, With<FocusVisible>>,
+    mut commands: Commands,
+) {
+    // Focused entity may be despawned or disabled.
+    let current = focus.focused.filter(|&entity| q_nodes.contains(entity));
+
+    let (focused, visible) = if focus.request.is_some() {
+        let request = focus.request.take().flatten();
+        (request, focus.visible)
+    } else if let Some((entity, _)) = q_interactions
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Pressed)
+    {
+        (Some(entity), false)
+    } else if let Some(navigation) = read_navigation(
+        keys.as_deref(),
+        gamepad_buttons.as_deref(),
+        focus.directional,
+    ) {
+        let focusables = collect_focusables(&q_roots, &q_children, &q_focusables);
+        let next = match navigation {
+            Navigation::Previous => next_in_tab_order(&focusables, current, false),
+            Navigation::Next => next_in_tab_order(&focusables, current, true),
+            Navigation::Direction(direction) => {
+                match current.and_then(|current| focusables.iter().find(|f| f.entity == current)) {
+                    Some(current)
+                        if get_focus_order(current.entity, &q_parents, &q_focus_orders)
+                            == FocusOrder::Geometry =>
+                    {
+                        nearest_in_direction(&focusables, current, direction)
+                            .or(Some(current.entity))
+                    }
+                    _ => next_in_tab_order(&focusables, current, direction.is_forward()),
+                }
+            }
+        };
+        (next, true)
+    } else {
+        (current, focus.visible)
+    };
+
+    if focus.focused == focused && focus.visible == visible {
+        return;
+    }
+
+    focus.focused = focused;
+    focus.visible = visible;
+
+    for entity in &q_focused {
+        if Some(entity) != focused {
+            commands.entity(entity).remove::<Focused>();
+        }
+    }
+    for entity in &q_visible {
+        if Some(entity) != focused || !visible {
+            commands.entity(entity).remove::<FocusVisible>();
+        }
+    }
+
+    if let Some(entity) = focused {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            if !q_focused.contains(entity) {
+                entity_commands.insert(Focused);
+            }
+            if visible && !q_visible.contains(entity) {
+                entity_commands.insert(FocusVisible);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focusable(index: u32, tab_index: i32, x: f32, y: f32) -> Focusable {
+        Focusable {
+            entity: Entity::from_raw(index),
+            tab_index,
+            order: index as usize,
+            center: Vec2::new(x, y),
+            size: Vec2::splat(10.0),
+        }
+    }
+
+    #[test]
+    fn navigate_tab_order() {
+        let focusables = [
+            focusable(0, 0, 0.0, 0.0),
+            focusable(1, 2, 0.0, 0.0),
+            focusable(2, -1, 0.0, 0.0),
+            focusable(3, 1, 0.0, 0.0),
+            focusable(4, 0, 0.0, 0.0),
+        ];
+        let e = Entity::from_raw;
+
+        assert_eq!(next_in_tab_order(&focusables, None, true), Some(e(3)));
+        assert_eq!(next_in_tab_order(&focusables, None, false), Some(e(4)));
+        assert_eq!(next_in_tab_order(&focusables, Some(e(3)), true), Some(e(1)));
+        assert_eq!(next_in_tab_order(&focusables, Some(e(1)), true), Some(e(0)));
+        assert_eq!(next_in_tab_order(&focusables, Some(e(4)), true), Some(e(3)));
+        assert_eq!(
+            next_in_tab_order(&focusables, Some(e(3)), false),
+            Some(e(4))
+        );
+        assert_eq!(next_in_tab_order(&focusables, Some(e(2)), true), Some(e(3)));
+        assert_eq!(next_in_tab_order(&[], None, true), None);
+    }
+
+    #[test]
+    fn navigate_geometry() {
+        // A 3x2 grid of buttons, with the bottom right one missing
+        let focusables = [
+            focusable(0, 0, 0.0, 0.0),
+            focusable(1, 0, 20.0, 0.0),
+            focusable(2, 0, 40.0, 0.0),
+            focusable(3, 0, 0.0, 20.0),
+            focusable(4, 0, 20.0, 20.0),
+        ];
+        let e = Entity::from_raw;
+
+        let nearest = |current: usize, direction| {
+            nearest_in_direction(&focusables, &focusables[current], direction)
+        };
+
+        assert_eq!(nearest(0, Direction::Right), Some(e(1)));
+        assert_eq!(nearest(0, Direction::Down), Some(e(3)));
+        assert_eq!(nearest(0, Direction::Up), None);
+        assert_eq!(nearest(0, Direction::Left), None);
+        assert_eq!(nearest(4, Direction::Up), Some(e(1)));
+        assert_eq!(nearest(4, Direction::Left), Some(e(3)));
+        assert_eq!(nearest(4, Direction::Right), Some(e(2)));
+        assert_eq!(nearest(2, Direction::Down), Some(e(4)));
+    }
+
+    #[test]
+    fn update_focus_from_input() {
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.init_resource::<EcssFocus>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_systems(Update, update_focus);
+
+        let spawn_button = |app: &mut App, parent: Entity, x: f32| {
+            app.world_mut()
+                .spawn((
+                    NodeBundle {
+                        global_transform: GlobalTransform::from_xyz(x, 0.0, 0.0),
+                        ..default()
+                    },
+                    TabIndex(0),
+                    Interaction::None,
+                ))
+                .set_parent(parent)
+                .id()
+        };
+
+        let root = app.world_mut().spawn(NodeBundle::default()).id();
+        let play = spawn_button(&mut app, root, 0.0);
+        let options = spawn_button(&mut app, root, 20.0);
+        let quit = spawn_button(&mut app, root, 40.0);
+
+        let press = |app: &mut App, keys: &[KeyCode]| {
+            let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            input.release_all();
+            input.clear();
+            for &key in keys {
+                input.press(key);
+            }
+            app.update();
+        };
+        let focused = |app: &mut App| {
+            let mut query = app.world_mut().query_filtered::<Entity, With<Focused>>();
+            let focused = query.iter(app.world()).collect::<Vec<_>>();
+            let mut query = app
+                .world_mut()
+                .query_filtered::<Entity, With<FocusVisible>>();
+            let visible = query.iter(app.world()).collect::<Vec<_>>();
+            (focused, visible)
+        };
+
+        press(&mut app, &[KeyCode::Tab]);
+        assert_eq!(focused(&mut app), (vec![play], vec![play]));
+
+        press(&mut app, &[KeyCode::Tab]);
+        assert_eq!(focused(&mut app), (vec![options], vec![options]));
+
+        press(&mut app, &[KeyCode::ShiftLeft, KeyCode::Tab]);
+        assert_eq!(focused(&mut app), (vec![play], vec![play]));
+
+        press(&mut app, &[KeyCode::ArrowRight]);
+        assert_eq!(
+            focused(&mut app),
+            (vec![play], vec![play]),
+            "Arrow keys should be ignored by default"
+        );
+
+        app.world_mut()
+            .resource_mut::<EcssFocus>()
+            .set_directional_navigation(true);
+        press(&mut app, &[KeyCode::ArrowRight]);
+        assert_eq!(focused(&mut app), (vec![options], vec![options]));
+
+        press(&mut app, &[KeyCode::ArrowLeft]);
+        assert_eq!(focused(&mut app), (vec![play], vec![play]));
+
+        app.world_mut()
+            .entity_mut(quit)
+            .insert(Interaction::Pressed);
+        press(&mut app, &[]);
+        assert_eq!(
+            focused(&mut app),
+            (vec![quit], vec![]),
+            "Focus by pressing shouldn't be visible"
+        );
+        assert_eq!(app.world().resource::<EcssFocus>().focused(), Some(quit));
+    }
+}
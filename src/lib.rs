@@ -1,6 +1,9 @@
 #![doc = include_str!("../README.md")]
 
 mod component;
+mod container;
+mod focus;
+mod media;
 mod parser;
 pub mod property;
 mod selector;
@@ -12,32 +15,55 @@ use std::{error::Error, fmt::Display};
 use bevy::{
     app::{First, MainScheduleOrder, Update},
     asset::AssetEvents,
-    ecs::{schedule::ScheduleLabel, system::SystemState},
+    ecs::{schedule::ScheduleLabel, system::SystemState, world::EntityRef},
+    log::warn,
     prelude::{
         AssetApp, Button, Component, Entity, IntoSystemConfigs, IntoSystemSetConfigs, Plugin,
-        PostUpdate, PreUpdate, Query, SystemSet, With,
+        Query, Resource, State, States, SystemSet, With,
     },
     text::Text,
     ui::{BackgroundColor, Interaction, Node, Style, UiImage},
 };
 
-use property::StyleSheetState;
-use stylesheet::{SCSSLoader, StyleSheetLoader};
-
-use system::{ComponentFilterRegistry, PrepareParams};
-
-pub use component::{Class, StyleSheet};
-pub use property::{Property, PropertyToken, PropertyValues};
-pub use selector::{Selector, SelectorElement};
+use container::ContainerQueryState;
+use media::{MediaFeatureValues, MediaQueryState};
+use property::{CustomPropertiesState, StyleSheetState};
+use selector::PseudoClassElement;
+#[cfg(feature = "sass")]
+use stylesheet::SCSSLoader;
+use stylesheet::StyleSheetLoader;
+
+use system::{ComponentFilterRegistry, PrepareParams, PseudoClassPredicate, PseudoClassRegistry};
+
+pub use component::{Checked, Class, CssAttributes, Disabled, Focused, Selected, StyleSheet};
+pub use container::ContainerType;
+pub use focus::{EcssFocus, FocusOrder, FocusVisible, TabIndex};
+pub use media::{
+    ColorScheme, EcssMedia, MediaComparison, MediaCondition, MediaContext, MediaFeature,
+    MediaQuery, MediaQueryList, MediaValue,
+};
+pub use property::{
+    EcssVariables, LengthRect, LengthValue, Property, PropertyToken, PropertyValues,
+};
+pub use selector::{AttributeElement, AttributeMatcher, Selector, SelectorElement, Specificity};
 pub use stylesheet::{StyleRule, StyleSheetAsset};
+pub use system::PseudoClassTicks;
 
 /// use `bevy_ecss::prelude::*;` to import common components, and plugins and utility functions.
 pub mod prelude {
-    pub use super::component::{Class, StyleSheet};
+    pub use super::component::{
+        Checked, Class, CssAttributes, Disabled, Focused, Selected, StyleSheet,
+    };
+    pub use super::container::ContainerType;
+    pub use super::focus::{EcssFocus, FocusOrder, FocusVisible, TabIndex};
+    pub use super::media::{ColorScheme, EcssMedia};
+    pub use super::property::EcssVariables;
     pub use super::stylesheet::StyleSheetAsset;
     pub use super::EcssPlugin;
     pub use super::RegisterComponentSelector;
+    pub use super::RegisterMediaFeature;
     pub use super::RegisterProperty;
+    pub use super::RegisterPseudoClass;
 }
 
 /// Errors which can happens while parsing `css` into [`Selector`] or [`Property`].
@@ -54,6 +80,8 @@ pub enum EcssError {
     InvalidSelector,
     /// An unexpected token was found on a style sheet rule.
     UnexpectedToken(String),
+    /// A value with an unknown unit, like `10furlongs`, was found on a style sheet rule.
+    UnknownUnit(String),
 }
 
 impl Error for EcssError {}
@@ -68,6 +96,7 @@ impl Display for EcssError {
             EcssError::InvalidPropertyValue(p) => write!(f, "Invalid property value: {}", p),
             EcssError::InvalidSelector => write!(f, "Invalid selector"),
             EcssError::UnexpectedToken(t) => write!(f, "Unexpected token: {}", t),
+            EcssError::UnknownUnit(u) => write!(f, "Unknown unit: {}", u),
         }
     }
 }
@@ -104,6 +133,15 @@ impl Plugin for EcssPlugin {
             .resource_mut::<MainScheduleOrder>()
             .insert_after(Update, DoEcss);
         app.register_type::<Class>()
+            .register_type::<CssAttributes>()
+            .register_type::<Disabled>()
+            .register_type::<Checked>()
+            .register_type::<Selected>()
+            .register_type::<Focused>()
+            .register_type::<FocusVisible>()
+            .register_type::<TabIndex>()
+            .register_type::<FocusOrder>()
+            .register_type::<ContainerType>()
             .register_type::<StyleSheet>()
             .init_asset::<StyleSheetAsset>()
             // .configure_sets(
@@ -121,12 +159,33 @@ impl Plugin for EcssPlugin {
                     .chain(),
             )
             .init_resource::<StyleSheetState>()
+            .init_resource::<CustomPropertiesState>()
+            .init_resource::<EcssVariables>()
+            .init_resource::<EcssMedia>()
+            .init_resource::<MediaQueryState>()
+            .init_resource::<MediaFeatureValues>()
+            .init_resource::<ContainerQueryState>()
             .init_resource::<ComponentFilterRegistry>()
+            .init_resource::<PseudoClassRegistry>()
+            .init_resource::<EcssFocus>()
             .init_asset_loader::<StyleSheetLoader>()
+            .add_systems(Update, focus::update_focus)
             .add_systems(DoEcss, system::prepare.in_set(EcssSet::Prepare))
             .add_systems(
                 DoEcss,
-                system::watch_tracked_entities.in_set(EcssSet::ChangeDetection),
+                (
+                    system::watch_tracked_entities,
+                    system::watch_variables,
+                    media::watch_media,
+                    container::watch_containers,
+                )
+                    .in_set(EcssSet::ChangeDetection),
+            )
+            .add_systems(
+                DoEcss,
+                property::calc::resolve_calc_values
+                    .after(EcssSet::Apply)
+                    .before(EcssSet::Cleanup),
             )
             .add_systems(DoEcss, system::clear_state.in_set(EcssSet::Cleanup));
 
@@ -219,6 +278,11 @@ fn register_properties(app: &mut bevy::prelude::App) {
     app.register_property::<BorderColorProperty>();
     app.register_property::<ImageProperty>();
     app.register_property::<ZIndexProperty>();
+
+    app.register_property::<TabIndexProperty>();
+    app.register_property::<FocusOrderProperty>();
+
+    app.register_property::<ContainerTypeProperty>();
 }
 
 /// Utility trait which adds the [`register_component_selector`](RegisterComponentSelector::register_component_selector)
@@ -246,7 +310,6 @@ fn register_properties(app: &mut bevy::prelude::App) {
 /// app.register_component_selector::<MyFancyComponentSelector>("fancy-pants");
 /// # }
 /// ```
-
 pub trait RegisterComponentSelector {
     fn register_component_selector<T>(&mut self, name: &'static str) -> &mut Self
     where
@@ -269,6 +332,161 @@ impl RegisterComponentSelector for bevy::prelude::App {
     }
 }
 
+/// Utility trait which adds the [`register_pseudo_class`](RegisterPseudoClass::register_pseudo_class)
+/// function on [`App`](bevy::prelude::App) to add a new pseudo-class.
+///
+/// The pseudo-class watches a single component `T`, so only entities with it are passed to the predicate,
+/// alongside it's [`PseudoClassTicks`]. Whenever `T` changes or is removed, the style sheet is applied again.
+///
+/// Built-in pseudo-classes, like `hover` or `selected`, can't be overridden, so registering them
+/// only logs a warning.
+///
+/// # Examples
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecss::prelude::*;
+/// #
+/// # #[derive(Component)]
+/// # struct Health(f32);
+/// #
+/// # fn some_main() {
+/// #    let mut app = App::new();
+/// #    app.add_plugins(DefaultPlugins).add_plugins(EcssPlugin::default());
+/// // You may use it as selector now, like
+/// // .portrait:low-health {
+/// //      background-color: red;
+/// // }
+/// app.register_pseudo_class::<Health>("low-health", |entity, _| {
+///     entity.get::<Health>().is_some_and(|health| health.0 < 20.0)
+/// });
+/// # }
+/// ```
+pub trait RegisterPseudoClass {
+    fn register_pseudo_class<T>(
+        &mut self,
+        name: &'static str,
+        predicate: impl Fn(EntityRef, PseudoClassTicks) -> bool + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        T: Component;
+}
+
+impl RegisterPseudoClass for bevy::prelude::App {
+    fn register_pseudo_class<T>(
+        &mut self,
+        name: &'static str,
+        predicate: impl Fn(EntityRef, PseudoClassTicks) -> bool + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        T: Component,
+    {
+        if PseudoClassElement::is_built_in(name) {
+            warn!("Pseudo-class {name} is built-in and can't be registered");
+            return self;
+        }
+
+        let boxed_predicate = Box::new(PseudoClassPredicate::<T, _>::new(predicate));
+
+        self.world_mut()
+            .get_resource_or_insert_with::<PseudoClassRegistry>(bevy::utils::default)
+            .insert(name, boxed_predicate);
+
+        self
+    }
+}
+
+/// Utility trait which adds the [`register_media_feature`](RegisterMediaFeature::register_media_feature)
+/// function on [`App`](bevy::prelude::App) to add a new media feature, which can be used on `@media` queries.
+///
+/// The media feature value is read from a single resource `R`. Whenever `R` changes, all style sheets with
+/// a media query which result changed are applied again. If `R` doesn't exist, the feature never matches.
+///
+/// Feature names are case-insensitive, like on `@media` queries. Built-in media features, like `width`,
+/// can't be overridden, so registering them only logs a warning.
+///
+/// # Examples
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecss::prelude::*;
+/// #
+/// # #[derive(Resource)]
+/// # struct GameSettings { difficulty: f32, handheld: bool }
+/// #
+/// # #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// # enum GameState { #[default] Playing, Paused }
+/// #
+/// # fn some_main() {
+/// #    let mut app = App::new();
+/// #    app.add_plugins(DefaultPlugins).add_plugins(EcssPlugin::default());
+/// // You may use it on media queries now, like
+/// // @media (difficulty >= 2) and (platform: handheld) {
+/// //      #enemy-health { display: none; }
+/// // }
+/// app.register_media_feature::<GameSettings>("difficulty", |settings| settings.difficulty.into());
+/// app.register_media_feature::<GameSettings>("platform", |settings| {
+///     if settings.handheld { "handheld".into() } else { "desktop".into() }
+/// });
+///
+/// // @media (state: Paused) {
+/// //      #pause-menu { display: flex; }
+/// // }
+/// app.register_media_state::<GameState>();
+/// # }
+/// ```
+pub trait RegisterMediaFeature {
+    fn register_media_feature<R>(
+        &mut self,
+        name: &'static str,
+        value: impl Fn(&R) -> MediaValue + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        R: Resource;
+
+    /// Adds the current state `S` as a value of the `state` media feature, using it's [`Debug`](std::fmt::Debug)
+    /// representation, like `(state: Paused)`. Many state types can be registered, in which case `state`
+    /// matches if any of them does.
+    fn register_media_state<S>(&mut self) -> &mut Self
+    where
+        S: States;
+}
+
+impl RegisterMediaFeature for bevy::prelude::App {
+    fn register_media_feature<R>(
+        &mut self,
+        name: &'static str,
+        value: impl Fn(&R) -> MediaValue + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        R: Resource,
+    {
+        let name = name.to_ascii_lowercase();
+        if MediaContext::FEATURES.contains(&name.as_str()) {
+            warn!("Media feature {name} is built-in and can't be registered");
+            return self;
+        }
+
+        self.init_resource::<MediaFeatureValues>().add_systems(
+            DoEcss,
+            media::watch_media_feature(name, value)
+                .in_set(EcssSet::ChangeDetection)
+                .before(media::watch_media),
+        );
+
+        self
+    }
+
+    fn register_media_state<S>(&mut self) -> &mut Self
+    where
+        S: States,
+    {
+        self.register_media_feature::<State<S>>("state", |state| {
+            MediaValue::Ident(format!("{:?}", state.get()))
+        })
+    }
+}
+
 /// Utility trait which adds the [`register_property`](RegisterProperty::register_property) function
 /// on [`App`](bevy::prelude::App) to add a [`Property`] parser.
 ///
@@ -0,0 +1,1020 @@
+use std::any::TypeId;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{
+        Assets, Camera, DetectChanges, Entity, Handle, Parent, Query, Res, ResMut, Resource, With,
+    },
+    ui::TargetCamera,
+    utils::HashMap,
+    window::{PrimaryWindow, Window},
+};
+use cssparser::{ParseError, Parser, Token};
+use smallvec::{smallvec, SmallVec};
+
+use crate::{property::calc::absolute_length, EcssError, StyleSheet, StyleSheetAsset};
+
+/// Font size used to resolve `em` and `rem` units on media queries, which is always the initial font size.
+const MEDIA_FONT_SIZE: f32 = 16.0;
+
+/// The preferred color scheme of the user, used by `prefers-color-scheme` media feature.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// User preferences used to evaluate `@media` queries, like `@media (prefers-color-scheme: dark)`.
+///
+/// Whenever this resource is changed, all [`StyleSheet`] which have a media query with a different result
+/// are applied again.
+///
+/// # Examples
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecss::prelude::*;
+/// fn system(mut media: ResMut<EcssMedia>) {
+///     media.color_scheme = ColorScheme::Dark;
+///     media.reduced_motion = true;
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Resource)]
+pub struct EcssMedia {
+    /// Value of `prefers-color-scheme` media feature.
+    pub color_scheme: ColorScheme,
+    /// If `prefers-reduced-motion` media feature is `reduce`, otherwise it's `no-preference`.
+    pub reduced_motion: bool,
+}
+
+/// A media feature value, like `800px`, `16/9` or `portrait`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaValue {
+    /// A plain number, like `2`.
+    Number(f32),
+    /// A length, like `800px` or `50em`, converted to pixels.
+    Length(f32),
+    /// A ratio, like `16/9`, converted to a single number.
+    Ratio(f32),
+    /// A resolution, like `2dppx` or `192dpi`, converted to dots per pixel.
+    Resolution(f32),
+    /// A keyword, like `portrait` or `dark`.
+    Ident(String),
+}
+
+impl From<f32> for MediaValue {
+    fn from(value: f32) -> Self {
+        MediaValue::Number(value)
+    }
+}
+
+impl From<bool> for MediaValue {
+    fn from(value: bool) -> Self {
+        MediaValue::Number(if value { 1.0 } else { 0.0 })
+    }
+}
+
+impl From<&str> for MediaValue {
+    fn from(value: &str) -> Self {
+        MediaValue::Ident(value.to_string())
+    }
+}
+
+impl From<String> for MediaValue {
+    fn from(value: String) -> Self {
+        MediaValue::Ident(value)
+    }
+}
+
+impl MediaValue {
+    /// Compares this value with the given one, if both are numeric or both are keywords.
+    fn compare(&self, other: &MediaValue) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (MediaValue::Ident(a), MediaValue::Ident(b)) => a
+                .eq_ignore_ascii_case(b)
+                .then_some(std::cmp::Ordering::Equal),
+            (MediaValue::Ident(_), _) | (_, MediaValue::Ident(_)) => None,
+            (a, b) => a.to_f32().partial_cmp(&b.to_f32()),
+        }
+    }
+
+    fn to_f32(&self) -> f32 {
+        match self {
+            MediaValue::Number(v)
+            | MediaValue::Length(v)
+            | MediaValue::Ratio(v)
+            | MediaValue::Resolution(v) => *v,
+            MediaValue::Ident(_) => 0.0,
+        }
+    }
+
+    /// Checks if this value is considered `true` on a boolean context, like `(width)`.
+    fn is_truthy(&self) -> bool {
+        match self {
+            MediaValue::Ident(ident) => {
+                !ident.eq_ignore_ascii_case("none") && !ident.eq_ignore_ascii_case("no-preference")
+            }
+            value => value.to_f32() != 0.0,
+        }
+    }
+}
+
+/// How a media feature is compared against a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaComparison {
+    /// `(width: 800px)` or `(width = 800px)`
+    Eq,
+    /// `(width < 800px)`
+    Lt,
+    /// `(max-width: 800px)` or `(width <= 800px)`
+    Le,
+    /// `(width > 800px)`
+    Gt,
+    /// `(min-width: 800px)` or `(width >= 800px)`
+    Ge,
+}
+
+impl MediaComparison {
+    /// The comparison of the same values, but with swapped sides, like `800px < width` is `width > 800px`.
+    fn flip(self) -> Self {
+        match self {
+            MediaComparison::Eq => MediaComparison::Eq,
+            MediaComparison::Lt => MediaComparison::Gt,
+            MediaComparison::Le => MediaComparison::Ge,
+            MediaComparison::Gt => MediaComparison::Lt,
+            MediaComparison::Ge => MediaComparison::Le,
+        }
+    }
+
+    fn matches(self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self {
+            MediaComparison::Eq => ordering == Equal,
+            MediaComparison::Lt => ordering == Less,
+            MediaComparison::Le => ordering != Greater,
+            MediaComparison::Gt => ordering == Greater,
+            MediaComparison::Ge => ordering != Less,
+        }
+    }
+}
+
+/// A single media feature test, like `(min-width: 800px)` or `(400px < width <= 800px)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaFeature {
+    /// Feature name, without `min-` or `max-` prefixes, like `width`.
+    pub name: String,
+    /// Comparisons which the feature value must satisfy, with the feature on left side.
+    /// When empty, the feature is evaluated on a boolean context, like `(orientation)`.
+    pub comparisons: SmallVec<[(MediaComparison, MediaValue); 2]>,
+}
+
+impl MediaFeature {
+    fn matches(&self, context: &impl FeatureContext) -> bool {
+        context.any_value(&self.name, |value| self.matches_value(value))
+    }
+
+    fn matches_value(&self, value: &MediaValue) -> bool {
+        if self.comparisons.is_empty() {
+            return value.is_truthy();
+        }
+
+        self.comparisons.iter().all(|(comparison, other)| {
+            value
+                .compare(other)
+                .is_some_and(|ordering| comparison.matches(ordering))
+        })
+    }
+}
+
+/// Provides the values of features evaluated by a [`MediaCondition`], like the viewport on `@media` rules.
+pub(crate) trait FeatureContext {
+    /// Checks if any value of the given feature matches the predicate. Unknown features never matches.
+    fn any_value(&self, name: &str, predicate: impl Fn(&MediaValue) -> bool) -> bool;
+}
+
+/// A media condition, like `(min-width: 800px) and (orientation: portrait)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+}
+
+impl MediaCondition {
+    /// Evaluates this condition against the given media or container context.
+    pub(crate) fn matches(&self, context: &impl FeatureContext) -> bool {
+        match self {
+            MediaCondition::Feature(feature) => feature.matches(context),
+            MediaCondition::Not(condition) => !condition.matches(context),
+            MediaCondition::And(conditions) => conditions.iter().all(|c| c.matches(context)),
+            MediaCondition::Or(conditions) => conditions.iter().any(|c| c.matches(context)),
+        }
+    }
+}
+
+/// A single media query, like `screen and (min-width: 800px)` or `not (orientation: portrait)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    /// If the query was prefixed by `not`.
+    pub negated: bool,
+    /// The media type, like `screen`, if any. Only `all` and `screen` media types are matched.
+    pub media_type: Option<String>,
+    /// The media condition, if any.
+    pub condition: Option<MediaCondition>,
+}
+
+impl MediaQuery {
+    /// A query which never matches, used in place of invalid queries, like `not all`.
+    fn never() -> Self {
+        Self {
+            negated: true,
+            media_type: Some("all".to_string()),
+            condition: None,
+        }
+    }
+
+    fn matches(&self, context: &MediaContext) -> bool {
+        let type_matches = self.media_type.as_ref().is_none_or(|media_type| {
+            media_type.eq_ignore_ascii_case("all") || media_type.eq_ignore_ascii_case("screen")
+        });
+
+        let matches = type_matches
+            && self
+                .condition
+                .as_ref()
+                .is_none_or(|condition| condition.matches(context));
+
+        matches != self.negated
+    }
+}
+
+/// A comma separated list of media queries, like `(orientation: portrait), (max-width: 600px)`,
+/// which matches if any query matches. An empty list always matches.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaQueryList(pub Vec<MediaQuery>);
+
+impl MediaQueryList {
+    /// Evaluates this list against the given [`MediaContext`].
+    pub fn matches(&self, context: &MediaContext) -> bool {
+        self.0.is_empty() || self.0.iter().any(|query| query.matches(context))
+    }
+}
+
+/// Values used to evaluate media queries, like the viewport size.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaContext {
+    /// Logical viewport width.
+    pub width: f32,
+    /// Logical viewport height.
+    pub height: f32,
+    /// Number of physical pixels per logical pixel.
+    pub scale_factor: f32,
+    /// User preferences.
+    pub preferences: EcssMedia,
+    /// Values of app-defined media features.
+    pub(crate) custom: MediaFeatureValues,
+}
+
+impl MediaContext {
+    /// Names of all built-in media features, which can't be overridden by app-defined ones.
+    pub(crate) const FEATURES: [&'static str; 7] = [
+        "width",
+        "height",
+        "aspect-ratio",
+        "orientation",
+        "resolution",
+        "prefers-color-scheme",
+        "prefers-reduced-motion",
+    ];
+
+    /// Returns the value of the given built-in media feature, if it's supported.
+    pub fn feature(&self, name: &str) -> Option<MediaValue> {
+        let value = match name {
+            "width" => MediaValue::Length(self.width),
+            "height" => MediaValue::Length(self.height),
+            "aspect-ratio" if self.height > 0.0 => MediaValue::Ratio(self.width / self.height),
+            "orientation" if self.height >= self.width => MediaValue::Ident("portrait".into()),
+            "orientation" => MediaValue::Ident("landscape".into()),
+            "resolution" => MediaValue::Resolution(self.scale_factor),
+            "prefers-color-scheme" => match self.preferences.color_scheme {
+                ColorScheme::Light => MediaValue::Ident("light".into()),
+                ColorScheme::Dark => MediaValue::Ident("dark".into()),
+            },
+            "prefers-reduced-motion" if self.preferences.reduced_motion => {
+                MediaValue::Ident("reduce".into())
+            }
+            "prefers-reduced-motion" => MediaValue::Ident("no-preference".into()),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+impl FeatureContext for MediaContext {
+    /// Checks the built-in feature value, or all app-defined ones if it isn't a built-in feature.
+    fn any_value(&self, name: &str, predicate: impl Fn(&MediaValue) -> bool) -> bool {
+        match self.feature(name) {
+            Some(value) => predicate(&value),
+            None => self.custom.values(name).any(predicate),
+        }
+    }
+}
+
+/// Parameters used to build the [`MediaContext`] of each [`StyleSheet`] root.
+#[derive(SystemParam)]
+pub(crate) struct MediaParam<'w, 's> {
+    preferences: Option<Res<'w, EcssMedia>>,
+    custom: Option<Res<'w, MediaFeatureValues>>,
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, &'static Camera>,
+    target_cameras: Query<'w, 's, &'static TargetCamera>,
+    parents: Query<'w, 's, &'static Parent>,
+}
+
+impl MediaParam<'_, '_> {
+    /// Builds the [`MediaContext`] of the given entity, using the viewport of the [`TargetCamera`] of
+    /// it's nearest ancestor, or the primary window if there is none.
+    pub(crate) fn context(&self, entity: Entity) -> MediaContext {
+        let preferences = self.preferences.as_deref().cloned().unwrap_or_default();
+        let custom = self.custom.as_deref().cloned().unwrap_or_default();
+
+        let mut ancestor = Some(entity);
+        while let Some(entity) = ancestor {
+            if let Ok(target) = self.target_cameras.get(entity) {
+                if let Ok(camera) = self.cameras.get(target.entity()) {
+                    let size = camera.logical_viewport_size().unwrap_or_default();
+                    return MediaContext {
+                        width: size.x,
+                        height: size.y,
+                        scale_factor: camera.target_scaling_factor().unwrap_or(1.0),
+                        preferences,
+                        custom,
+                    };
+                }
+            }
+            ancestor = self.parents.get(entity).ok().map(Parent::get);
+        }
+
+        match self.windows.get_single() {
+            Ok(window) => MediaContext {
+                width: window.width(),
+                height: window.height(),
+                scale_factor: window.scale_factor(),
+                preferences,
+                custom,
+            },
+            Err(_) => MediaContext {
+                scale_factor: 1.0,
+                preferences,
+                custom,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Values of app-defined media features, registered with [`RegisterMediaFeature`](crate::RegisterMediaFeature),
+/// keyed by feature name and the resource type it's value comes from.
+///
+/// Many resources may provide values to the same feature, like `state` for each [`States`](bevy::prelude::States) type,
+/// in which case the feature matches if any value does.
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub(crate) struct MediaFeatureValues(HashMap<(String, TypeId), MediaValue>);
+
+impl MediaFeatureValues {
+    /// Returns all values of the given feature.
+    fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a MediaValue> + 'a {
+        self.0
+            .iter()
+            .filter(move |((feature, _), _)| *feature == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Builds a system which updates the value of an app-defined media feature whenever the resource `R` changes.
+///
+/// The feature is removed when `R` doesn't exist, so it never matches.
+pub(crate) fn watch_media_feature<R, F>(
+    name: String,
+    value: F,
+) -> impl FnMut(Option<Res<R>>, ResMut<MediaFeatureValues>) + Send + Sync + 'static
+where
+    R: Resource,
+    F: Fn(&R) -> MediaValue + Send + Sync + 'static,
+{
+    let key = (name, TypeId::of::<R>());
+    move |resource, mut values| match resource {
+        Some(resource) if resource.is_changed() => {
+            values.0.insert(key.clone(), value(&resource));
+        }
+        Some(_) => (),
+        None => {
+            if values.0.contains_key(&key) {
+                values.0.remove(&key);
+            }
+        }
+    }
+}
+
+/// Results of all media queries of each [`StyleSheet`] root, on the last time it was applied,
+/// in the same order as [`evaluate_media`] yields them.
+#[derive(Debug, Default, Resource)]
+pub(crate) struct MediaQueryState(pub HashMap<Entity, SmallVec<[bool; 8]>>);
+
+/// Evaluates every rule with media queries on the given sheets, in order.
+pub(crate) fn evaluate_media(
+    context: &MediaContext,
+    handles: &[Handle<StyleSheetAsset>],
+    assets: &Assets<StyleSheetAsset>,
+) -> SmallVec<[bool; 8]> {
+    handles
+        .iter()
+        .filter_map(|handle| assets.get(handle))
+        .flat_map(|sheet| sheet.iter())
+        .filter(|rule| !rule.media.is_empty())
+        .map(|rule| rule.media.iter().all(|media| media.matches(context)))
+        .collect()
+}
+
+/// Applies again all [`StyleSheet`] which have any media query which result changed since the last
+/// time it was applied, like when the window is resized past a breakpoint.
+pub(crate) fn watch_media(
+    media: MediaParam,
+    assets: Res<Assets<StyleSheetAsset>>,
+    mut state: ResMut<MediaQueryState>,
+    mut q_sheets: Query<(Entity, &mut StyleSheet)>,
+) {
+    state.0.retain(|entity, _| q_sheets.contains(*entity));
+
+    for (entity, mut sheet) in q_sheets.iter_mut() {
+        let Some(previous) = state.0.get(&entity) else {
+            continue;
+        };
+
+        let current = evaluate_media(&media.context(entity), sheet.handles(), &assets);
+        if &current != previous {
+            bevy::log::debug!("Refreshing sheet {:?} due to changed media queries", sheet);
+            state.0.remove(&entity);
+            sheet.refresh();
+        }
+    }
+}
+
+/// Parses a media query list, like `screen and (min-width: 800px), (orientation: portrait)`.
+///
+/// Invalid queries never matches, but they doesn't invalidate the whole list.
+pub(crate) fn parse_media_query_list<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaQueryList, ParseError<'i, EcssError>> {
+    if input.is_exhausted() {
+        return Ok(MediaQueryList::default());
+    }
+
+    let queries = input.parse_comma_separated(|input| {
+        Ok(input.try_parse(parse_media_query).unwrap_or_else(|_| {
+            while input.next().is_ok() {}
+            MediaQuery::never()
+        }))
+    })?;
+
+    Ok(MediaQueryList(queries))
+}
+
+/// Parses a single media query, like `not screen and (min-width: 800px)` or `(orientation: portrait)`.
+fn parse_media_query<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaQuery, ParseError<'i, EcssError>> {
+    if let Ok(condition) = input.try_parse(parse_media_condition) {
+        input.expect_exhausted()?;
+        return Ok(MediaQuery {
+            negated: false,
+            media_type: None,
+            condition: Some(condition),
+        });
+    }
+
+    let negated = input
+        .try_parse(|input| input.expect_ident_matching("not"))
+        .is_ok();
+    if !negated {
+        let _ = input.try_parse(|input| input.expect_ident_matching("only"));
+    }
+
+    let media_type = input.expect_ident()?.to_string();
+    if ["not", "only", "and", "or"]
+        .iter()
+        .any(|keyword| media_type.eq_ignore_ascii_case(keyword))
+    {
+        return Err(input.new_custom_error(EcssError::UnexpectedToken(media_type)));
+    }
+
+    let condition = if input
+        .try_parse(|input| input.expect_ident_matching("and"))
+        .is_ok()
+    {
+        // `or` isn't allowed after a media type, so it must be a chain of `and`.
+        let mut conditions = vec![parse_media_in_parens(input)?];
+        while input
+            .try_parse(|input| input.expect_ident_matching("and"))
+            .is_ok()
+        {
+            conditions.push(parse_media_in_parens(input)?);
+        }
+        Some(single_or(conditions, MediaCondition::And))
+    } else {
+        None
+    };
+
+    input.expect_exhausted()?;
+
+    Ok(MediaQuery {
+        negated,
+        media_type: Some(media_type),
+        condition,
+    })
+}
+
+/// Parses a media condition, like `not (a)`, `(a) and (b)` or `(a) or (b)`.
+///
+/// `and` and `or` can't be mixed on the same level without parenthesis.
+pub(crate) fn parse_media_condition<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaCondition, ParseError<'i, EcssError>> {
+    if input
+        .try_parse(|input| input.expect_ident_matching("not"))
+        .is_ok()
+    {
+        let condition = parse_media_in_parens(input)?;
+        return Ok(MediaCondition::Not(Box::new(condition)));
+    }
+
+    let first = parse_media_in_parens(input)?;
+    let location = input.current_source_location();
+    let operator = match input.try_parse(|input| input.expect_ident_cloned()) {
+        Ok(operator) if operator.eq_ignore_ascii_case("and") => "and",
+        Ok(operator) if operator.eq_ignore_ascii_case("or") => "or",
+        Ok(operator) => {
+            return Err(location.new_custom_error(EcssError::UnexpectedToken(operator.to_string())))
+        }
+        Err(_) => return Ok(first),
+    };
+
+    let mut conditions = vec![first, parse_media_in_parens(input)?];
+    while input
+        .try_parse(|input| input.expect_ident_matching(operator))
+        .is_ok()
+    {
+        conditions.push(parse_media_in_parens(input)?);
+    }
+
+    Ok(if operator == "and" {
+        MediaCondition::And(conditions)
+    } else {
+        MediaCondition::Or(conditions)
+    })
+}
+
+/// Wraps many conditions using the given variant, unless there is a single one.
+fn single_or(
+    mut conditions: Vec<MediaCondition>,
+    variant: fn(Vec<MediaCondition>) -> MediaCondition,
+) -> MediaCondition {
+    if conditions.len() == 1 {
+        conditions.remove(0)
+    } else {
+        variant(conditions)
+    }
+}
+
+/// Parses a media condition or feature inside parenthesis, like `((a) or (b))` or `(min-width: 800px)`.
+fn parse_media_in_parens<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaCondition, ParseError<'i, EcssError>> {
+    input.expect_parenthesis_block()?;
+    input.parse_nested_block(|input| {
+        if let Ok(condition) = input.try_parse(parse_media_condition) {
+            return Ok(condition);
+        }
+
+        parse_media_feature(input).map(MediaCondition::Feature)
+    })
+}
+
+/// Parses a media feature, without the parenthesis, like `min-width: 800px`, `width >= 800px`,
+/// `400px < width < 800px` or `orientation`.
+fn parse_media_feature<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaFeature, ParseError<'i, EcssError>> {
+    // Range syntax starting with a value, like `400px < width`.
+    if let Ok((value, comparison, name)) = input.try_parse(|input| {
+        let value = parse_media_value(input)?;
+        let comparison = parse_media_comparison(input)?;
+        let name = input.expect_ident()?.to_ascii_lowercase();
+        Ok::<_, ParseError<'i, EcssError>>((value, comparison, name))
+    }) {
+        let mut comparisons: SmallVec<[_; 2]> = smallvec![(comparison.flip(), value)];
+
+        if let Ok(second) = input.try_parse(parse_media_comparison) {
+            // Only `a < name < b` or `a > name > b` are allowed.
+            let same_direction = matches!(
+                (comparison, second),
+                (
+                    MediaComparison::Lt | MediaComparison::Le,
+                    MediaComparison::Lt | MediaComparison::Le
+                ) | (
+                    MediaComparison::Gt | MediaComparison::Ge,
+                    MediaComparison::Gt | MediaComparison::Ge
+                )
+            );
+            if !same_direction {
+                return Err(
+                    input.new_custom_error(EcssError::UnexpectedToken(format!("{second:?}")))
+                );
+            }
+            comparisons.push((second, parse_media_value(input)?));
+        }
+
+        input.expect_exhausted()?;
+        return Ok(MediaFeature { name, comparisons });
+    }
+
+    let name = input.expect_ident()?.to_ascii_lowercase();
+
+    if input.is_exhausted() {
+        return Ok(MediaFeature {
+            name,
+            comparisons: smallvec![],
+        });
+    }
+
+    // Plain syntax, like `min-width: 800px`.
+    if input.try_parse(|input| input.expect_colon()).is_ok() {
+        let value = parse_media_value(input)?;
+        input.expect_exhausted()?;
+
+        let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+            (MediaComparison::Ge, name.to_string())
+        } else if let Some(name) = name.strip_prefix("max-") {
+            (MediaComparison::Le, name.to_string())
+        } else {
+            (MediaComparison::Eq, name)
+        };
+
+        return Ok(MediaFeature {
+            name,
+            comparisons: smallvec![(comparison, value)],
+        });
+    }
+
+    // Range syntax, like `width >= 800px`.
+    let comparison = parse_media_comparison(input)?;
+    let value = parse_media_value(input)?;
+    input.expect_exhausted()?;
+
+    Ok(MediaFeature {
+        name,
+        comparisons: smallvec![(comparison, value)],
+    })
+}
+
+/// Parses a range comparison operator, like `<` or `>=`.
+fn parse_media_comparison<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaComparison, ParseError<'i, EcssError>> {
+    let location = input.current_source_location();
+    let comparison = match input.next()? {
+        Token::Delim('=') => return Ok(MediaComparison::Eq),
+        Token::Delim('<') => MediaComparison::Lt,
+        Token::Delim('>') => MediaComparison::Gt,
+        token => return Err(location.new_unexpected_token_error(token.clone())),
+    };
+
+    // `<=` and `>=` must not have any white space between the characters.
+    let or_equal = input
+        .try_parse(|input| match input.next_including_whitespace()? {
+            Token::Delim('=') => Ok(()),
+            token => {
+                let token = token.clone();
+                Err(input.new_unexpected_token_error::<EcssError>(token))
+            }
+        })
+        .is_ok();
+
+    Ok(match (comparison, or_equal) {
+        (MediaComparison::Lt, true) => MediaComparison::Le,
+        (MediaComparison::Gt, true) => MediaComparison::Ge,
+        (comparison, _) => comparison,
+    })
+}
+
+/// Parses a media feature value, like `800px`, `16/9`, `2dppx` or `portrait`.
+pub(crate) fn parse_media_value<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<MediaValue, ParseError<'i, EcssError>> {
+    let location = input.current_source_location();
+    match input.next()?.clone() {
+        Token::Number { value, .. } => {
+            let denominator = input.try_parse(|input| {
+                input.expect_delim('/')?;
+                input.expect_number()
+            });
+
+            Ok(match denominator {
+                Ok(denominator) if denominator != 0.0 => MediaValue::Ratio(value / denominator),
+                Ok(_) => MediaValue::Ratio(f32::INFINITY),
+                Err(_) => MediaValue::Number(value),
+            })
+        }
+        Token::Dimension { value, unit, .. } => {
+            let value = match unit.to_ascii_lowercase().as_str() {
+                "em" | "rem" => MediaValue::Length(value * MEDIA_FONT_SIZE),
+                "dppx" | "x" => MediaValue::Resolution(value),
+                "dpi" => MediaValue::Resolution(value / 96.0),
+                "dpcm" => MediaValue::Resolution(value * 2.54 / 96.0),
+                unit => match absolute_length(value, unit) {
+                    Some(length) => MediaValue::Length(length),
+                    None => {
+                        return Err(
+                            location.new_custom_error(EcssError::UnexpectedToken(unit.to_string()))
+                        )
+                    }
+                },
+            };
+            Ok(value)
+        }
+        Token::Ident(ident) => Ok(MediaValue::Ident(ident.to_string())),
+        token => Err(location.new_unexpected_token_error(token)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cssparser::ParserInput;
+
+    use super::*;
+
+    fn parse(query: &str) -> MediaQueryList {
+        let mut input = ParserInput::new(query);
+        let mut parser = Parser::new(&mut input);
+        parse_media_query_list(&mut parser).expect("Should be a valid media query list")
+    }
+
+    fn context(width: f32, height: f32) -> MediaContext {
+        MediaContext {
+            width,
+            height,
+            scale_factor: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_media_features() {
+        let feature = |name: &str, comparisons: &[(MediaComparison, MediaValue)]| {
+            Some(MediaCondition::Feature(MediaFeature {
+                name: name.to_string(),
+                comparisons: comparisons.iter().cloned().collect(),
+            }))
+        };
+
+        use MediaComparison::*;
+        use MediaValue::*;
+
+        let cases = [
+            (
+                "(min-width: 800px)",
+                feature("width", &[(Ge, Length(800.0))]),
+            ),
+            (
+                "(max-height: 2in)",
+                feature("height", &[(Le, Length(192.0))]),
+            ),
+            ("(width >= 50em)", feature("width", &[(Ge, Length(800.0))])),
+            ("(800px < width)", feature("width", &[(Gt, Length(800.0))])),
+            (
+                "(400px <= width < 800px)",
+                feature("width", &[(Ge, Length(400.0)), (Lt, Length(800.0))]),
+            ),
+            (
+                "(aspect-ratio: 16/9)",
+                feature("aspect-ratio", &[(Eq, Ratio(16.0 / 9.0))]),
+            ),
+            (
+                "(min-resolution: 192dpi)",
+                feature("resolution", &[(Ge, Resolution(2.0))]),
+            ),
+            (
+                "(orientation: portrait)",
+                feature("orientation", &[(Eq, Ident("portrait".to_string()))]),
+            ),
+            (
+                "(prefers-reduced-motion)",
+                feature("prefers-reduced-motion", &[]),
+            ),
+        ];
+
+        for (query, expected) in cases {
+            let list = parse(query);
+            assert_eq!(list.0.len(), 1, "{query}");
+            assert_eq!(list.0[0].condition, expected, "{query}");
+        }
+
+        let list = parse("not screen and (min-width: 800px) and (orientation: portrait), print");
+        assert_eq!(list.0.len(), 2);
+        assert!(list.0[0].negated);
+        assert_eq!(list.0[0].media_type.as_deref(), Some("screen"));
+        assert!(matches!(&list.0[0].condition, Some(MediaCondition::And(c)) if c.len() == 2));
+
+        let list =
+            parse("((min-width: 800px) or (orientation: portrait)) and (not (width > 1200px))");
+        assert!(matches!(&list.0[0].condition, Some(MediaCondition::And(c)) if c.len() == 2));
+
+        for invalid in [
+            "(min-width: 800px) and (orientation: portrait) or (width)",
+            "(width < 800px > 400px)",
+            "(width: 10foo)",
+            "screen or (width)",
+        ] {
+            assert_eq!(parse(invalid).0, vec![MediaQuery::never()], "{invalid}");
+        }
+    }
+
+    #[test]
+    fn evaluate_media_queries() {
+        let matches = |query: &str, context: &MediaContext| parse(query).matches(context);
+
+        let desktop = context(1280.0, 720.0);
+        let handheld = context(720.0, 1280.0);
+
+        assert!(matches("", &desktop));
+        assert!(matches("(min-width: 800px)", &desktop));
+        assert!(!matches("(min-width: 800px)", &handheld));
+        assert!(matches("(orientation: portrait)", &handheld));
+        assert!(matches("screen and (orientation: landscape)", &desktop));
+        assert!(!matches("print", &desktop));
+        assert!(matches("not print", &desktop));
+        assert!(matches("(400px < width <= 720px)", &handheld));
+        assert!(matches("(min-aspect-ratio: 16/9)", &desktop));
+        assert!(matches(
+            "(width > 1000px), (orientation: portrait)",
+            &handheld
+        ));
+        assert!(matches(
+            "not ((width > 1000px) or (height < 800px))",
+            &handheld
+        ));
+        assert!(!matches("(unknown-feature)", &desktop));
+        assert!(!matches("(width: portrait)", &desktop));
+
+        let mut hidpi = context(1280.0, 720.0);
+        hidpi.scale_factor = 2.0;
+        hidpi.preferences = EcssMedia {
+            color_scheme: ColorScheme::Dark,
+            reduced_motion: true,
+        };
+        assert!(matches("(min-resolution: 2dppx)", &hidpi));
+        assert!(!matches("(min-resolution: 2x)", &desktop));
+        assert!(matches("(prefers-color-scheme: dark)", &hidpi));
+        assert!(!matches("(prefers-color-scheme: dark)", &desktop));
+        assert!(matches("(prefers-reduced-motion)", &hidpi));
+        assert!(!matches("(prefers-reduced-motion)", &desktop));
+        assert!(matches("(prefers-reduced-motion: no-preference)", &desktop));
+    }
+
+    #[test]
+    fn evaluate_app_defined_features() {
+        let mut context = context(1280.0, 720.0);
+        context
+            .custom
+            .0
+            .insert(("state".to_string(), TypeId::of::<u8>()), "Paused".into());
+        context
+            .custom
+            .0
+            .insert(("state".to_string(), TypeId::of::<u16>()), "InGame".into());
+        context
+            .custom
+            .0
+            .insert(("difficulty".to_string(), TypeId::of::<u8>()), 2.0.into());
+        context
+            .custom
+            .0
+            .insert(("width".to_string(), TypeId::of::<u8>()), 0.0.into());
+
+        let matches = |query: &str| parse(query).matches(&context);
+        assert!(matches("(state: paused)"));
+        assert!(matches("(state: InGame) and (not (state: MainMenu))"));
+        assert!(matches("(difficulty >= 2)"));
+        assert!(!matches("(max-difficulty: 1)"));
+        assert!(matches("(difficulty)"));
+        assert!(!matches("(platform)"));
+        assert!(
+            matches("(min-width: 800px)"),
+            "Built-in features can't be overridden"
+        );
+    }
+
+    #[test]
+    fn apply_app_defined_features() {
+        use bevy::{prelude::*, state::app::StatesPlugin};
+
+        use crate::RegisterMediaFeature;
+
+        #[derive(Resource)]
+        struct GameSettings {
+            handheld: bool,
+        }
+
+        #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+        enum GameState {
+            #[default]
+            Playing,
+            Paused,
+        }
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StatesPlugin,
+            crate::EcssPlugin,
+        ))
+        .init_state::<GameState>()
+        .register_media_state::<GameState>()
+        .register_media_feature::<GameSettings>("Platform", |settings| {
+            if settings.handheld {
+                "handheld"
+            } else {
+                "desktop"
+            }
+            .into()
+        })
+        .register_media_feature::<GameSettings>("width", |_| 0.0.into());
+
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #menu { display: none; width: 100px; }
+                @media (state: Paused) {
+                    #menu { display: flex; }
+                }
+                @media (platform: handheld) {
+                    #menu { width: 200px; }
+                }
+                "#,
+            ));
+        let menu = app
+            .world_mut()
+            .spawn((
+                NodeBundle::default(),
+                Name::new("menu"),
+                StyleSheet::new(handle),
+            ))
+            .id();
+
+        let style = |app: &mut App| {
+            app.update();
+            app.update();
+            let style = app.world().get::<Style>(menu).unwrap();
+            (style.display, style.width)
+        };
+
+        assert_eq!(style(&mut app), (Display::None, Val::Px(100.0)));
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Paused);
+        assert_eq!(style(&mut app), (Display::Flex, Val::Px(100.0)));
+
+        app.world_mut()
+            .insert_resource(GameSettings { handheld: true });
+        assert_eq!(
+            style(&mut app),
+            (Display::Flex, Val::Px(200.0)),
+            "Registered names should be case-insensitive"
+        );
+        assert!(
+            app.world()
+                .resource::<MediaFeatureValues>()
+                .values("width")
+                .next()
+                .is_none(),
+            "Built-in features can't be registered"
+        );
+
+        app.world_mut().resource_mut::<GameSettings>().handheld = false;
+        assert_eq!(style(&mut app), (Display::Flex, Val::Px(100.0)));
+
+        app.world_mut().resource_mut::<GameSettings>().handheld = true;
+        app.world_mut().remove_resource::<GameSettings>();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        assert_eq!(style(&mut app), (Display::None, Val::Px(100.0)));
+    }
+}
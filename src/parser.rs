@@ -1,28 +1,89 @@
-use bevy::log::prelude::error;
+use bevy::{log::prelude::error, utils::HashMap};
 use cssparser::{
-    AtRuleParser, CowRcStr, DeclarationParser, ParseError, Parser, ParserInput,
-    QualifiedRuleParser, RuleBodyItemParser, RuleBodyParser, ToCss, Token,
+    AtRuleParser, BasicParseErrorKind, CowRcStr, DeclarationParser, Delimiter, ParseError,
+    ParseErrorKind, Parser, ParserInput, QualifiedRuleParser, RuleBodyItemParser, RuleBodyParser,
+    ToCss, Token,
 };
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    property::PropertyValues,
-    selector::{Selector, SelectorElement},
+    media::{parse_media_condition, parse_media_query_list, MediaCondition, MediaQueryList},
+    property::{
+        calc::{absolute_length, CalcExpr, CalcUnit},
+        PropertyToken, PropertyValues,
+    },
+    selector::{AttributeElement, AttributeMatcher, PseudoClassElement, Selector, SelectorElement},
     stylesheet::StyleRule,
     EcssError,
 };
 
 /// Parses a `css` string using [`RuleListParser`].
-pub(crate) struct StyleSheetParser;
+#[derive(Default)]
+pub(crate) struct StyleSheetParser {
+    /// Full name of the cascade layer being parsed, like `widgets.buttons`.
+    layer: Option<String>,
+    /// Full name of all declared cascade layers, on the order they were first declared.
+    layers: Vec<String>,
+    /// Number of anonymous layers, used to give each one an unique name.
+    anonymous_layers: usize,
+    /// Media query lists of all `@media` blocks being parsed, which must all match.
+    media: Vec<MediaQueryList>,
+    /// Conditions of all `@container` blocks being parsed, which must all match.
+    containers: Vec<MediaCondition>,
+    /// All `@import` rules, which are only allowed before any other rule.
+    imports: Vec<ImportRule>,
+    /// If any rule other than `@import` or `@layer` statements was parsed.
+    any_rule: bool,
+}
+
+/// A `@import` rule, like `@import "base.css" layer(base) (min-width: 800px);`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ImportRule {
+    /// Path of the imported sheet, relative to the importing one.
+    pub path: String,
+    /// Full name of the cascade layer which the imported rules are put into, if any.
+    pub layer: Option<String>,
+    /// Media query list which the imported rules must match.
+    pub media: MediaQueryList,
+    /// Number of layers declared before this import, which is where the imported layers are declared.
+    pub layers_before: usize,
+}
+
+/// The result of parsing a `css` string.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedSheet {
+    pub rules: SmallVec<[StyleRule; 8]>,
+    /// Full name of all declared cascade layers, on the order they were first declared.
+    pub layers: Vec<String>,
+    pub imports: Vec<ImportRule>,
+}
 
 impl StyleSheetParser {
+    #[cfg(test)]
     pub(crate) fn parse(content: &str) -> SmallVec<[StyleRule; 8]> {
+        Self::parse_sheet(content).rules
+    }
+
+    /// Parses a `css` string, returning it's rules, cascade layers and imports.
+    pub(crate) fn parse_sheet(content: &str) -> ParsedSheet {
         let mut input = ParserInput::new(content);
         let mut parser = Parser::new(&mut input);
 
-        cssparser::StyleSheetParser::new(&mut parser, &mut StyleSheetParser)
+        let mut sheet_parser = StyleSheetParser::default();
+        let rules = sheet_parser.parse_rules(&mut parser);
+
+        ParsedSheet {
+            rules,
+            layers: sheet_parser.layers,
+            imports: sheet_parser.imports,
+        }
+    }
+
+    /// Parses a list of rules, either on top level or inside a `@layer` block.
+    fn parse_rules<'i>(&mut self, parser: &mut Parser<'i, '_>) -> SmallVec<[StyleRule; 8]> {
+        cssparser::StyleSheetParser::new(parser, self)
             .filter_map(|result| match result {
-                Ok(rule) => Some(rule),
+                Ok(rules) => Some(rules),
                 Err((err, rule)) => {
                     error!(
                         "Failed to parse rule: {}. Error: {}",
@@ -32,8 +93,26 @@ impl StyleSheetParser {
                     None
                 }
             })
+            .flatten()
             .collect()
     }
+
+    /// Declares the given layer, relative to the current one, and all it's parent layers, if they
+    /// weren't declared yet. Returns the layer full name.
+    fn declare_layer(&mut self, name: &str) -> String {
+        let mut full_name = self.layer.clone().unwrap_or_default();
+        for segment in name.split('.') {
+            if !full_name.is_empty() {
+                full_name.push('.');
+            }
+            full_name.push_str(segment);
+
+            if !self.layers.contains(&full_name) {
+                self.layers.push(full_name.clone());
+            }
+        }
+        full_name
+    }
 }
 
 fn format_error(error: ParseError<EcssError>) -> String {
@@ -70,63 +149,170 @@ enum NextElementWithPrefix {
 }
 
 impl<'i> QualifiedRuleParser<'i> for StyleSheetParser {
-    type Prelude = Selector;
-    type QualifiedRule = StyleRule;
+    type Prelude = SmallVec<[Selector; 4]>;
+    type QualifiedRule = SmallVec<[StyleRule; 4]>;
     type Error = EcssError;
 
     fn parse_prelude<'t>(
         &mut self,
         input: &mut Parser<'i, 't>,
     ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
-        let mut elements = smallvec![];
-
-        let mut next_element_with_prefix = NextElementWithPrefix::None;
-
-        while let Ok(token) = input.next_including_whitespace() {
-            use cssparser::Token::*;
-            match token {
-                Ident(v) => {
-                    match next_element_with_prefix {
-                        NextElementWithPrefix::None => {
-                            elements.push(SelectorElement::Component(v.to_string()))
-                        }
-                        NextElementWithPrefix::Class => {
-                            elements.push(SelectorElement::Class(v.to_string()))
-                        }
-                        NextElementWithPrefix::PseudoClass => {
-                            elements.push(SelectorElement::PseudoClass(v.into()))
-                        }
-                    }
-                    next_element_with_prefix = NextElementWithPrefix::None;
-                }
-                IDHash(v) => {
-                    if v.is_empty() {
-                        return Err(input.new_custom_error(EcssError::InvalidSelector));
-                    } else {
-                        elements.push(SelectorElement::Name(v.to_string()));
+        // A selector list, like `a, .b, #c`, is valid only if all selectors on it are valid.
+        Ok(input
+            .parse_comma_separated(parse_selector)?
+            .into_iter()
+            .collect())
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::Prelude,
+        _start: &cssparser::ParserState,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
+        self.any_rule = true;
+        let mut properties = HashMap::<String, PropertyValues>::default();
+
+        for property in RuleBodyParser::new(input, &mut PropertyParser) {
+            match property {
+                Ok((name, property)) => {
+                    // A normal declaration never overrides an important one on the same block.
+                    if !property.is_important()
+                        && properties
+                            .get(&name)
+                            .is_some_and(PropertyValues::is_important)
+                    {
+                        continue;
                     }
+                    properties.insert(name, property);
                 }
-                WhiteSpace(_) => elements.push(SelectorElement::Child),
-                Delim(c) if *c == '.' => next_element_with_prefix = NextElementWithPrefix::Class,
-                Delim(c) if *c == '*' => elements.push(SelectorElement::Any),
-                Colon => next_element_with_prefix = NextElementWithPrefix::PseudoClass,
-                _ => {
-                    let token = token.to_css_string();
-                    return Err(input.new_custom_error(EcssError::UnexpectedToken(token)));
+                Err((err, a)) => {
+                    error!("Failed to parse property : {:?} ({})", err, a)
                 }
             }
         }
 
-        if elements.is_empty() {
-            return Err(input.new_custom_error(EcssError::InvalidSelector));
+        // Each selector on a selector list becomes it's own rule, sharing the same properties.
+        Ok(prelude
+            .into_iter()
+            .map(|selector| StyleRule {
+                selector,
+                properties: properties.clone(),
+                layer: self.layer.clone(),
+                media: self.media.clone(),
+                containers: self.containers.clone(),
+            })
+            .collect())
+    }
+}
+
+/// Prelude of supported at-rules.
+pub(crate) enum AtRulePrelude {
+    /// Layer names of a `@layer` rule, which is empty on anonymous layers.
+    Layer(SmallVec<[String; 4]>),
+    /// Media query list of a `@media` rule.
+    Media(MediaQueryList),
+    /// Size condition of a `@container` rule.
+    Container(MediaCondition),
+    /// Path, layer and media query list of a `@import` rule.
+    Import(String, Option<Option<String>>, MediaQueryList),
+}
+
+impl<'i> AtRuleParser<'i> for StyleSheetParser {
+    type Prelude = AtRulePrelude;
+    type AtRule = SmallVec<[StyleRule; 4]>;
+    type Error = EcssError;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+        if name.eq_ignore_ascii_case("media") {
+            return Ok(AtRulePrelude::Media(parse_media_query_list(input)?));
+        }
+
+        if name.eq_ignore_ascii_case("import") {
+            if self.any_rule || self.layer.is_some() {
+                return Err(input.new_custom_error(EcssError::UnexpectedToken(
+                    "@import must be before all other rules".to_string(),
+                )));
+            }
+
+            let path = input.expect_url_or_string()?.to_string();
+            // Either an anonymous layer, like `layer`, or a named one, like `layer(base)`.
+            let layer = input
+                .try_parse(|input| {
+                    if input
+                        .try_parse(|input| input.expect_ident_matching("layer"))
+                        .is_ok()
+                    {
+                        return Ok(None);
+                    }
+                    input.expect_function_matching("layer")?;
+                    input.parse_nested_block(|input| parse_layer_name(input).map(Some))
+                })
+                .ok();
+            let media = parse_media_query_list(input)?;
+
+            return Ok(AtRulePrelude::Import(path, layer, media));
+        }
+
+        // Named containers aren't supported, so the prelude must be a single condition.
+        if name.eq_ignore_ascii_case("container") {
+            let condition = parse_media_condition(input)?;
+            input.expect_exhausted()?;
+            return Ok(AtRulePrelude::Container(condition));
         }
 
-        // Remove noise the trailing white spaces, if any
-        while !elements.is_empty() && elements.last().unwrap() == &SelectorElement::Child {
-            elements.remove(elements.len() - 1);
+        if !name.eq_ignore_ascii_case("layer") {
+            return Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name)));
         }
 
-        Ok(Selector::new(elements))
+        // Anonymous layers, like `@layer { ... }`, have no names.
+        if input.is_exhausted() {
+            return Ok(AtRulePrelude::Layer(smallvec![]));
+        }
+
+        Ok(AtRulePrelude::Layer(
+            input
+                .parse_comma_separated(parse_layer_name)?
+                .into_iter()
+                .collect(),
+        ))
+    }
+
+    fn rule_without_block(
+        &mut self,
+        prelude: Self::Prelude,
+        _start: &cssparser::ParserState,
+    ) -> Result<Self::AtRule, ()> {
+        // Layer statements, like `@layer a, b;`, only declares the layers order.
+        match prelude {
+            AtRulePrelude::Layer(names) if !names.is_empty() => {
+                for name in names {
+                    self.declare_layer(&name);
+                }
+                Ok(smallvec![])
+            }
+            AtRulePrelude::Import(path, layer, media) => {
+                let layer = layer.map(|name| match name {
+                    Some(name) => self.declare_layer(&name),
+                    None => {
+                        self.anonymous_layers += 1;
+                        self.declare_layer(&format!("@{}", self.anonymous_layers))
+                    }
+                });
+                self.imports.push(ImportRule {
+                    path,
+                    layer,
+                    media,
+                    layers_before: self.layers.len(),
+                });
+                Ok(smallvec![])
+            }
+            _ => Err(()),
+        }
     }
 
     fn parse_block<'t>(
@@ -134,31 +320,278 @@ impl<'i> QualifiedRuleParser<'i> for StyleSheetParser {
         prelude: Self::Prelude,
         _start: &cssparser::ParserState,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
-        let mut rule = StyleRule {
-            selector: prelude,
-            properties: Default::default(),
+    ) -> Result<Self::AtRule, ParseError<'i, Self::Error>> {
+        self.any_rule = true;
+        let names = match prelude {
+            AtRulePrelude::Layer(names) => names,
+            AtRulePrelude::Media(media) => {
+                self.media.push(media);
+                let rules = self.parse_rules(input);
+                self.media.pop();
+
+                return Ok(rules.into_iter().collect());
+            }
+            AtRulePrelude::Container(condition) => {
+                self.containers.push(condition);
+                let rules = self.parse_rules(input);
+                self.containers.pop();
+
+                return Ok(rules.into_iter().collect());
+            }
+            AtRulePrelude::Import(..) => {
+                return Err(input.new_error(BasicParseErrorKind::AtRuleBodyInvalid))
+            }
         };
 
-        for property in RuleBodyParser::new(input, &mut PropertyParser) {
-            match property {
-                Ok((name, property)) => {
-                    rule.properties.insert(name, property);
+        let name = match names.as_slice() {
+            [name] => name.clone(),
+            [] => {
+                // Anonymous layers can't be referenced, so they are named with a character which
+                // isn't allowed on identifiers.
+                self.anonymous_layers += 1;
+                format!("@{}", self.anonymous_layers)
+            }
+            _ => return Err(input.new_error(BasicParseErrorKind::AtRuleBodyInvalid)),
+        };
+
+        let full_name = self.declare_layer(&name);
+        let parent = self.layer.replace(full_name);
+        let rules = self.parse_rules(input);
+        self.layer = parent;
+
+        Ok(rules.into_iter().collect())
+    }
+}
+
+/// Parses a layer name, like `widgets` or `widgets.buttons`.
+fn parse_layer_name<'i>(input: &mut Parser<'i, '_>) -> Result<String, ParseError<'i, EcssError>> {
+    let mut name = input.expect_ident()?.to_string();
+    while input.try_parse(|input| input.expect_delim('.')).is_ok() {
+        name.push('.');
+        name.push_str(input.expect_ident()?);
+    }
+    Ok(name)
+}
+
+/// Parses a single selector, like `a.b #c`, until the end of input or the next comma.
+fn parse_selector<'i>(input: &mut Parser<'i, '_>) -> Result<Selector, ParseError<'i, EcssError>> {
+    parse_selector_elements(input, smallvec![])
+}
+
+/// Parses a single relative selector, like `> a.b`, until the end of input or the next comma.
+///
+/// Relative selectors always starts with a combinator, so if none is given, a descendant
+/// combinator is used, like `a.b` is the same as ` a.b`.
+fn parse_relative_selector<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, EcssError>> {
+    let combinator = input
+        .try_parse(|input| match input.next()? {
+            Token::Delim('>') => Ok(SelectorElement::DirectChild),
+            Token::Delim('+') => Ok(SelectorElement::NextSibling),
+            Token::Delim('~') => Ok(SelectorElement::SubsequentSibling),
+            token => {
+                let token = token.clone();
+                Err(input.new_unexpected_token_error::<EcssError>(token))
+            }
+        })
+        .unwrap_or(SelectorElement::Child);
+
+    parse_selector_elements(input, smallvec![combinator])
+}
+
+/// Parses selector elements, appending to the given ones, until the end of input or the next comma.
+fn parse_selector_elements<'i>(
+    input: &mut Parser<'i, '_>,
+    mut elements: SmallVec<[SelectorElement; 8]>,
+) -> Result<Selector, ParseError<'i, EcssError>> {
+    let mut next_element_with_prefix = NextElementWithPrefix::None;
+
+    while let Ok(token) = input.next_including_whitespace() {
+        use cssparser::Token::*;
+        match token {
+            Ident(v) => {
+                match next_element_with_prefix {
+                    NextElementWithPrefix::None => {
+                        elements.push(SelectorElement::Component(v.to_string()))
+                    }
+                    NextElementWithPrefix::Class => {
+                        elements.push(SelectorElement::Class(v.to_string()))
+                    }
+                    NextElementWithPrefix::PseudoClass => {
+                        elements.push(SelectorElement::PseudoClass(v.into()))
+                    }
                 }
-                Err((err, a)) => {
-                    error!("Failed to parse property : {:?} ({})", err, a)
+                next_element_with_prefix = NextElementWithPrefix::None;
+            }
+            IDHash(v) => {
+                if v.is_empty() {
+                    return Err(input.new_custom_error(EcssError::InvalidSelector));
+                } else {
+                    elements.push(SelectorElement::Name(v.to_string()));
                 }
             }
+            WhiteSpace(_) => {
+                // White spaces around other combinators, like `a > b`, aren't descendant combinators
+                if elements.last().is_some_and(|e| !e.is_combinator()) {
+                    elements.push(SelectorElement::Child);
+                }
+            }
+            Delim(c) if *c == '>' => {
+                push_combinator(input, &mut elements, SelectorElement::DirectChild)?
+            }
+            Delim(c) if *c == '+' => {
+                push_combinator(input, &mut elements, SelectorElement::NextSibling)?
+            }
+            Delim(c) if *c == '~' => {
+                push_combinator(input, &mut elements, SelectorElement::SubsequentSibling)?
+            }
+            Delim(c) if *c == '.' => next_element_with_prefix = NextElementWithPrefix::Class,
+            Delim(c) if *c == '*' => elements.push(SelectorElement::Any),
+            Colon => next_element_with_prefix = NextElementWithPrefix::PseudoClass,
+            SquareBracketBlock => {
+                let attribute = input.parse_nested_block(parse_attribute)?;
+                elements.push(SelectorElement::Attribute(attribute));
+            }
+            Function(name)
+                if matches!(next_element_with_prefix, NextElementWithPrefix::PseudoClass) =>
+            {
+                let name = name.clone();
+                let pseudo_class =
+                    input.parse_nested_block(|input| parse_pseudo_class_function(name, input))?;
+                elements.push(SelectorElement::PseudoClass(pseudo_class));
+                next_element_with_prefix = NextElementWithPrefix::None;
+            }
+            _ => {
+                let token = token.to_css_string();
+                return Err(input.new_custom_error(EcssError::UnexpectedToken(token)));
+            }
         }
+    }
+
+    // Remove noise the trailing white spaces, if any
+    while !elements.is_empty() && elements.last().unwrap() == &SelectorElement::Child {
+        elements.remove(elements.len() - 1);
+    }
 
-        Ok(rule)
+    // A selector can't be empty nor end with a combinator, like `a >`
+    if elements.last().is_none_or(SelectorElement::is_combinator) {
+        return Err(input.new_custom_error(EcssError::InvalidSelector));
     }
+
+    Ok(Selector::new(elements))
 }
 
-impl<'i> AtRuleParser<'i> for StyleSheetParser {
-    type Prelude = ();
-    type AtRule = StyleRule;
-    type Error = EcssError;
+/// Parses the arguments of a functional pseudo-class, like `:nth-child(2n+1)`.
+fn parse_pseudo_class_function<'i>(
+    name: CowRcStr<'i>,
+    input: &mut Parser<'i, '_>,
+) -> Result<PseudoClassElement, ParseError<'i, EcssError>> {
+    match name.as_ref() {
+        "nth-child" => {
+            let (a, b) = cssparser::parse_nth(input)?;
+            Ok(PseudoClassElement::NthChild(a, b))
+        }
+        "is" => Ok(PseudoClassElement::Is(
+            input.parse_comma_separated(parse_selector)?,
+        )),
+        "where" => Ok(PseudoClassElement::Where(
+            input.parse_comma_separated(parse_selector)?,
+        )),
+        "not" => Ok(PseudoClassElement::Not(
+            input.parse_comma_separated(parse_selector)?,
+        )),
+        "has" => Ok(PseudoClassElement::Has(
+            input.parse_comma_separated(parse_relative_selector)?,
+        )),
+        _ => Err(input.new_custom_error(EcssError::UnsupportedSelector)),
+    }
+}
+
+/// Parses the content of an attribute selector, like `key=value` on `[key=value]`.
+///
+/// The attribute name may be a path to a reflected component field, like `Health.current`.
+fn parse_attribute<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<AttributeElement, ParseError<'i, EcssError>> {
+    let mut name = input.expect_ident()?.to_string();
+    while let Ok(field) = input.try_parse(|input| {
+        input.expect_delim('.')?;
+        input.expect_ident_cloned()
+    }) {
+        name.push('.');
+        name.push_str(&field);
+    }
+
+    if input.is_exhausted() {
+        return Ok(AttributeElement {
+            name,
+            matcher: AttributeMatcher::Exists,
+        });
+    }
+
+    let location = input.current_source_location();
+    let token = input.next()?.clone();
+    let matcher: fn(String) -> AttributeMatcher = match token {
+        Token::Delim('=') => AttributeMatcher::Equals,
+        Token::IncludeMatch => AttributeMatcher::Includes,
+        Token::PrefixMatch => AttributeMatcher::Prefix,
+        Token::SuffixMatch => AttributeMatcher::Suffix,
+        Token::SubstringMatch => AttributeMatcher::Substring,
+        Token::Delim('<') if input.try_parse(|input| input.expect_delim('=')).is_ok() => {
+            AttributeMatcher::LessOrEqual
+        }
+        Token::Delim('<') => AttributeMatcher::Less,
+        Token::Delim('>') if input.try_parse(|input| input.expect_delim('=')).is_ok() => {
+            AttributeMatcher::GreaterOrEqual
+        }
+        Token::Delim('>') => AttributeMatcher::Greater,
+        token => {
+            let token = token.to_css_string();
+            return Err(location.new_custom_error(EcssError::UnexpectedToken(token)));
+        }
+    };
+
+    let location = input.current_source_location();
+    let value = match input.next()? {
+        Token::Ident(value) | Token::QuotedString(value) => value.to_string(),
+        Token::Number {
+            int_value: Some(value),
+            ..
+        } => value.to_string(),
+        Token::Number { value, .. } => value.to_string(),
+        token => {
+            let token = token.to_css_string();
+            return Err(location.new_custom_error(EcssError::UnexpectedToken(token)));
+        }
+    };
+
+    input.expect_exhausted()?;
+
+    Ok(AttributeElement {
+        name,
+        matcher: matcher(value),
+    })
+}
+
+/// Pushes a combinator into the given elements, replacing any previous descendant combinator,
+/// which is just a white space before the combinator.
+fn push_combinator<'i>(
+    input: &Parser<'i, '_>,
+    elements: &mut SmallVec<[SelectorElement; 8]>,
+    combinator: SelectorElement,
+) -> Result<(), ParseError<'i, EcssError>> {
+    if elements.last() == Some(&SelectorElement::Child) {
+        elements.pop();
+    }
+
+    // A combinator must always have elements on both sides, like `a > b`
+    if elements.last().is_none_or(SelectorElement::is_combinator) {
+        return Err(input.new_custom_error(EcssError::InvalidSelector));
+    }
+
+    elements.push(combinator);
+    Ok(())
 }
 
 struct PropertyParser;
@@ -184,7 +617,7 @@ impl<'i> DeclarationParser<'i> for PropertyParser {
         parser: &mut Parser<'i, 't>,
     ) -> Result<Self::Declaration, ParseError<'i, EcssError>> {
         let mut tokens = smallvec![];
-        for token in parse_values(parser)? {
+        for token in parser.parse_until_before(Delimiter::Bang, parse_values)? {
             bevy::log::info!("Trying to parse token: {token:?}");
             match token.try_into() {
                 Ok(t) => tokens.push(t),
@@ -192,7 +625,10 @@ impl<'i> DeclarationParser<'i> for PropertyParser {
             }
         }
 
-        Ok((name.to_string(), PropertyValues(tokens)))
+        let important = parser.try_parse(cssparser::parse_important).is_ok();
+        parser.expect_exhausted()?;
+
+        Ok((name.to_string(), PropertyValues(tokens, important)))
     }
 }
 
@@ -208,10 +644,29 @@ impl<'i> QualifiedRuleParser<'i> for PropertyParser {
     type Error = EcssError;
 }
 
+/// Parses a single property value, like `10px 20px` or `#ff03ab`, into [`PropertyValues`].
+pub(crate) fn parse_property_values(content: &str) -> Result<PropertyValues, EcssError> {
+    let mut input = ParserInput::new(content);
+    let mut parser = Parser::new(&mut input);
+
+    let tokens = parse_values(&mut parser)
+        .map_err(|_| EcssError::InvalidPropertyValue(content.to_string()))?
+        .into_iter()
+        .filter_map(|token| token.try_into().ok())
+        .collect::<SmallVec<_>>();
+
+    if tokens.is_empty() {
+        return Err(EcssError::InvalidPropertyValue(content.to_string()));
+    }
+
+    Ok(PropertyValues(tokens, false))
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum ParsedToken<'i> {
     Single(Token<'i>),
-    Function(CowRcStr<'i>, Vec<Token<'i>>),
+    Function(CowRcStr<'i>, Vec<ParsedToken<'i>>),
+    Calc(CalcExpr),
 }
 
 fn parse_values<'i>(
@@ -219,18 +674,59 @@ fn parse_values<'i>(
 ) -> Result<SmallVec<[ParsedToken<'i>; 8]>, ParseError<'i, EcssError>> {
     let mut values = SmallVec::new();
 
-    while let Ok(token) = parser.next() {
+    loop {
+        let location = parser.current_source_location();
+        let Ok(token) = parser.next() else {
+            break;
+        };
+
         if let Token::Function(fn_name) = token {
-            values.push(ParsedToken::Function(
-                fn_name.clone(),
-                parser.parse_nested_block(|parser| {
-                    let mut values = Vec::new();
-                    while let Ok(token) = parser.next() {
-                        values.push(token.clone());
+            let fn_name = fn_name.clone();
+            if is_calc_function(&fn_name) {
+                // Math functions which can't be parsed, like the ones using `var()`, are kept as plain functions.
+                match parser.try_parse(|parser| {
+                    parser.parse_nested_block(|parser| parse_calc_function(&fn_name, parser))
+                }) {
+                    Ok(expr) => {
+                        values.push(ParsedToken::Calc(expr));
+                        continue;
                     }
-                    Ok(values)
-                })?,
+                    Err(
+                        error @ ParseError {
+                            kind: ParseErrorKind::Custom(EcssError::UnknownUnit(_)),
+                            ..
+                        },
+                    ) => return Err(error),
+                    Err(_) => (),
+                }
+            }
+
+            // Quoted urls, like `url("image.png")`, are parsed as functions, unlike unquoted ones.
+            if fn_name.eq_ignore_ascii_case("url") {
+                if let Ok(url) = parser.try_parse(|parser| {
+                    parser.parse_nested_block(|parser| {
+                        parser
+                            .expect_string_cloned()
+                            .map_err(ParseError::<EcssError>::from)
+                    })
+                }) {
+                    values.push(ParsedToken::Single(Token::UnquotedUrl(url)));
+                    continue;
+                }
+            }
+
+            values.push(ParsedToken::Function(
+                fn_name,
+                parser.parse_nested_block(|parser| Ok(parse_values(parser)?.into_vec()))?,
             ));
+        } else if let Token::Dimension { value, unit, .. } = token {
+            // The whole declaration is invalid when any value has an unknown unit.
+            if PropertyToken::try_from(token.clone()).is_err() {
+                return Err(
+                    location.new_custom_error(EcssError::UnknownUnit(format!("{value}{unit}")))
+                );
+            }
+            values.push(ParsedToken::Single(token.clone()));
         } else {
             values.push(ParsedToken::Single(token.clone()));
         }
@@ -239,9 +735,115 @@ fn parse_values<'i>(
     Ok(values)
 }
 
+/// Checks if the given function name is a math function, which is parsed into a [`CalcExpr`].
+/// Function names are ASCII case-insensitive, like `CALC()`.
+fn is_calc_function(name: &str) -> bool {
+    ["calc", "min", "max", "clamp"]
+        .iter()
+        .any(|function| name.eq_ignore_ascii_case(function))
+}
+
+/// Parses the arguments of a `calc()`, `min()`, `max()` or `clamp()` function.
+fn parse_calc_function<'i>(
+    name: &str,
+    parser: &mut Parser<'i, '_>,
+) -> Result<CalcExpr, ParseError<'i, EcssError>> {
+    if name.eq_ignore_ascii_case("calc") {
+        parse_calc_sum(parser)
+    } else if name.eq_ignore_ascii_case("min") {
+        Ok(CalcExpr::Min(parser.parse_comma_separated(parse_calc_sum)?))
+    } else if name.eq_ignore_ascii_case("max") {
+        Ok(CalcExpr::Max(parser.parse_comma_separated(parse_calc_sum)?))
+    } else {
+        let location = parser.current_source_location();
+        let args = parser.parse_comma_separated(parse_calc_sum)?;
+        match <[CalcExpr; 3]>::try_from(args) {
+            Ok([min, val, max]) => Ok(CalcExpr::Clamp(Box::new(min), Box::new(val), Box::new(max))),
+            Err(_) => Err(location.new_custom_error(EcssError::InvalidPropertyValue(
+                "clamp() expects exactly 3 arguments".to_string(),
+            ))),
+        }
+    }
+}
+
+/// Parses a sum of products, like `100% - 10px * 2`.
+fn parse_calc_sum<'i>(parser: &mut Parser<'i, '_>) -> Result<CalcExpr, ParseError<'i, EcssError>> {
+    let mut expr = parse_calc_product(parser)?;
+
+    while let Ok(op) = parser.try_parse(|parser| match parser.next()?.clone() {
+        Token::Delim(op @ ('+' | '-')) => Ok(op),
+        token => Err(parser.new_unexpected_token_error::<EcssError>(token)),
+    }) {
+        let rhs = Box::new(parse_calc_product(parser)?);
+        expr = match op {
+            '+' => CalcExpr::Add(Box::new(expr), rhs),
+            _ => CalcExpr::Sub(Box::new(expr), rhs),
+        };
+    }
+
+    parser.expect_exhausted()?;
+
+    Ok(expr)
+}
+
+/// Parses a product of values, like `10px * 2 / 3`.
+fn parse_calc_product<'i>(
+    parser: &mut Parser<'i, '_>,
+) -> Result<CalcExpr, ParseError<'i, EcssError>> {
+    let mut expr = parse_calc_value(parser)?;
+
+    while let Ok(op) = parser.try_parse(|parser| match parser.next()?.clone() {
+        Token::Delim(op @ ('*' | '/')) => Ok(op),
+        token => Err(parser.new_unexpected_token_error::<EcssError>(token)),
+    }) {
+        let rhs = Box::new(parse_calc_value(parser)?);
+        expr = match op {
+            '*' => CalcExpr::Mul(Box::new(expr), rhs),
+            _ => CalcExpr::Div(Box::new(expr), rhs),
+        };
+    }
+
+    Ok(expr)
+}
+
+/// Parses a single value, a parenthesized expression or a nested math function.
+fn parse_calc_value<'i>(
+    parser: &mut Parser<'i, '_>,
+) -> Result<CalcExpr, ParseError<'i, EcssError>> {
+    let location = parser.current_source_location();
+    match parser.next()?.clone() {
+        Token::Number { value, .. } => Ok(CalcExpr::Value(value, CalcUnit::Number)),
+        Token::Percentage { unit_value, .. } => {
+            Ok(CalcExpr::Value(unit_value * 100.0, CalcUnit::Percent))
+        }
+        Token::Dimension { value, unit, .. } => match unit.to_ascii_lowercase().as_str() {
+            "vw" => Ok(CalcExpr::Value(value, CalcUnit::Vw)),
+            "vh" => Ok(CalcExpr::Value(value, CalcUnit::Vh)),
+            "vmin" => Ok(CalcExpr::Value(value, CalcUnit::VMin)),
+            "vmax" => Ok(CalcExpr::Value(value, CalcUnit::VMax)),
+            "em" => Ok(CalcExpr::Value(value, CalcUnit::Em)),
+            "rem" => Ok(CalcExpr::Value(value, CalcUnit::Rem)),
+            other => match absolute_length(value, other) {
+                Some(px) => Ok(CalcExpr::Value(px, CalcUnit::Px)),
+                None => {
+                    Err(location.new_custom_error(EcssError::UnknownUnit(format!("{value}{unit}"))))
+                }
+            },
+        },
+        Token::Ident(keyword) => Ok(CalcExpr::Keyword(keyword.to_string())),
+        Token::ParenthesisBlock => parser.parse_nested_block(parse_calc_sum),
+        Token::Function(name) if is_calc_function(&name) => {
+            parser.parse_nested_block(|parser| parse_calc_function(&name, parser))
+        }
+        token => Err(location.new_custom_error(EcssError::UnexpectedToken(token.to_css_string()))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{property::PropertyToken, selector::PseudoClassElement};
+    use bevy::ui::Val;
+
+    use crate::{property::PropertyToken, selector::Specificity};
 
     use super::*;
 
@@ -454,7 +1056,7 @@ mod tests {
         use SelectorElement::*;
         let expected: SmallVec<[SmallVec<[SelectorElement; 8]>; 8]> = smallvec![smallvec![
             Component("a".to_string()),
-            PseudoClass(PseudoClassElement::Unsupported)
+            PseudoClass(PseudoClassElement::Custom("pseudo".to_string()))
         ],];
 
         expected
@@ -574,6 +1176,605 @@ mod tests {
         });
     }
 
+    #[test]
+    fn parse_selector_list() {
+        let rules = StyleSheetParser::parse("button, .card , #title.big {a: b}");
+        assert_eq!(rules.len(), 3, "Should have a rule for each selector");
+
+        use SelectorElement::*;
+        let expected: [SmallVec<[SelectorElement; 8]>; 3] = [
+            smallvec![Component("button".to_string())],
+            smallvec![Class("card".to_string())],
+            smallvec![Name("title".to_string()), Class("big".to_string())],
+        ];
+
+        for (rule, expected) in rules.iter().zip(expected) {
+            let tree = rule.selector.get_parent_tree();
+            assert_eq!(tree.len(), 1, "Should have a single selector node");
+            assert_eq!(tree[0].len(), expected.len());
+            expected
+                .iter()
+                .zip(&tree[0])
+                .for_each(|(expected, element)| assert_eq!(expected, *element));
+
+            assert!(
+                rule.properties.contains_key("a"),
+                "All selectors should share the same properties"
+            );
+        }
+
+        assert_eq!(rules[0].selector.specificity(), Specificity(0, 0, 1));
+        assert_eq!(rules[1].selector.specificity(), Specificity(0, 1, 0));
+        assert_eq!(rules[2].selector.specificity(), Specificity(1, 1, 0));
+    }
+
+    #[test]
+    fn parse_invalid_selector_list() {
+        assert!(
+            StyleSheetParser::parse("a, {b: c}").is_empty(),
+            "An empty selector should invalidate the whole rule"
+        );
+        assert!(
+            StyleSheetParser::parse("a, b$ {b: c}").is_empty(),
+            "An invalid selector should invalidate the whole rule"
+        );
+    }
+
+    #[test]
+    fn parse_direct_child_combinator() {
+        use SelectorElement::*;
+
+        for selector in ["#menu > button .a", "#menu>button .a", "#menu >button  .a"] {
+            let rules = StyleSheetParser::parse(&format!("{selector} {{}}"));
+            assert_eq!(rules.len(), 1, "Should have a single rule: {selector}");
+
+            let tree = rules[0].selector.get_combinator_tree();
+            assert_eq!(tree.len(), 3, "Should have 3 selector nodes: {selector}");
+
+            assert_eq!(tree[0].0, None);
+            assert_eq!(tree[0].1[0], &Name("menu".to_string()));
+            assert_eq!(tree[1].0, Some(&DirectChild));
+            assert_eq!(tree[1].1[0], &Component("button".to_string()));
+            assert_eq!(tree[2].0, Some(&Child));
+            assert_eq!(tree[2].1[0], &Class("a".to_string()));
+        }
+
+        for selector in ["> a", "a >", "a > > b"] {
+            assert!(
+                StyleSheetParser::parse(&format!("{selector} {{}}")).is_empty(),
+                "Should be an invalid selector: {selector}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_sibling_combinators() {
+        use SelectorElement::*;
+
+        let rules = StyleSheetParser::parse(".a + .b~.c ~ .d {}");
+        assert_eq!(rules.len(), 1, "Should have a single rule");
+
+        let tree = rules[0].selector.get_combinator_tree();
+        let expected = [
+            (None, "a"),
+            (Some(&NextSibling), "b"),
+            (Some(&SubsequentSibling), "c"),
+            (Some(&SubsequentSibling), "d"),
+        ];
+
+        assert_eq!(tree.len(), expected.len());
+        for ((combinator, node), (expected_combinator, class)) in tree.into_iter().zip(expected) {
+            assert_eq!(combinator, expected_combinator);
+            assert_eq!(node.as_slice(), &[&Class(class.to_string())]);
+        }
+
+        assert!(StyleSheetParser::parse("a + ~ b {}").is_empty());
+    }
+
+    #[test]
+    fn parse_structural_pseudo_classes() {
+        use PseudoClassElement::*;
+
+        let expected = [
+            ("a:first-child", FirstChild),
+            ("a:last-child", LastChild),
+            ("a:only-child", OnlyChild),
+            ("a:empty", Empty),
+            (":root", Root),
+            ("a:nth-child(2n+1)", NthChild(2, 1)),
+            ("a:nth-child( -n + 3 )", NthChild(-1, 3)),
+            ("a:nth-child(odd)", NthChild(2, 1)),
+            ("a:nth-child(even)", NthChild(2, 0)),
+            ("a:nth-child(4)", NthChild(0, 4)),
+        ];
+
+        for (selector, pseudo_class) in expected {
+            let rules = StyleSheetParser::parse(&format!("{selector} {{}}"));
+            assert_eq!(rules.len(), 1, "Should have a single rule: {selector}");
+
+            let tree = rules[0].selector.get_parent_tree();
+            assert_eq!(
+                tree[0].last(),
+                Some(&&SelectorElement::PseudoClass(pseudo_class)),
+                "{selector}"
+            );
+        }
+
+        assert!(StyleSheetParser::parse("a:nth-child(foo) {}").is_empty());
+        assert!(StyleSheetParser::parse("a:unknown-function(1) {}").is_empty());
+    }
+
+    #[test]
+    fn parse_logical_pseudo_classes() {
+        let rules = StyleSheetParser::parse("button:not(.disabled, #a):hover {}");
+        assert_eq!(rules.len(), 1, "Should have a single rule");
+
+        let tree = rules[0].selector.get_parent_tree();
+        assert_eq!(tree[0].len(), 3);
+
+        match tree[0][1] {
+            SelectorElement::PseudoClass(PseudoClassElement::Not(selectors)) => {
+                assert_eq!(selectors.len(), 2);
+                assert_eq!(selectors[0].to_string(), ".disabled");
+                assert_eq!(selectors[1].to_string(), "#a");
+            }
+            _ => panic!("Should have a :not pseudo-class"),
+        }
+
+        assert_eq!(
+            rules[0].selector.specificity(),
+            Specificity(1, 1, 1),
+            ":not should take the specificity of it's most specific argument"
+        );
+
+        let rules = StyleSheetParser::parse(":is(a, .b > c) :where(#d, .e) {}");
+        assert_eq!(rules.len(), 1, "Should have a single rule");
+        assert_eq!(
+            rules[0].selector.to_string(),
+            ":is(a, .b > c) :where(#d, .e)"
+        );
+        assert_eq!(
+            rules[0].selector.specificity(),
+            Specificity(0, 1, 1),
+            ":where shouldn't add any specificity"
+        );
+
+        assert!(StyleSheetParser::parse("a:not() {}").is_empty());
+        assert!(StyleSheetParser::parse("a:is(b,) {}").is_empty());
+    }
+
+    #[test]
+    fn parse_has_pseudo_class() {
+        let rules = StyleSheetParser::parse(".slot:has(.item-rare, > .a + .b, ~ c) {}");
+        assert_eq!(rules.len(), 1, "Should have a single rule");
+        assert_eq!(
+            rules[0].selector.to_string(),
+            ".slot:has(.item-rare, > .a + .b, ~ c)"
+        );
+
+        let tree = rules[0].selector.get_parent_tree();
+        match tree[0][1] {
+            SelectorElement::PseudoClass(PseudoClassElement::Has(selectors)) => {
+                let combinators = selectors
+                    .iter()
+                    .map(|selector| selector.get_combinator_tree()[1].0)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    combinators,
+                    [
+                        Some(&SelectorElement::Child),
+                        Some(&SelectorElement::DirectChild),
+                        Some(&SelectorElement::SubsequentSibling),
+                    ]
+                );
+            }
+            _ => panic!("Should have a :has pseudo-class"),
+        }
+
+        assert_eq!(rules[0].selector.specificity(), Specificity(0, 3, 0));
+
+        assert!(StyleSheetParser::parse("a:has() {}").is_empty());
+        assert!(StyleSheetParser::parse("a:has(>) {}").is_empty());
+        assert!(StyleSheetParser::parse("a:has(> > b) {}").is_empty());
+    }
+
+    #[test]
+    fn parse_attribute_selectors() {
+        use AttributeMatcher::*;
+
+        let expected = [
+            ("[key]", "key", Exists),
+            ("[key=value]", "key", Equals("value".to_string())),
+            (
+                "[ key = \"a value\" ]",
+                "key",
+                Equals("a value".to_string()),
+            ),
+            ("[key~=value]", "key", Includes("value".to_string())),
+            ("[key^=value]", "key", Prefix("value".to_string())),
+            ("[key$=value]", "key", Suffix("value".to_string())),
+            ("[key*=value]", "key", Substring("value".to_string())),
+            (
+                "[Health.current<20]",
+                "Health.current",
+                Less("20".to_string()),
+            ),
+            (
+                "[Health.current<=-2.5]",
+                "Health.current",
+                LessOrEqual("-2.5".to_string()),
+            ),
+            ("[a.b.c>1]", "a.b.c", Greater("1".to_string())),
+            ("[a.b.c>=1]", "a.b.c", GreaterOrEqual("1".to_string())),
+        ];
+
+        for (selector, name, matcher) in expected {
+            let rules = StyleSheetParser::parse(&format!("button{selector} {{}}"));
+            assert_eq!(rules.len(), 1, "Should have a single rule: {selector}");
+
+            let tree = rules[0].selector.get_parent_tree();
+            assert_eq!(
+                tree[0][1],
+                &SelectorElement::Attribute(AttributeElement {
+                    name: name.to_string(),
+                    matcher
+                }),
+                "{selector}"
+            );
+            assert_eq!(rules[0].selector.specificity(), Specificity(0, 1, 1));
+        }
+
+        for selector in ["[]", "[key=]", "[key==value]", "[key=a b]", "[.key]"] {
+            assert!(
+                StyleSheetParser::parse(&format!("{selector} {{}}")).is_empty(),
+                "Should be an invalid selector: {selector}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_calc_values() {
+        use CalcUnit::*;
+
+        let value = |value, unit| Box::new(CalcExpr::Value(value, unit));
+
+        let values = parse_property_values("calc(100% - 40px * 2)").unwrap();
+        assert_eq!(
+            values[0],
+            PropertyToken::Calc(CalcExpr::Sub(
+                value(100.0, Percent),
+                Box::new(CalcExpr::Mul(value(40.0, Px), value(2.0, Number)))
+            ))
+        );
+
+        let values = parse_property_values("calc((10px + 2vw) / 2)").unwrap();
+        assert_eq!(
+            values[0],
+            PropertyToken::Calc(CalcExpr::Div(
+                Box::new(CalcExpr::Add(value(10.0, Px), value(2.0, Vw))),
+                value(2.0, Number)
+            ))
+        );
+
+        let values = parse_property_values("clamp(12px, 2vw, min(24px, 5vh))").unwrap();
+        assert_eq!(
+            values[0],
+            PropertyToken::Calc(CalcExpr::Clamp(
+                value(12.0, Px),
+                value(2.0, Vw),
+                Box::new(CalcExpr::Min(vec![
+                    CalcExpr::Value(24.0, Px),
+                    CalcExpr::Value(5.0, Vh)
+                ]))
+            ))
+        );
+
+        let values = parse_property_values("CALC(100% - 10px) Min(1px, 2px)").unwrap();
+        assert_eq!(
+            values[0],
+            PropertyToken::Calc(CalcExpr::Sub(value(100.0, Percent), value(10.0, Px))),
+            "Function names should be case-insensitive"
+        );
+        assert!(matches!(values[1], PropertyToken::Calc(CalcExpr::Min(_))));
+
+        let values = parse_property_values("max(10vmin, 50%) 10px").unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(
+            values[0],
+            PropertyToken::Calc(CalcExpr::Max(vec![
+                CalcExpr::Value(10.0, VMin),
+                CalcExpr::Value(50.0, Percent)
+            ]))
+        );
+
+        assert_eq!(
+            parse_property_values("calc(100% / 4)").unwrap().val(),
+            Some(Val::Percent(25.0))
+        );
+        assert_eq!(
+            parse_property_values("calc(100% - 40px)").unwrap().val(),
+            None
+        );
+        assert_eq!(
+            parse_property_values("calc(12px * 2)").unwrap().f32(),
+            Some(24.0)
+        );
+
+        for invalid in ["calc(10px +)", "calc(10px 5px)", "clamp(1px, 2px)", "min()"] {
+            let values = parse_property_values(invalid).unwrap();
+            assert!(
+                matches!(&values[0], PropertyToken::Function(..)),
+                "Should not be a math expression: {invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_length_units() {
+        use PropertyToken::*;
+
+        let expected = [
+            ("10px", Dimension(10.0)),
+            ("1in", Dimension(96.0)),
+            ("2.54cm", Dimension(96.0)),
+            ("12pt", Dimension(16.0)),
+            ("1pc", Dimension(16.0)),
+            ("2em", Em(2.0)),
+            ("1.5REM", Rem(1.5)),
+        ];
+
+        for (value, token) in expected {
+            let values = parse_property_values(value).unwrap();
+            match (&values[0], &token) {
+                (Dimension(a), Dimension(b)) => assert!((a - b).abs() < 1e-4, "{value}"),
+                (a, b) => assert_eq!(a, b, "{value}"),
+            }
+        }
+
+        assert!(parse_property_values("10furlongs").is_err());
+        assert!(parse_property_values("10furlongs 5px").is_err());
+        assert!(parse_property_values("rgb(10furlongs, 0, 0)").is_err());
+
+        assert_eq!(
+            parse_property_values("calc(2em + 1in)").unwrap()[0],
+            PropertyToken::Calc(CalcExpr::Add(
+                Box::new(CalcExpr::Value(2.0, CalcUnit::Em)),
+                Box::new(CalcExpr::Value(96.0, CalcUnit::Px))
+            ))
+        );
+        assert!(parse_property_values("calc(2furlongs + 1px)").is_err());
+        assert!(parse_property_values("min(var(--gap), 2furlongs)").is_err());
+
+        let rules = StyleSheetParser::parse(
+            "a { width: 10furlongs 5px; height: calc(2furlongs + 1px); left: 1px; }",
+        );
+        let properties = &rules[0].properties;
+        assert!(
+            !properties.contains_key("width"),
+            "Should reject the whole declaration"
+        );
+        assert!(!properties.contains_key("height"));
+        assert!(properties.contains_key("left"));
+    }
+
+    #[test]
+    fn parse_lossless_values() {
+        use PropertyToken::*;
+
+        let values = parse_property_values("\"Fira Sans\", sans-serif").unwrap();
+        assert_eq!(
+            values.to_vec(),
+            vec![
+                String("Fira Sans".to_string()),
+                Comma,
+                Identifier("sans-serif".to_string())
+            ]
+        );
+        assert_eq!(values.comma_separated().count(), 2);
+
+        let values = parse_property_values("repeat(2, minmax(10px, 1fr))").unwrap();
+        assert_eq!(
+            values[0],
+            Function(
+                "repeat".to_string(),
+                vec![
+                    Number(2.0),
+                    Comma,
+                    Function("minmax".to_string(), vec![Dimension(10.0), Comma, Fr(1.0)])
+                ]
+            )
+        );
+
+        let values = parse_property_values("opacity 300ms, transform 2s 90deg 0.5turn").unwrap();
+        assert_eq!(
+            values.to_vec(),
+            vec![
+                Identifier("opacity".to_string()),
+                Time(0.3),
+                Comma,
+                Identifier("transform".to_string()),
+                Time(2.0),
+                Angle(std::f32::consts::FRAC_PI_2),
+                Angle(std::f32::consts::PI),
+            ]
+        );
+
+        for value in ["url(image.png)", "url(\"image.png\")", "URL('image.png')"] {
+            let values = parse_property_values(value).unwrap();
+            assert_eq!(values.url(), Some("image.png"), "{value}");
+        }
+    }
+
+    #[test]
+    fn parse_important_declarations() {
+        use PropertyToken::*;
+
+        let rules = StyleSheetParser::parse(
+            "a { color: red !important; width: 10px; --accent: blue ! IMPORTANT; height: 1px !foo; color: blue; }",
+        );
+        assert_eq!(rules.len(), 1, "Should have a single rule");
+
+        let properties = &rules[0].properties;
+        assert!(
+            properties["color"].is_important(),
+            "Later normal declarations shouldn't override important ones"
+        );
+        assert_eq!(
+            properties["color"].to_vec(),
+            vec![Identifier("red".to_string())]
+        );
+
+        assert!(!properties["width"].is_important());
+        assert!(properties["--accent"].is_important());
+        assert_eq!(
+            properties["--accent"].to_vec(),
+            vec![Identifier("blue".to_string())]
+        );
+        assert!(
+            !properties.contains_key("height"),
+            "Invalid flags should discard the declaration"
+        );
+
+        let both_important =
+            StyleSheetParser::parse("a { color: red !important; color: blue !important; }");
+        assert_eq!(
+            both_important[0].properties["color"].to_vec(),
+            vec![Identifier("blue".to_string())],
+            "Later important declarations should override earlier ones"
+        );
+    }
+
+    #[test]
+    fn parse_layers() {
+        let ParsedSheet { rules, layers, .. } = StyleSheetParser::parse_sheet(
+            r#"
+            @layer base, widgets.buttons;
+            a {}
+            @layer widgets {
+                b {}
+                @layer buttons { c {} }
+            }
+            @layer screens.menu { d {} }
+            @layer { e {} }
+            @layer a, b { f {} }
+            @supports (display: grid) { g {} }
+            @layer;
+            "#,
+        );
+
+        let names = rules
+            .iter()
+            .map(|rule| (rule.selector.to_string(), rule.layer.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("a".to_string(), None),
+                ("b".to_string(), Some("widgets")),
+                ("c".to_string(), Some("widgets.buttons")),
+                ("d".to_string(), Some("screens.menu")),
+                ("e".to_string(), Some("@1")),
+            ]
+        );
+        assert_eq!(
+            layers,
+            vec![
+                "base",
+                "widgets",
+                "widgets.buttons",
+                "screens",
+                "screens.menu",
+                "@1"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_media_rules() {
+        let rules = StyleSheetParser::parse(
+            r#"
+            a {}
+            @media (min-width: 800px) {
+                b {}
+                @layer widgets {
+                    @media (orientation: portrait), print { c {} }
+                }
+            }
+            @media { d {} }
+            @container (min-width: 300px) {
+                e {}
+                @media print { @container (orientation: portrait) { f {} } }
+            }
+            @container card (min-width: 300px) { g {} }
+            @container { h {} }
+            "#,
+        );
+
+        let media = rules
+            .iter()
+            .map(|rule| {
+                (
+                    rule.selector.to_string(),
+                    rule.layer.as_deref(),
+                    rule.media
+                        .iter()
+                        .map(|list| list.0.len())
+                        .collect::<Vec<_>>(),
+                    rule.containers.len(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            media,
+            vec![
+                ("a".to_string(), None, vec![], 0),
+                ("b".to_string(), None, vec![1], 0),
+                ("c".to_string(), Some("widgets"), vec![1, 2], 0),
+                ("d".to_string(), None, vec![0], 0),
+                ("e".to_string(), None, vec![], 1),
+                ("f".to_string(), None, vec![1], 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_imports() {
+        let ParsedSheet {
+            rules,
+            layers,
+            imports,
+        } = StyleSheetParser::parse_sheet(
+            r#"
+            @layer theme;
+            @import "base.css";
+            @import url(widgets/buttons.css) layer(widgets.buttons);
+            @import url("screens.css") layer (orientation: portrait), print;
+            a {}
+            @import "ignored.css";
+            @media screen { @import "ignored.css"; }
+            "#,
+        );
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(layers, vec!["theme", "widgets", "widgets.buttons", "@1"]);
+        assert_eq!(
+            imports
+                .iter()
+                .map(|import| (
+                    import.path.as_str(),
+                    import.layer.as_deref(),
+                    import.media.0.len(),
+                    import.layers_before
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("base.css", None, 0, 1),
+                ("widgets/buttons.css", Some("widgets.buttons"), 0, 3),
+                ("screens.css", Some("@1"), 2, 4),
+            ]
+        );
+    }
+
     #[test]
     fn parse_multiple_rules() {
         let rules = StyleSheetParser::parse(r#"a{a:a}a{a:a}a{a:a}a{a:a}"#);
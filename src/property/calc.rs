@@ -0,0 +1,859 @@
+use bevy::{
+    ecs::change_detection::DetectChangesMut,
+    math::Vec2,
+    prelude::{
+        Changed, Commands, Component, DetectChanges, Entity, EntityWorldMut, Local, Mut, Or,
+        ParamSet, Parent, Query, Ref, Text, TextStyle, Window, With,
+    },
+    ui::{Node, Style, Val},
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+/// The unit of a single value inside a [`CalcExpr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub enum CalcUnit {
+    /// A unitless number, like `2`.
+    Number,
+    /// A pixel value, like `10px`.
+    Px,
+    /// A percent value, like `50%`.
+    Percent,
+    /// A viewport width value, like `10vw`.
+    Vw,
+    /// A viewport height value, like `10vh`.
+    Vh,
+    /// A minimum viewport axis value, like `10vmin`.
+    VMin,
+    /// A maximum viewport axis value, like `10vmax`.
+    VMax,
+    /// A value relative to the inherited font size, like `2em`.
+    Em,
+    /// A value relative to the root font size, like `2rem`.
+    Rem,
+}
+
+/// How many [`CalcUnit`] variants there are.
+const UNITS: usize = 9;
+
+/// A value which is the sum of each [`CalcUnit`] coefficient, like `100% - 40px`.
+type Linear = [f32; UNITS];
+
+/// A math expression parsed from `calc()`, `min()`, `max()` or `clamp()` functions.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum CalcExpr {
+    /// A single value, like `10px`.
+    Value(f32, CalcUnit),
+    /// A keyword, which is either a constant, like `pi` or `e`, or a channel of a relative color, like `r` or `h`.
+    Keyword(String),
+    /// The sum of two expressions, like `a + b`.
+    Add(Box<CalcExpr>, Box<CalcExpr>),
+    /// The subtraction of two expressions, like `a - b`.
+    Sub(Box<CalcExpr>, Box<CalcExpr>),
+    /// The product of two expressions, like `a * b`, where at least one must be a number.
+    Mul(Box<CalcExpr>, Box<CalcExpr>),
+    /// The division of two expressions, like `a / b`, where the divisor must be a number.
+    Div(Box<CalcExpr>, Box<CalcExpr>),
+    /// The smallest of the expressions, like `min(a, b)`.
+    Min(Vec<CalcExpr>),
+    /// The largest of the expressions, like `max(a, b)`.
+    Max(Vec<CalcExpr>),
+    /// The preferred value clamped between a minimum and a maximum, like `clamp(min, val, max)`.
+    Clamp(Box<CalcExpr>, Box<CalcExpr>, Box<CalcExpr>),
+}
+
+/// Values used to resolve units which depends on where the expression is applied.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CalcBasis {
+    /// The size which `100%` refers to, if percentages are allowed.
+    pub percent: Option<f32>,
+    /// The primary window size, used by viewport units.
+    pub viewport: Vec2,
+    /// The inherited font size, used by `em` unit.
+    pub em: f32,
+    /// The root font size, used by `rem` unit.
+    pub rem: f32,
+}
+
+impl CalcExpr {
+    /// Tries to simplify this expression into a single [`Val`].
+    ///
+    /// This is only possible when all values shares the same unit, like `calc(100% / 3)`, and
+    /// the unit isn't relative to font size.
+    pub fn to_val(&self) -> Option<Val> {
+        let linear = self.linear()?;
+        let lengths = linear
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, v)| **v != 0.0)
+            .collect::<Vec<_>>();
+
+        if linear[CalcUnit::Number as usize] != 0.0
+            || linear[CalcUnit::Em as usize..].iter().any(|v| *v != 0.0)
+        {
+            return None;
+        }
+
+        // Each length unit, in the same order as [`CalcUnit`], skipping [`CalcUnit::Number`].
+        const VALS: [fn(f32) -> Val; 6] = [
+            Val::Px,
+            Val::Percent,
+            Val::Vw,
+            Val::Vh,
+            Val::VMin,
+            Val::VMax,
+        ];
+
+        match lengths.as_slice() {
+            [] => Some(Val::Px(0.0)),
+            [(unit, &value)] => Some(VALS[unit - 1](value)),
+            _ => None,
+        }
+    }
+
+    /// Tries to simplify this expression into a single [`f32`].
+    ///
+    /// This is only possible when all values are numbers or pixels, like `calc(12px * 2)`.
+    pub fn to_f32(&self) -> Option<f32> {
+        let linear = self.linear()?;
+        let (number, px) = (
+            linear[CalcUnit::Number as usize],
+            linear[CalcUnit::Px as usize],
+        );
+
+        linear[CalcUnit::Percent as usize..]
+            .iter()
+            .all(|v| *v == 0.0)
+            .then_some(number + px)
+    }
+
+    /// Checks if this expression has any [`Keyword`](CalcExpr::Keyword) which isn't a constant, like `r`.
+    pub fn has_variables(&self) -> bool {
+        match self {
+            CalcExpr::Value(..) => false,
+            CalcExpr::Keyword(keyword) => constant(keyword).is_none(),
+            CalcExpr::Add(a, b)
+            | CalcExpr::Sub(a, b)
+            | CalcExpr::Mul(a, b)
+            | CalcExpr::Div(a, b) => a.has_variables() || b.has_variables(),
+            CalcExpr::Min(args) | CalcExpr::Max(args) => args.iter().any(CalcExpr::has_variables),
+            CalcExpr::Clamp(min, val, max) => {
+                min.has_variables() || val.has_variables() || max.has_variables()
+            }
+        }
+    }
+
+    /// Evaluates this expression into pixels, using the given basis to resolve relative units.
+    ///
+    /// Returns [`None`] if there is a percent value and percentages aren't allowed or if there is
+    /// a division by zero.
+    pub fn eval(&self, basis: &CalcBasis) -> Option<f32> {
+        self.eval_with(basis, &|_| None)
+    }
+
+    /// Evaluates this expression like [`eval`](CalcExpr::eval), using `keywords` to resolve any
+    /// [`Keyword`](CalcExpr::Keyword) which isn't a constant.
+    pub fn eval_with(
+        &self,
+        basis: &CalcBasis,
+        keywords: &dyn Fn(&str) -> Option<f32>,
+    ) -> Option<f32> {
+        let eval = |expr: &CalcExpr| expr.eval_with(basis, keywords);
+        let value = match self {
+            CalcExpr::Value(value, unit) => match unit {
+                CalcUnit::Number | CalcUnit::Px => *value,
+                CalcUnit::Percent => basis.percent? * value / 100.0,
+                CalcUnit::Vw => basis.viewport.x * value / 100.0,
+                CalcUnit::Vh => basis.viewport.y * value / 100.0,
+                CalcUnit::VMin => basis.viewport.min_element() * value / 100.0,
+                CalcUnit::VMax => basis.viewport.max_element() * value / 100.0,
+                CalcUnit::Em => basis.em * value,
+                CalcUnit::Rem => basis.rem * value,
+            },
+            CalcExpr::Keyword(keyword) => constant(keyword).or_else(|| keywords(keyword))?,
+            CalcExpr::Add(a, b) => eval(a)? + eval(b)?,
+            CalcExpr::Sub(a, b) => eval(a)? - eval(b)?,
+            CalcExpr::Mul(a, b) => eval(a)? * eval(b)?,
+            CalcExpr::Div(a, b) => {
+                let divisor = eval(b)?;
+                if divisor == 0.0 {
+                    return None;
+                }
+                eval(a)? / divisor
+            }
+            CalcExpr::Min(args) => args.iter().map(eval).reduce(|a, b| Some(a?.min(b?)))??,
+            CalcExpr::Max(args) => args.iter().map(eval).reduce(|a, b| Some(a?.max(b?)))??,
+            CalcExpr::Clamp(min, val, max) => {
+                let (min, max) = (eval(min)?, eval(max)?);
+                eval(val)?.min(max).max(min)
+            }
+        };
+
+        Some(value)
+    }
+
+    /// Simplifies this expression into the sum of each unit coefficient.
+    ///
+    /// Returns [`None`] if the expression can't be simplified, like `min(10px, 5%)`, or is invalid,
+    /// like `10px * 10px`.
+    fn linear(&self) -> Option<Linear> {
+        match self {
+            CalcExpr::Value(value, unit) => Some(single(*unit as usize, *value)),
+            CalcExpr::Keyword(keyword) => {
+                Some(single(CalcUnit::Number as usize, constant(keyword)?))
+            }
+            CalcExpr::Add(a, b) => Some(combine(a.linear()?, b.linear()?, |a, b| a + b)),
+            CalcExpr::Sub(a, b) => Some(combine(a.linear()?, b.linear()?, |a, b| a - b)),
+            CalcExpr::Mul(a, b) => {
+                let (a, b) = (a.linear()?, b.linear()?);
+                match (as_number(&a), as_number(&b)) {
+                    (Some(a), _) => Some(b.map(|v| v * a)),
+                    (_, Some(b)) => Some(a.map(|v| v * b)),
+                    _ => None,
+                }
+            }
+            CalcExpr::Div(a, b) => {
+                let divisor = as_number(&b.linear()?).filter(|b| *b != 0.0)?;
+                Some(a.linear()?.map(|v| v / divisor))
+            }
+            CalcExpr::Min(args) => {
+                let (unit, values) = same_unit(args.iter())?;
+                Some(single(unit, values.into_iter().reduce(f32::min)?))
+            }
+            CalcExpr::Max(args) => {
+                let (unit, values) = same_unit(args.iter())?;
+                Some(single(unit, values.into_iter().reduce(f32::max)?))
+            }
+            CalcExpr::Clamp(min, val, max) => {
+                let (unit, values) = same_unit([min, val, max].into_iter().map(Box::as_ref))?;
+                let [min, val, max] = values[..] else {
+                    return None;
+                };
+                Some(single(unit, val.min(max).max(min)))
+            }
+        }
+    }
+}
+
+/// Returns the value of a constant keyword, like `pi`.
+fn constant(keyword: &str) -> Option<f32> {
+    match keyword.to_ascii_lowercase().as_str() {
+        "pi" => Some(std::f32::consts::PI),
+        "e" => Some(std::f32::consts::E),
+        _ => None,
+    }
+}
+
+/// Combines two linear values, unit by unit.
+fn combine(a: Linear, b: Linear, f: impl Fn(f32, f32) -> f32) -> Linear {
+    std::array::from_fn(|i| f(a[i], b[i]))
+}
+
+/// Returns the number if the given linear value is a plain number.
+fn as_number(linear: &Linear) -> Option<f32> {
+    linear[1..]
+        .iter()
+        .all(|v| *v == 0.0)
+        .then_some(linear[CalcUnit::Number as usize])
+}
+
+/// Returns the only unit used by the given linear value or [`None`] if it's zero.
+/// Returns an error if it has more than one unit.
+fn single_unit(linear: &Linear) -> Result<Option<usize>, ()> {
+    let mut units = linear.iter().enumerate().filter(|(_, v)| **v != 0.0);
+    match (units.next(), units.next()) {
+        (None, _) => Ok(None),
+        (Some((unit, _)), None) => Ok(Some(unit)),
+        _ => Err(()),
+    }
+}
+
+/// Returns the unit shared by all expressions and their values on that unit.
+/// Zero values may have any unit. Returns [`None`] if the expressions have different units.
+fn same_unit<'a>(args: impl Iterator<Item = &'a CalcExpr>) -> Option<(usize, Vec<f32>)> {
+    let linears = args.map(CalcExpr::linear).collect::<Option<Vec<_>>>()?;
+
+    let mut unit = None;
+    for linear in &linears {
+        match (single_unit(linear).ok()?, unit) {
+            (Some(u), Some(other)) if u != other => return None,
+            (Some(u), _) => unit = Some(u),
+            (None, _) => (),
+        }
+    }
+
+    let unit = unit.unwrap_or(CalcUnit::Px as usize);
+    Some((unit, linears.iter().map(|l| l[unit]).collect()))
+}
+
+/// Creates a linear value with a single unit.
+fn single(unit: usize, value: f32) -> Linear {
+    let mut linear = [0.0; UNITS];
+    linear[unit] = value;
+    linear
+}
+
+/// Converts an absolute length, like `2cm` or `12pt`, into pixels, where `1in` is `96px`.
+///
+/// Returns [`None`] if the given unit isn't an absolute length unit.
+pub(crate) fn absolute_length(value: f32, unit: &str) -> Option<f32> {
+    let px_per_unit = match unit.to_ascii_lowercase().as_str() {
+        "px" => 1.0,
+        "in" => 96.0,
+        "cm" => 96.0 / 2.54,
+        "mm" => 96.0 / 25.4,
+        "q" => 96.0 / 101.6,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+
+    Some(value * px_per_unit)
+}
+
+/// Which axis of the parent node size `100%` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalcAxis {
+    Width,
+    Height,
+}
+
+/// Where the result of a [`CalcExpr`] resolved at runtime is written to.
+#[derive(Debug, Clone, Copy)]
+pub enum CalcTarget {
+    /// A [`Val`] field of [`Style`] component, returned by the given function.
+    Style(CalcAxis, fn(&mut Style) -> &mut Val),
+    /// The [`ComputedFontSize`] and the font size of all sections of [`Text`] component. Percentages aren't allowed.
+    FontSize,
+}
+
+/// Math expressions, mixing units which can't be expressed by a single [`Val`], like `calc(100% - 40px)`.
+///
+/// This component is inserted by properties which accepts math expressions and each expression is
+/// resolved against the parent node and primary window size, so it's re-evaluated when any of them
+/// is resized.
+#[derive(Debug, Component, Default, Clone)]
+pub struct CalcValues(HashMap<&'static str, (CalcExpr, CalcTarget)>);
+
+impl CalcValues {
+    /// Sets the expression to be resolved for the given property name.
+    pub fn insert(&mut self, property: &'static str, expr: CalcExpr, target: CalcTarget) {
+        self.0.insert(property, (expr, target));
+    }
+
+    /// Removes the expression of the given property name, if any.
+    pub fn remove(&mut self, property: &str) -> bool {
+        self.0.remove(property).is_some()
+    }
+
+    /// Returns the expression of the given property name, if any.
+    pub fn get(&self, property: &str) -> Option<&CalcExpr> {
+        self.0.get(property).map(|(expr, _)| expr)
+    }
+
+    /// Checks if there is no expression to be resolved.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The font size declared or inherited by the `font-size` property, used to resolve `em` and `rem` units.
+///
+/// Unlike [`TextStyle::font_size`](`TextStyle`), this component is also kept on nodes without [`Text`],
+/// so any node can be used as a font size basis for it's own `em` units and those of it's descendants.
+#[derive(Debug, Component, Clone, Copy, PartialEq)]
+pub struct ComputedFontSize(pub f32);
+
+impl Default for ComputedFontSize {
+    fn default() -> Self {
+        Self(default_font_size())
+    }
+}
+
+/// Inserts the expression of the given property on entity [`CalcValues`], adding the component if needed.
+pub(crate) fn insert_calc_value(
+    commands: &mut Commands,
+    entity: Entity,
+    calc: Option<Mut<CalcValues>>,
+    property: &'static str,
+    expr: CalcExpr,
+    target: CalcTarget,
+) {
+    match calc {
+        Some(mut calc) => calc.insert(property, expr, target),
+        // Many properties may insert an expression on the same frame, so merge with any existing component.
+        None => {
+            commands
+                .entity(entity)
+                .add(
+                    move |mut entity: EntityWorldMut| match entity.get_mut::<CalcValues>() {
+                        Some(mut calc) => calc.insert(property, expr, target),
+                        None => {
+                            let mut calc = CalcValues::default();
+                            calc.insert(property, expr, target);
+                            entity.insert(calc);
+                        }
+                    },
+                );
+        }
+    }
+}
+
+/// Removes the expressions of the given properties from entity [`CalcValues`], if any.
+pub(crate) fn remove_calc_values(calc: Option<Mut<CalcValues>>, properties: &[&str]) {
+    if let Some(mut calc) = calc {
+        for property in properties {
+            if calc.bypass_change_detection().0.contains_key(*property) {
+                calc.remove(property);
+            }
+        }
+    }
+}
+
+/// Font size used by `em` and `rem` units when there is no font size to inherit from.
+fn default_font_size() -> f32 {
+    TextStyle::default().font_size
+}
+
+/// Queries the hierarchy and the font size of entities, alongside whether the font size was changed.
+type FontQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Parent>,
+        Option<Ref<'static, ComputedFontSize>>,
+        Option<Ref<'static, Text>>,
+    ),
+>;
+
+/// Returns the font size of the given entity, preferring it's [`ComputedFontSize`] over the first [`Text`] section,
+/// and whether it was changed since the last run.
+fn own_font_size(
+    computed: Option<Ref<ComputedFontSize>>,
+    text: Option<Ref<Text>>,
+) -> Option<(f32, bool)> {
+    if let Some(computed) = computed {
+        return Some((computed.0, computed.is_changed()));
+    }
+
+    let text = text?;
+    let section = text.sections.first()?;
+    Some((section.style.font_size, text.is_changed()))
+}
+
+/// Returns the first font size found on the given entity or on any of it's ancestors.
+fn inherited_font_size(q_fonts: &FontQuery, entity: Entity) -> Option<(f32, bool)> {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        let (parent, computed, text) = q_fonts.get(entity).ok()?;
+        if let Some(size) = own_font_size(computed, text) {
+            return Some(size);
+        }
+        current = parent.map(Parent::get);
+    }
+    None
+}
+
+/// Depth and root of visited entities and font size of visited roots, so each hierarchy is walked only once per run.
+#[derive(Default)]
+struct HierarchyCache {
+    ancestry: HashMap<Entity, (usize, Entity)>,
+    root_font_sizes: HashMap<Entity, (f32, bool)>,
+}
+
+impl HierarchyCache {
+    /// Returns the number of ancestors and the root ancestor of the given entity.
+    fn ancestry(&mut self, q_fonts: &FontQuery, entity: Entity) -> (usize, Entity) {
+        let mut chain = Vec::new();
+        let mut current = Some(entity);
+        let mut known = None;
+        while let Some(entity) = current {
+            if let Some(ancestry) = self.ancestry.get(&entity) {
+                known = Some(*ancestry);
+                break;
+            }
+            chain.push(entity);
+            current = q_fonts
+                .get(entity)
+                .ok()
+                .and_then(|(parent, ..)| parent)
+                .map(Parent::get);
+        }
+
+        let (mut depth, root) = match (known, chain.last()) {
+            (Some((depth, root)), _) => (depth + 1, root),
+            (None, Some(root)) => (0, *root),
+            (None, None) => unreachable!("The chain always contains the given entity"),
+        };
+        for entity in chain.into_iter().rev() {
+            self.ancestry.insert(entity, (depth, root));
+            depth += 1;
+        }
+
+        self.ancestry[&entity]
+    }
+
+    /// Returns the font size of the root ancestor of the given entity, and whether it was changed.
+    fn root_font_size(&mut self, q_fonts: &FontQuery, entity: Entity) -> (f32, bool) {
+        let (_, root) = self.ancestry(q_fonts, entity);
+        *self.root_font_sizes.entry(root).or_insert_with(|| {
+            q_fonts
+                .get(root)
+                .ok()
+                .and_then(|(_, computed, text)| own_font_size(computed, text))
+                .unwrap_or((default_font_size(), false))
+        })
+    }
+}
+
+/// Resolves [`CalcValues`] expressions and writes the result on the target components, only when the value was changed.
+///
+/// Expressions are only resolved again when the primary window is resized, the expressions, the parent or the parent
+/// [`Node`] are changed, or the font sizes `em` and `rem` units refer to are changed.
+///
+/// Font sizes are resolved first, from ancestors to descendants, so other expressions using `em` or `rem`
+/// units are resolved against the font sizes of this same run.
+#[allow(clippy::type_complexity)]
+pub(crate) fn resolve_calc_values(
+    mut last_viewport: Local<Vec2>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_nodes: Query<Ref<Node>>,
+    q_calc: Query<(Entity, Ref<CalcValues>, Option<Ref<Parent>>)>,
+    mut q_targets: ParamSet<(
+        FontQuery,
+        Query<(
+            Option<&mut Style>,
+            Option<&mut ComputedFontSize>,
+            Option<&mut Text>,
+        )>,
+        Query<
+            (),
+            Or<(
+                Changed<CalcValues>,
+                Changed<Parent>,
+                Changed<Node>,
+                Changed<ComputedFontSize>,
+                Changed<Text>,
+            )>,
+        >,
+    )>,
+) {
+    let viewport = q_windows
+        .get_single()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or_default();
+    let resized = *last_viewport != viewport;
+    *last_viewport = viewport;
+
+    if !resized && q_targets.p2().is_empty() {
+        return;
+    }
+
+    let mut cache = HierarchyCache::default();
+
+    let mut font_sizes = q_calc
+        .iter()
+        .filter_map(|(entity, calc, parent)| {
+            let calc_changed = calc.is_changed();
+            let (expr, _) = calc
+                .into_inner()
+                .0
+                .values()
+                .find(|(_, target)| matches!(target, CalcTarget::FontSize))?;
+            let (depth, _) = cache.ancestry(&q_targets.p0(), entity);
+            Some((depth, entity, expr, calc_changed, parent))
+        })
+        .collect::<Vec<_>>();
+    font_sizes.sort_by_key(|(depth, ..)| *depth);
+
+    for (depth, entity, expr, calc_changed, parent) in font_sizes {
+        let value = {
+            let q_fonts = q_targets.p0();
+            let (rem, rem_changed) = cache.root_font_size(&q_fonts, entity);
+            // Font size can't be relative to itself, so `em` refers to the parent font size.
+            let (em, em_changed) = parent
+                .as_ref()
+                .and_then(|parent| inherited_font_size(&q_fonts, parent.get()))
+                .unwrap_or((rem, rem_changed));
+
+            let parent_changed = parent.as_ref().is_some_and(|parent| parent.is_changed());
+            if !(resized || calc_changed || parent_changed || em_changed || rem_changed) {
+                continue;
+            }
+
+            let basis = CalcBasis {
+                percent: None,
+                viewport,
+                em,
+                rem,
+            };
+            expr.eval(&basis)
+        };
+
+        let mut q_targets = q_targets.p1();
+        let (Some(value), Ok((_, computed, text))) = (value, q_targets.get_mut(entity)) else {
+            continue;
+        };
+
+        if let Some(mut computed) = computed {
+            computed.set_if_neq(ComputedFontSize(value));
+        }
+
+        if let Some(mut text) = text {
+            if text
+                .sections
+                .iter()
+                .any(|section| section.style.font_size != value)
+            {
+                text.sections
+                    .iter_mut()
+                    .for_each(|section| section.style.font_size = value);
+            }
+        }
+
+        // Roots are the `rem` basis of their own descendants, which are resolved next.
+        if depth == 0 {
+            cache.root_font_sizes.remove(&entity);
+        }
+    }
+
+    let resolved = {
+        let q_fonts = q_targets.p0();
+        q_calc
+            .iter()
+            .filter_map(|(entity, calc, parent)| {
+                let parent_node = parent
+                    .as_ref()
+                    .and_then(|parent| q_nodes.get(parent.get()).ok());
+                let parent_size = parent_node
+                    .as_ref()
+                    .map(|node| node.size())
+                    .unwrap_or(viewport);
+                let (rem, rem_changed) = cache.root_font_size(&q_fonts, entity);
+                let (em, em_changed) =
+                    inherited_font_size(&q_fonts, entity).unwrap_or((rem, rem_changed));
+
+                let changed = resized
+                    || calc.is_changed()
+                    || parent.as_ref().is_some_and(|parent| parent.is_changed())
+                    || parent_node.is_some_and(|node| node.is_changed())
+                    || em_changed
+                    || rem_changed;
+                if !changed {
+                    return None;
+                }
+
+                let values = calc
+                    .0
+                    .values()
+                    .filter_map(|(expr, target)| match target {
+                        CalcTarget::Style(axis, field) => {
+                            let basis = CalcBasis {
+                                percent: Some(match axis {
+                                    CalcAxis::Width => parent_size.x,
+                                    CalcAxis::Height => parent_size.y,
+                                }),
+                                viewport,
+                                em,
+                                rem,
+                            };
+                            Some((*field, expr.eval(&basis)?))
+                        }
+                        CalcTarget::FontSize => None,
+                    })
+                    .collect::<Vec<_>>();
+
+                Some((entity, values))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut q_targets = q_targets.p1();
+    for (entity, values) in resolved {
+        let Ok((Some(mut style), ..)) = q_targets.get_mut(entity) else {
+            continue;
+        };
+
+        for (field, value) in values {
+            if *field(style.bypass_change_detection()) != Val::Px(value) {
+                *field(&mut style) = Val::Px(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: f32, unit: CalcUnit) -> Box<CalcExpr> {
+        Box::new(CalcExpr::Value(value, unit))
+    }
+
+    #[test]
+    fn simplify_same_unit() {
+        use CalcUnit::*;
+
+        let expr = CalcExpr::Div(value(100.0, Percent), value(4.0, Number));
+        assert_eq!(expr.to_val(), Some(Val::Percent(25.0)));
+
+        let expr = CalcExpr::Add(value(10.0, Px), value(20.0, Px));
+        assert_eq!(expr.to_val(), Some(Val::Px(30.0)));
+        assert_eq!(expr.to_f32(), Some(30.0));
+
+        let expr = CalcExpr::Clamp(value(12.0, Px), value(30.0, Px), value(24.0, Px));
+        assert_eq!(expr.to_val(), Some(Val::Px(24.0)));
+
+        let expr = CalcExpr::Min(vec![CalcExpr::Value(10.0, Vw), CalcExpr::Value(5.0, Vw)]);
+        assert_eq!(expr.to_val(), Some(Val::Vw(5.0)));
+
+        let expr = CalcExpr::Sub(value(100.0, Percent), value(40.0, Px));
+        assert_eq!(expr.to_val(), None);
+        assert_eq!(expr.to_f32(), None);
+
+        let expr = CalcExpr::Mul(value(10.0, Px), value(10.0, Px));
+        assert_eq!(expr.to_val(), None);
+
+        let expr = CalcExpr::Div(value(10.0, Px), value(0.0, Number));
+        assert_eq!(expr.to_val(), None);
+    }
+
+    #[test]
+    fn eval_mixed_units() {
+        use CalcUnit::*;
+
+        let basis = CalcBasis {
+            percent: Some(200.0),
+            viewport: Vec2::new(1000.0, 500.0),
+            em: 16.0,
+            rem: 20.0,
+        };
+
+        let expr = CalcExpr::Sub(value(100.0, Percent), value(40.0, Px));
+        assert_eq!(expr.eval(&basis), Some(160.0));
+
+        let expr = CalcExpr::Clamp(value(12.0, Px), value(2.0, Vw), value(24.0, Px));
+        assert_eq!(expr.eval(&basis), Some(20.0));
+
+        let expr = CalcExpr::Max(vec![
+            CalcExpr::Value(10.0, VMin),
+            CalcExpr::Value(10.0, Percent),
+        ]);
+        assert_eq!(expr.eval(&basis), Some(50.0));
+
+        let expr = CalcExpr::Add(value(2.0, Em), value(0.5, Rem));
+        assert_eq!(expr.eval(&basis), Some(42.0));
+        assert_eq!(expr.to_val(), None);
+
+        let expr = CalcExpr::Add(value(10.0, Percent), value(1.0, Px));
+        assert_eq!(expr.eval(&CalcBasis::default()), None);
+    }
+
+    #[test]
+    fn resolve_font_relative_units() {
+        use bevy::{ecs::system::RunSystemOnce, prelude::BuildWorldChildren, prelude::World};
+
+        let mut world = World::new();
+
+        let text = |size| {
+            Text::from_section(
+                "",
+                TextStyle {
+                    font_size: size,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let mut calc = CalcValues::default();
+        calc.insert(
+            "width",
+            CalcExpr::Add(value(2.0, CalcUnit::Em), value(1.0, CalcUnit::Rem)),
+            CalcTarget::Style(CalcAxis::Width, |style| &mut style.width),
+        );
+        calc.insert("font-size", *value(2.0, CalcUnit::Em), CalcTarget::FontSize);
+
+        let mut panel_calc = CalcValues::default();
+        panel_calc.insert("font-size", *value(3.0, CalcUnit::Em), CalcTarget::FontSize);
+
+        // Descendants are spawned first, so font sizes must be resolved on hierarchy order.
+        let label = world.spawn((Style::default(), text(0.0), calc)).id();
+        let panel = world.spawn((Style::default(), text(0.0), panel_calc)).id();
+        let root = world.spawn((Style::default(), text(10.0))).id();
+        world.entity_mut(root).add_child(panel);
+        world.entity_mut(panel).add_child(label);
+
+        world.run_system_once(resolve_calc_values);
+
+        // `em` of font-size refers to the parent font size.
+        let font_size = |world: &World, entity| {
+            world.get::<Text>(entity).unwrap().sections[0]
+                .style
+                .font_size
+        };
+        assert_eq!(font_size(&world, panel), 30.0);
+        assert_eq!(font_size(&world, label), 60.0);
+
+        // Font sizes are resolved before `em` of other properties.
+        assert_eq!(world.get::<Style>(label).unwrap().width, Val::Px(130.0));
+    }
+
+    #[test]
+    fn resolve_on_window_resize() {
+        use bevy::{ecs::system::RunSystemOnce, prelude::World};
+
+        let mut world = World::new();
+        let window = world.spawn((Window::default(), PrimaryWindow)).id();
+
+        let mut calc = CalcValues::default();
+        calc.insert(
+            "width",
+            CalcExpr::Sub(value(50.0, CalcUnit::Vw), value(40.0, CalcUnit::Px)),
+            CalcTarget::Style(CalcAxis::Width, |style| &mut style.width),
+        );
+        let entity = world.spawn((Style::default(), calc)).id();
+
+        world.run_system_once(resolve_calc_values);
+        assert_eq!(world.get::<Style>(entity).unwrap().width, Val::Px(600.0));
+
+        world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set(800.0, 600.0);
+        world.run_system_once(resolve_calc_values);
+        assert_eq!(world.get::<Style>(entity).unwrap().width, Val::Px(360.0));
+    }
+
+    #[test]
+    fn resolve_only_on_changes() {
+        use bevy::prelude::{BuildWorldChildren, Schedule, World};
+
+        let mut world = World::new();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(resolve_calc_values);
+
+        let mut calc = CalcValues::default();
+        calc.insert(
+            "width",
+            CalcExpr::Add(value(2.0, CalcUnit::Em), value(1.0, CalcUnit::Px)),
+            CalcTarget::Style(CalcAxis::Width, |style| &mut style.width),
+        );
+        let entity = world.spawn((Style::default(), calc)).id();
+        let root = world.spawn(ComputedFontSize(10.0)).id();
+        world.entity_mut(root).add_child(entity);
+
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Style>(entity).unwrap().width, Val::Px(21.0));
+
+        // Nothing the expression depends on was changed, so it isn't resolved again.
+        world.get_mut::<Style>(entity).unwrap().width = Val::Auto;
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Style>(entity).unwrap().width, Val::Auto);
+
+        world.get_mut::<ComputedFontSize>(root).unwrap().0 = 20.0;
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Style>(entity).unwrap().width, Val::Px(41.0));
+    }
+}
@@ -1,4 +1,9 @@
-use bevy::prelude::Color;
+use bevy::{
+    color::{ColorToComponents, Hsla, Hwba, Laba, Lcha, LinearRgba, Oklaba, Oklcha, Srgba, Xyza},
+    prelude::Color,
+};
+
+use super::{calc::CalcBasis, PropertyToken};
 
 pub(super) fn parse_hex_color(hex: &str) -> Option<Color> {
     if let Ok((r, g, b, a)) = cssparser::color::parse_hash_color(hex.as_bytes()) {
@@ -26,3 +31,575 @@ pub(super) fn parse_named_color(name: &str) -> Option<Color> {
         None
     }
 }
+
+/// Parses a single color token, which may be a named color, a hex color or a color function,
+/// like `oklch(70% 0.1 200)` or `color-mix(in oklab, red, blue)`.
+pub(super) fn parse_color(token: &PropertyToken) -> Option<Color> {
+    match token {
+        PropertyToken::Identifier(name) => parse_named_color(name),
+        PropertyToken::Hash(hash) => parse_hex_color(hash),
+        PropertyToken::Function(name, args) => parse_color_function(name, args),
+        _ => None,
+    }
+}
+
+/// A color space which colors can be specified or mixed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorSpace {
+    /// sRGB, with channels ranging from `0` to `255`, used by `rgb()` function.
+    Rgb,
+    /// sRGB, with channels ranging from `0` to `1`, used by `color(srgb ...)` function.
+    Srgb,
+    SrgbLinear,
+    Xyz,
+    Hsl,
+    Hwb,
+    Lab,
+    Lch,
+    Oklab,
+    Oklch,
+}
+
+impl ColorSpace {
+    /// Parses a color space name, used by `color()` and `color-mix()` functions.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "srgb" => Some(Self::Srgb),
+            "srgb-linear" => Some(Self::SrgbLinear),
+            "xyz" | "xyz-d65" => Some(Self::Xyz),
+            "hsl" => Some(Self::Hsl),
+            "hwb" => Some(Self::Hwb),
+            "lab" => Some(Self::Lab),
+            "lch" => Some(Self::Lch),
+            "oklab" => Some(Self::Oklab),
+            "oklch" => Some(Self::Oklch),
+            _ => None,
+        }
+    }
+
+    /// Name of each channel, used as keywords by relative colors, like `rgb(from red r g 0)`.
+    fn channels(self) -> [&'static str; 3] {
+        match self {
+            Self::Rgb | Self::Srgb | Self::SrgbLinear => ["r", "g", "b"],
+            Self::Xyz => ["x", "y", "z"],
+            Self::Hsl => ["h", "s", "l"],
+            Self::Hwb => ["h", "w", "b"],
+            Self::Lab | Self::Oklab => ["l", "a", "b"],
+            Self::Lch | Self::Oklch => ["l", "c", "h"],
+        }
+    }
+
+    /// Index of the hue channel, if this is a polar color space.
+    fn hue(self) -> Option<usize> {
+        match self {
+            Self::Hsl | Self::Hwb => Some(0),
+            Self::Lch | Self::Oklch => Some(2),
+            _ => None,
+        }
+    }
+
+    /// Value which `100%` refers to on each channel. Hue channels don't accept percentages.
+    fn percent_reference(self) -> [f32; 3] {
+        match self {
+            Self::Rgb => [255.0; 3],
+            Self::Srgb | Self::SrgbLinear | Self::Xyz => [1.0; 3],
+            Self::Hsl | Self::Hwb => [0.0, 100.0, 100.0],
+            Self::Lab => [100.0, 125.0, 125.0],
+            Self::Lch => [100.0, 150.0, 0.0],
+            Self::Oklab => [1.0, 0.4, 0.4],
+            Self::Oklch => [1.0, 0.4, 0.0],
+        }
+    }
+
+    /// Converts the given color into this color space channels, on the same scale used by CSS, and alpha.
+    fn to_channels(self, color: Color) -> [f32; 4] {
+        match self {
+            Self::Rgb => {
+                let Srgba {
+                    red,
+                    green,
+                    blue,
+                    alpha,
+                } = color.into();
+                [red * 255.0, green * 255.0, blue * 255.0, alpha]
+            }
+            Self::Srgb => Srgba::from(color).to_f32_array(),
+            Self::SrgbLinear => LinearRgba::from(color).to_f32_array(),
+            Self::Xyz => {
+                let Xyza { x, y, z, alpha } = color.into();
+                [x, y, z, alpha]
+            }
+            Self::Hsl => {
+                let Hsla {
+                    hue,
+                    saturation,
+                    lightness,
+                    alpha,
+                } = color.into();
+                [hue, saturation * 100.0, lightness * 100.0, alpha]
+            }
+            Self::Hwb => {
+                let Hwba {
+                    hue,
+                    whiteness,
+                    blackness,
+                    alpha,
+                } = color.into();
+                [hue, whiteness * 100.0, blackness * 100.0, alpha]
+            }
+            Self::Lab => {
+                let Laba {
+                    lightness,
+                    a,
+                    b,
+                    alpha,
+                } = color.into();
+                [lightness * 100.0, a * 100.0, b * 100.0, alpha]
+            }
+            Self::Lch => {
+                let Lcha {
+                    lightness,
+                    chroma,
+                    hue,
+                    alpha,
+                } = color.into();
+                [lightness * 100.0, chroma * 100.0, hue, alpha]
+            }
+            Self::Oklab => {
+                let Oklaba {
+                    lightness,
+                    a,
+                    b,
+                    alpha,
+                } = color.into();
+                [lightness, a, b, alpha]
+            }
+            Self::Oklch => {
+                let Oklcha {
+                    lightness,
+                    chroma,
+                    hue,
+                    alpha,
+                } = color.into();
+                [lightness, chroma, hue, alpha]
+            }
+        }
+    }
+
+    /// Creates a color on this color space from channels on the same scale used by CSS, and alpha.
+    fn to_color(self, [c0, c1, c2, alpha]: [f32; 4]) -> Color {
+        let hue = |hue: f32| hue.rem_euclid(360.0);
+        match self {
+            Self::Rgb => Srgba::new(c0 / 255.0, c1 / 255.0, c2 / 255.0, alpha).into(),
+            Self::Srgb => Srgba::new(c0, c1, c2, alpha).into(),
+            Self::SrgbLinear => LinearRgba::new(c0, c1, c2, alpha).into(),
+            Self::Xyz => Xyza::new(c0, c1, c2, alpha).into(),
+            Self::Hsl => Hsla::new(hue(c0), c1 / 100.0, c2 / 100.0, alpha).into(),
+            Self::Hwb => Hwba::new(hue(c0), c1 / 100.0, c2 / 100.0, alpha).into(),
+            Self::Lab => Laba::new(c0 / 100.0, c1 / 100.0, c2 / 100.0, alpha).into(),
+            Self::Lch => Lcha::new(c0 / 100.0, c1 / 100.0, hue(c2), alpha).into(),
+            Self::Oklab => Oklaba::new(c0, c1, c2, alpha).into(),
+            Self::Oklch => Oklcha::new(c0, c1, hue(c2), alpha).into(),
+        }
+    }
+}
+
+/// Parses a color function, like `rgb(255 0 0 / 50%)` or `hsl(from red h s 30%)`.
+fn parse_color_function(name: &str, args: &[PropertyToken]) -> Option<Color> {
+    let space = match name.to_ascii_lowercase().as_str() {
+        "rgb" | "rgba" => ColorSpace::Rgb,
+        "hsl" | "hsla" => ColorSpace::Hsl,
+        "hwb" => ColorSpace::Hwb,
+        "lab" => ColorSpace::Lab,
+        "lch" => ColorSpace::Lch,
+        "oklab" => ColorSpace::Oklab,
+        "oklch" => ColorSpace::Oklch,
+        "color" => return parse_color_space_function(args),
+        "color-mix" => return parse_color_mix(args),
+        _ => return None,
+    };
+
+    let (origin, args) = split_origin(args)?;
+
+    // Legacy syntax, like `rgba(255, 0, 0, 0.5)`, is only allowed on absolute colors.
+    let legacy = matches!(space, ColorSpace::Rgb | ColorSpace::Hsl)
+        && origin.is_none()
+        && args.contains(&PropertyToken::Comma);
+
+    if legacy {
+        let args = args
+            .split(|token| *token == PropertyToken::Comma)
+            .map(|arg| match arg {
+                [token] => Some(token),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        match args.as_slice() {
+            [c0, c1, c2] => parse_channels(space, None, [c0, c1, c2], None),
+            [c0, c1, c2, alpha] => parse_channels(space, None, [c0, c1, c2], Some(alpha)),
+            _ => None,
+        }
+    } else {
+        parse_modern_channels(space, origin, args)
+    }
+}
+
+/// Parses a `color()` function, like `color(srgb-linear 1 0.5 0)` or `color(from red xyz x y z)`.
+fn parse_color_space_function(args: &[PropertyToken]) -> Option<Color> {
+    let (origin, args) = split_origin(args)?;
+    let [PropertyToken::Identifier(space), args @ ..] = args else {
+        return None;
+    };
+
+    let space = match ColorSpace::from_name(space)? {
+        space @ (ColorSpace::Srgb | ColorSpace::SrgbLinear | ColorSpace::Xyz) => space,
+        _ => return None,
+    };
+
+    parse_modern_channels(space, origin, args)
+}
+
+/// Splits the origin color of a relative color, like `from red` of `rgb(from red r g 0)`, from the channels.
+fn split_origin(args: &[PropertyToken]) -> Option<(Option<Color>, &[PropertyToken])> {
+    match args {
+        [PropertyToken::Identifier(from), origin, args @ ..]
+            if from.eq_ignore_ascii_case("from") =>
+        {
+            Some((Some(parse_color(origin)?), args))
+        }
+        args => Some((None, args)),
+    }
+}
+
+/// Parses space separated channels, with an optional alpha after a slash, like `255 0 0 / 50%`.
+fn parse_modern_channels(
+    space: ColorSpace,
+    origin: Option<Color>,
+    args: &[PropertyToken],
+) -> Option<Color> {
+    match args {
+        [c0, c1, c2] => parse_channels(space, origin, [c0, c1, c2], None),
+        [c0, c1, c2, PropertyToken::Slash, alpha] => {
+            parse_channels(space, origin, [c0, c1, c2], Some(alpha))
+        }
+        _ => None,
+    }
+}
+
+/// Parses the channels of a color on the given space.
+///
+/// When there is an origin color, it's channels can be referenced by their names, like `r` or `alpha`,
+/// and the origin alpha is used if none is given.
+fn parse_channels(
+    space: ColorSpace,
+    origin: Option<Color>,
+    channels: [&PropertyToken; 3],
+    alpha: Option<&PropertyToken>,
+) -> Option<Color> {
+    let origin = origin.map(|color| space.to_channels(color));
+    let keywords = |name: &str| {
+        let origin = origin?;
+        if name.eq_ignore_ascii_case("alpha") {
+            return Some(origin[3]);
+        }
+        space
+            .channels()
+            .iter()
+            .position(|channel| channel.eq_ignore_ascii_case(name))
+            .map(|idx| origin[idx])
+    };
+
+    let mut values = [0.0; 4];
+    for (idx, token) in channels.into_iter().enumerate() {
+        values[idx] = if space.hue() == Some(idx) {
+            parse_hue(token, &keywords)?
+        } else {
+            parse_number(token, space.percent_reference()[idx], &keywords)?
+        };
+    }
+
+    values[3] = match alpha {
+        Some(alpha) => parse_number(alpha, 1.0, &keywords)?.clamp(0.0, 1.0),
+        None => origin.map_or(1.0, |origin| origin[3]),
+    };
+
+    Some(space.to_color(values))
+}
+
+/// Parses a numeric channel, where percentages are relative to the given reference.
+fn parse_number(
+    token: &PropertyToken,
+    reference: f32,
+    keywords: &dyn Fn(&str) -> Option<f32>,
+) -> Option<f32> {
+    match token {
+        PropertyToken::Number(value) => Some(*value),
+        PropertyToken::Percentage(value) => Some(value / 100.0 * reference),
+        PropertyToken::Identifier(none) if none.eq_ignore_ascii_case("none") => Some(0.0),
+        PropertyToken::Identifier(keyword) => keywords(keyword),
+        PropertyToken::Calc(expr) => {
+            let basis = CalcBasis {
+                percent: Some(reference),
+                ..Default::default()
+            };
+            expr.eval_with(&basis, keywords)
+        }
+        _ => None,
+    }
+}
+
+/// Parses a hue channel in degrees, which may be either a number or an angle.
+fn parse_hue(token: &PropertyToken, keywords: &dyn Fn(&str) -> Option<f32>) -> Option<f32> {
+    match token {
+        PropertyToken::Angle(radians) => Some(radians.to_degrees()),
+        PropertyToken::Percentage(_) => None,
+        token => parse_number(token, 0.0, keywords),
+    }
+}
+
+/// How hues are interpolated by `color-mix()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HueInterpolation {
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+/// Parses a `color-mix()` function, like `color-mix(in oklch longer hue, red 30%, blue)`.
+fn parse_color_mix(args: &[PropertyToken]) -> Option<Color> {
+    let mut args = args.split(|token| *token == PropertyToken::Comma);
+    let (Some(method), Some(first), Some(second), None) =
+        (args.next(), args.next(), args.next(), args.next())
+    else {
+        return None;
+    };
+
+    let (space, hue_interpolation) = match method {
+        [PropertyToken::Identifier(inn), PropertyToken::Identifier(space)]
+            if inn.eq_ignore_ascii_case("in") =>
+        {
+            (ColorSpace::from_name(space)?, HueInterpolation::Shorter)
+        }
+        [PropertyToken::Identifier(inn), PropertyToken::Identifier(space), PropertyToken::Identifier(method), PropertyToken::Identifier(hue)]
+            if inn.eq_ignore_ascii_case("in") && hue.eq_ignore_ascii_case("hue") =>
+        {
+            let space = ColorSpace::from_name(space)?;
+            space.hue()?;
+            let method = match method.to_ascii_lowercase().as_str() {
+                "shorter" => HueInterpolation::Shorter,
+                "longer" => HueInterpolation::Longer,
+                "increasing" => HueInterpolation::Increasing,
+                "decreasing" => HueInterpolation::Decreasing,
+                _ => return None,
+            };
+            (space, method)
+        }
+        _ => return None,
+    };
+
+    let (first, p1) = parse_mix_color(first)?;
+    let (second, p2) = parse_mix_color(second)?;
+
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (0.5, 0.5),
+        (Some(p1), None) => (p1, 1.0 - p1),
+        (None, Some(p2)) => (1.0 - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+
+    let sum = p1 + p2;
+    if !(0.0..=1.0).contains(&p1) || !(0.0..=1.0).contains(&p2) || sum <= 0.0 {
+        return None;
+    }
+
+    let mut mixed = mix(
+        space,
+        hue_interpolation,
+        space.to_channels(first),
+        space.to_channels(second),
+        p2 / sum,
+    );
+
+    // When percentages sums less than 100%, the result is partially transparent.
+    mixed[3] *= sum.min(1.0);
+
+    Some(space.to_color(mixed))
+}
+
+/// Parses a color and it's optional percentage on `color-mix()`, like `red 30%`.
+fn parse_mix_color(tokens: &[PropertyToken]) -> Option<(Color, Option<f32>)> {
+    match tokens {
+        [color] => Some((parse_color(color)?, None)),
+        [color, PropertyToken::Percentage(percent)]
+        | [PropertyToken::Percentage(percent), color] => {
+            Some((parse_color(color)?, Some(percent / 100.0)))
+        }
+        _ => None,
+    }
+}
+
+/// Interpolates two colors channels using premultiplied alpha, where `t` is the weight of second one.
+fn mix(
+    space: ColorSpace,
+    hue_interpolation: HueInterpolation,
+    mut a: [f32; 4],
+    mut b: [f32; 4],
+    t: f32,
+) -> [f32; 4] {
+    let hue = space.hue();
+
+    if let Some(idx) = hue {
+        let (mut h1, mut h2) = (a[idx].rem_euclid(360.0), b[idx].rem_euclid(360.0));
+        let diff = h2 - h1;
+        match hue_interpolation {
+            HueInterpolation::Shorter if diff > 180.0 => h1 += 360.0,
+            HueInterpolation::Shorter if diff < -180.0 => h2 += 360.0,
+            HueInterpolation::Longer if 0.0 < diff && diff < 180.0 => h1 += 360.0,
+            HueInterpolation::Longer if -180.0 < diff && diff <= 0.0 => h2 += 360.0,
+            HueInterpolation::Increasing if diff < 0.0 => h2 += 360.0,
+            HueInterpolation::Decreasing if diff > 0.0 => h1 += 360.0,
+            _ => (),
+        }
+        (a[idx], b[idx]) = (h1, h2);
+    }
+
+    let channels = (0..3).filter(|idx| Some(*idx) != hue);
+    for idx in channels.clone() {
+        a[idx] *= a[3];
+        b[idx] *= b[3];
+    }
+
+    let mut mixed: [f32; 4] = std::array::from_fn(|idx| a[idx] + (b[idx] - a[idx]) * t);
+
+    if mixed[3] != 0.0 {
+        for idx in channels {
+            mixed[idx] /= mixed[3];
+        }
+    }
+
+    mixed
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::color::{Hsla, Laba, Oklcha, Srgba};
+
+    use crate::parser::parse_property_values;
+
+    use super::*;
+
+    fn color(value: &str) -> Option<Color> {
+        parse_property_values(value).unwrap().color()
+    }
+
+    fn assert_close(value: &str, expected: Color) {
+        let actual = color(value).unwrap_or_else(|| panic!("Should be a valid color: {value}"));
+        let actual = ColorSpace::Srgb.to_channels(actual);
+        let expected = ColorSpace::Srgb.to_channels(expected);
+        assert!(
+            actual
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, b)| (a - b).abs() < 1e-3),
+            "{value}: {actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn parse_absolute_colors() {
+        assert_eq!(color("rgb(255 0 0)"), Some(Color::srgb(1.0, 0.0, 0.0)));
+        assert_eq!(
+            color("rgba(255, 0, 0, 0.5)"),
+            Some(Color::srgba(1.0, 0.0, 0.0, 0.5))
+        );
+        assert_eq!(
+            color("rgb(100% 0% 0% / 50%)"),
+            Some(Color::srgba(1.0, 0.0, 0.0, 0.5))
+        );
+        assert_eq!(
+            color("hsl(120 100% 50%)"),
+            Some(Hsla::new(120.0, 1.0, 0.5, 1.0).into())
+        );
+        assert_close("hsl(120deg 100% 50%)", Color::srgb(0.0, 1.0, 0.0));
+        assert_eq!(
+            color("hsla(120, 100%, 50%, 0.25)"),
+            Some(Hsla::new(120.0, 1.0, 0.5, 0.25).into())
+        );
+        assert_eq!(
+            color("lab(50% 25 -25)"),
+            Some(Laba::new(0.5, 0.25, -0.25, 1.0).into())
+        );
+        assert_eq!(
+            color("oklch(70% 0.1 180)"),
+            Some(Oklcha::new(0.7, 0.1, 180.0, 1.0).into())
+        );
+        assert_close(
+            "oklch(70% 0.1 0.5turn)",
+            Oklcha::new(0.7, 0.1, 180.0, 1.0).into(),
+        );
+        assert_eq!(
+            color("color(srgb 1 0.5 none)"),
+            Some(Srgba::new(1.0, 0.5, 0.0, 1.0).into())
+        );
+        assert_close("hwb(0 0% 0%)", Color::srgb(1.0, 0.0, 0.0));
+
+        for invalid in [
+            "rgb(255 0)",
+            "rgb(255, 0 0)",
+            "hsl(10% 100% 50%)",
+            "color(display-p3 1 0 0)",
+            "oklch(70% 0.1 200) red",
+        ] {
+            assert_eq!(color(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parse_relative_colors() {
+        assert_eq!(
+            color("rgb(from red r g 255 / 50%)"),
+            Some(Color::srgba(1.0, 0.0, 1.0, 0.5))
+        );
+        assert_eq!(
+            color("rgb(from rgb(255 0 0 / 0.5) calc(r / 2) g b)"),
+            Some(Color::srgba(0.5, 0.0, 0.0, 0.5))
+        );
+        assert_close(
+            "hsl(from red calc(h + 120) s l)",
+            Color::srgb(0.0, 1.0, 0.0),
+        );
+        assert_close(
+            "oklch(from #ff0000 l c calc(h + 360))",
+            Color::srgb(1.0, 0.0, 0.0),
+        );
+        assert_eq!(color("rgb(from red x y z)"), None);
+    }
+
+    #[test]
+    fn parse_color_mix() {
+        assert_eq!(
+            color("color-mix(in srgb, red, blue)"),
+            Some(Color::srgb(0.5, 0.0, 0.5))
+        );
+        assert_eq!(
+            color("color-mix(in srgb, red 25%, blue)"),
+            Some(Color::srgb(0.25, 0.0, 0.75))
+        );
+        assert_eq!(
+            color("color-mix(in srgb, red 20%, blue 20%)"),
+            Some(Color::srgba(0.5, 0.0, 0.5, 0.4))
+        );
+        assert_eq!(
+            color("color-mix(in hsl, hsl(350 100% 50%), hsl(30 100% 50%))"),
+            Some(Hsla::new(10.0, 1.0, 0.5, 1.0).into())
+        );
+        assert_eq!(
+            color("color-mix(in hsl longer hue, hsl(350 100% 50%), hsl(30 100% 50%))"),
+            Some(Hsla::new(190.0, 1.0, 0.5, 1.0).into())
+        );
+        assert_eq!(color("color-mix(in srgb, red)"), None);
+        assert_eq!(color("color-mix(in srgb longer hue, red, blue)"), None);
+    }
+}
@@ -1,8 +1,13 @@
 use bevy::{ecs::query::QueryItem, prelude::*};
 
-use crate::EcssError;
+use crate::{ContainerType, EcssError, FocusOrder, TabIndex};
 
-use super::{Property, PropertyValues};
+use super::{
+    calc::{
+        insert_calc_value, remove_calc_values, CalcAxis, CalcTarget, CalcValues, ComputedFontSize,
+    },
+    LengthRect, LengthValue, Property, PropertyValues,
+};
 
 pub use style::*;
 pub use text::*;
@@ -64,28 +69,96 @@ mod style {
         };
     }
 
+    /// Implements a new property for [`Style`] component which expects a single length value.
+    ///
+    /// Math expressions which can't be simplified into a single [`Val`] are resolved at runtime
+    /// by [`CalcValues`], where percentages refers to the given axis of the parent node size.
+    macro_rules! impl_style_length {
+        ($name:expr, $struct:ident, $axis:ident, $style_prop:ident$(.$style_field:ident)*) => {
+            impl_style_length!($name, $struct, $axis, $style_prop$(.$style_field)*, true);
+        };
+
+        ($name:expr, $struct:ident, $axis:ident, $style_prop:ident$(.$style_field:ident)*, $do_default:expr) => {
+            #[doc = "Applies the `"]
+            #[doc = $name]
+            #[doc = "` property on [Style::"]
+            #[doc = stringify!($style_prop)]
+            $(#[doc = concat!("::",stringify!($style_field))])*
+            #[doc = "](`Style`) field of all sections on matched [`Style`] components."]
+            #[derive(Default)]
+            pub struct $struct;
+
+            impl Property for $struct {
+                type Cache = LengthValue;
+                type Components = (
+                    Entity,
+                    &'static mut Style,
+                    Option<&'static mut CalcValues>,
+                );
+                type Filters = With<Node>;
+
+                fn name() -> &'static str {
+                    $name
+                }
+
+                fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
+                    if let Some(val) = values.length() {
+                        Ok(val)
+                    } else {
+                        Err(EcssError::InvalidPropertyValue(Self::name().to_string()))
+                    }
+                }
+
+                fn apply<'w>(
+                    cache: Option<&Self::Cache>,
+                    (entity, mut style, calc): QueryItem<Self::Components>,
+                    _asset_server: &AssetServer,
+                    commands: &mut Commands,
+                ) {
+                    match cache {
+                        Some(LengthValue::Calc(expr)) => {
+                            let target = CalcTarget::Style(CalcAxis::$axis, |style| {
+                                &mut style.$style_prop$(.$style_field)*
+                            });
+                            insert_calc_value(commands, entity, calc, $name, expr.clone(), target);
+                        }
+                        Some(LengthValue::Val(val)) => {
+                            style.$style_prop$(.$style_field)? = *val;
+                            remove_calc_values(calc, &[$name]);
+                        }
+                        None if $do_default => {
+                            style.$style_prop$(.$style_field)? = Default::default();
+                            remove_calc_values(calc, &[$name]);
+                        }
+                        None => (),
+                    }
+                }
+            }
+        };
+    }
+
     // Val properties
-    impl_style_single_value!("left", LeftProperty, Val, val, left);
-    impl_style_single_value!("right", RightProperty, Val, val, right);
-    impl_style_single_value!("top", TopProperty, Val, val, top);
-    impl_style_single_value!("bottom", BottomProperty, Val, val, bottom);
+    impl_style_length!("left", LeftProperty, Width, left);
+    impl_style_length!("right", RightProperty, Width, right);
+    impl_style_length!("top", TopProperty, Height, top);
+    impl_style_length!("bottom", BottomProperty, Height, bottom);
 
-    impl_style_single_value!("width", WidthProperty, Val, val, width);
-    impl_style_single_value!("height", HeightProperty, Val, val, height);
+    impl_style_length!("width", WidthProperty, Width, width);
+    impl_style_length!("height", HeightProperty, Height, height);
 
-    impl_style_single_value!("min-width", MinWidthProperty, Val, val, min_width);
-    impl_style_single_value!("min-height", MinHeightProperty, Val, val, min_height);
+    impl_style_length!("min-width", MinWidthProperty, Width, min_width);
+    impl_style_length!("min-height", MinHeightProperty, Height, min_height);
 
-    impl_style_single_value!("max-width", MaxWidthProperty, Val, val, max_width);
-    impl_style_single_value!("max-height", MaxHeightProperty, Val, val, max_height);
+    impl_style_length!("max-width", MaxWidthProperty, Width, max_width);
+    impl_style_length!("max-height", MaxHeightProperty, Height, max_height);
 
-    impl_style_single_value!("flex-basis", FlexBasisProperty, Val, val, flex_basis);
+    impl_style_length!("flex-basis", FlexBasisProperty, Width, flex_basis);
 
     impl_style_single_value!("flex-grow", FlexGrowProperty, f32, f32, flex_grow);
     impl_style_single_value!("flex-shrink", FlexShrinkProperty, f32, f32, flex_shrink);
 
-    impl_style_single_value!("row-gap", RowGapProperty, Val, val, row_gap);
-    impl_style_single_value!("column-gap", ColumnGapProperty, Val, val, column_gap);
+    impl_style_length!("row-gap", RowGapProperty, Height, row_gap);
+    impl_style_length!("column-gap", ColumnGapProperty, Width, column_gap);
 
     impl_style_single_value!(
         "grid-template-columns",
@@ -131,8 +204,12 @@ mod style {
             pub struct $struct;
 
             impl Property for $struct {
-                type Cache = UiRect;
-                type Components = &'static mut Style;
+                type Cache = LengthRect;
+                type Components = (
+                    Entity,
+                    &'static mut Style,
+                    Option<&'static mut CalcValues>,
+                );
                 type Filters = With<Node>;
 
                 fn name() -> &'static str {
@@ -140,7 +217,7 @@ mod style {
                 }
 
                 fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
-                    if let Some(val) = values.rect() {
+                    if let Some(val) = values.length_rect() {
                         Ok(val)
                     } else {
                         Err(EcssError::InvalidPropertyValue(Self::name().to_string()))
@@ -149,20 +226,38 @@ mod style {
 
                 fn apply<'w>(
                     cache: Option<&Self::Cache>,
-                    mut components: QueryItem<Self::Components>,
+                    (entity, mut style, mut calc): QueryItem<Self::Components>,
                     _asset_server: &AssetServer,
-                    _commands: &mut Commands,
+                    commands: &mut Commands,
                 ) {
-                        components.$style_prop$(.$style_field)? = cache
-                            .copied()
-                            .unwrap_or_default();
+                    let rect = cache.cloned().unwrap_or_default();
+                    let sides: [(&'static str, LengthValue, fn(&mut Style) -> &mut Val); 4] = [
+                        (concat!($name, "-top"), rect.top, |style| &mut style.$style_prop.top),
+                        (concat!($name, "-bottom"), rect.bottom, |style| &mut style.$style_prop.bottom),
+                        (concat!($name, "-left"), rect.left, |style| &mut style.$style_prop.left),
+                        (concat!($name, "-right"), rect.right, |style| &mut style.$style_prop.right),
+                    ];
+
+                    for (property, value, field) in sides {
+                        let calc = calc.as_mut().map(Mut::reborrow);
+                        match value {
+                            LengthValue::Val(val) => {
+                                *field(&mut style) = val;
+                                remove_calc_values(calc, &[property]);
+                            }
+                            LengthValue::Calc(expr) => {
+                                let target = CalcTarget::Style(CalcAxis::Width, field);
+                                insert_calc_value(commands, entity, calc, property, expr, target);
+                            }
+                        }
+                    }
                 }
             }
 
-            impl_style_single_value!(concat!($name, "-top"), $struct_top, Val, val, $style_prop.top, false);
-            impl_style_single_value!(concat!($name, "-bottom"), $struct_bottom, Val, val, $style_prop.bottom, false);
-            impl_style_single_value!(concat!($name, "-left"), $struct_left, Val, val, $style_prop.left, false);
-            impl_style_single_value!(concat!($name, "-right"), $struct_right, Val, val, $style_prop.right, false);
+            impl_style_length!(concat!($name, "-top"), $struct_top, Width, $style_prop.top, false);
+            impl_style_length!(concat!($name, "-bottom"), $struct_bottom, Width, $style_prop.bottom, false);
+            impl_style_length!(concat!($name, "-left"), $struct_left, Width, $style_prop.left, false);
+            impl_style_length!(concat!($name, "-right"), $struct_right, Width, $style_prop.right, false);
         };
     }
 
@@ -328,6 +423,10 @@ mod text {
             "color"
         }
 
+        fn inherited() -> bool {
+            true
+        }
+
         fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
             if let Some(color) = values.color() {
                 Ok(color)
@@ -363,6 +462,10 @@ mod text {
             "font"
         }
 
+        fn inherited() -> bool {
+            true
+        }
+
         fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
             if let Some(path) = values.string() {
                 Ok(path)
@@ -386,22 +489,34 @@ mod text {
         }
     }
 
-    /// Applies the `font-size` property on [`TextStyle::font_size`](`TextStyle`) property of all sections on matched [`Text`] components.
+    /// Applies the `font-size` property on [`ComputedFontSize`] of matched nodes and on [`TextStyle::font_size`](`TextStyle`)
+    /// property of all sections on matched [`Text`] components.
     #[derive(Default)]
     pub struct FontSizeProperty;
 
     impl Property for FontSizeProperty {
-        type Cache = f32;
-        type Components = &'static mut Text;
+        type Cache = LengthValue;
+        type Components = (
+            Entity,
+            Option<&'static mut ComputedFontSize>,
+            Option<&'static mut Text>,
+            Option<&'static mut CalcValues>,
+        );
         type Filters = With<Node>;
 
         fn name() -> &'static str {
             "font-size"
         }
 
+        fn inherited() -> bool {
+            true
+        }
+
         fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
             if let Some(size) = values.f32() {
-                Ok(size)
+                Ok(LengthValue::Val(Val::Px(size)))
+            } else if let Some(LengthValue::Calc(expr)) = values.length() {
+                Ok(LengthValue::Calc(expr))
             } else {
                 Err(EcssError::InvalidPropertyValue(Self::name().to_string()))
             }
@@ -409,17 +524,39 @@ mod text {
 
         fn apply<'w>(
             cache: Option<&Self::Cache>,
-            mut components: QueryItem<Self::Components>,
+            (entity, computed, text, calc): QueryItem<Self::Components>,
             _asset_server: &AssetServer,
-            _commands: &mut Commands,
+            commands: &mut Commands,
         ) {
-            let size = cache
-                .copied()
-                .unwrap_or_else(|| TextStyle::default().font_size);
-            components
-                .sections
-                .iter_mut()
-                .for_each(|section| section.style.font_size = size);
+            let size = match cache {
+                Some(LengthValue::Calc(expr)) => {
+                    let target = CalcTarget::FontSize;
+                    insert_calc_value(commands, entity, calc, "font-size", expr.clone(), target);
+                    // The expression is resolved later on, but the component must already be there to receive it.
+                    if computed.is_none() {
+                        commands.entity(entity).insert(ComputedFontSize::default());
+                    }
+                    return;
+                }
+                Some(LengthValue::Val(Val::Px(size))) => *size,
+                _ => TextStyle::default().font_size,
+            };
+
+            remove_calc_values(calc, &["font-size"]);
+            match computed {
+                Some(mut computed) => {
+                    computed.set_if_neq(ComputedFontSize(size));
+                }
+                None => {
+                    commands.entity(entity).insert(ComputedFontSize(size));
+                }
+            }
+
+            if let Some(mut text) = text {
+                text.sections
+                    .iter_mut()
+                    .for_each(|section| section.style.font_size = size);
+            }
         }
     }
 
@@ -437,6 +574,10 @@ mod text {
             "text-align"
         }
 
+        fn inherited() -> bool {
+            true
+        }
+
         fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
             if let Some(ident) = values.identifier() {
                 match ident {
@@ -667,3 +808,134 @@ impl Property for ZIndexProperty {
         *components = cache.copied().unwrap_or_default();
     }
 }
+
+/// Applies the `tab-index` property on [`TabIndex`] component of matched entities, making them focusable.
+///
+/// Entities which aren't styled by this property keeps their [`TabIndex`] component untouched.
+#[derive(Default)]
+pub struct TabIndexProperty;
+
+impl Property for TabIndexProperty {
+    type Cache = i32;
+    type Components = (Entity, Option<&'static mut TabIndex>);
+    type Filters = With<Node>;
+
+    fn name() -> &'static str {
+        "tab-index"
+    }
+
+    fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
+        if let Some(tab_index) = values.i32() {
+            Ok(tab_index)
+        } else {
+            Err(EcssError::InvalidPropertyValue(Self::name().to_string()))
+        }
+    }
+
+    fn apply<'w>(
+        cache: Option<&Self::Cache>,
+        (entity, tab_index): QueryItem<Self::Components>,
+        _asset_server: &AssetServer,
+        commands: &mut Commands,
+    ) {
+        let Some(cache) = cache.copied() else {
+            return;
+        };
+
+        match tab_index {
+            Some(mut tab_index) => tab_index.0 = cache,
+            None => {
+                commands.entity(entity).insert(TabIndex(cache));
+            }
+        }
+    }
+}
+
+/// Applies the `focus-order` property on [`FocusOrder`] component of matched entities.
+///
+/// Entities which aren't styled by this property keeps their [`FocusOrder`] component untouched.
+#[derive(Default)]
+pub struct FocusOrderProperty;
+
+impl Property for FocusOrderProperty {
+    type Cache = FocusOrder;
+    type Components = (Entity, Option<&'static mut FocusOrder>);
+    type Filters = With<Node>;
+
+    fn name() -> &'static str {
+        "focus-order"
+    }
+
+    fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
+        match values.identifier() {
+            Some("geometry") => Ok(FocusOrder::Geometry),
+            Some("tab-index") => Ok(FocusOrder::TabIndex),
+            _ => Err(EcssError::InvalidPropertyValue(Self::name().to_string())),
+        }
+    }
+
+    fn apply<'w>(
+        cache: Option<&Self::Cache>,
+        (entity, focus_order): QueryItem<Self::Components>,
+        _asset_server: &AssetServer,
+        commands: &mut Commands,
+    ) {
+        let Some(cache) = cache.copied() else {
+            return;
+        };
+
+        match focus_order {
+            Some(mut focus_order) => *focus_order = cache,
+            None => {
+                commands.entity(entity).insert(cache);
+            }
+        }
+    }
+}
+
+/// Applies the `container-type` property on [`ContainerType`] component of matched entities, making them
+/// query containers of `@container` rules.
+///
+/// Entities which aren't styled by this property keeps their [`ContainerType`] component untouched.
+#[derive(Default)]
+pub struct ContainerTypeProperty;
+
+impl Property for ContainerTypeProperty {
+    type Cache = ContainerType;
+    type Components = (Entity, Option<&'static mut ContainerType>);
+    type Filters = With<Node>;
+
+    fn name() -> &'static str {
+        "container-type"
+    }
+
+    fn parse<'a>(values: &PropertyValues) -> Result<Self::Cache, EcssError> {
+        match values.identifier() {
+            Some("normal") => Ok(ContainerType::Normal),
+            Some("size") => Ok(ContainerType::Size),
+            Some("inline-size") => Ok(ContainerType::InlineSize),
+            _ => Err(EcssError::InvalidPropertyValue(Self::name().to_string())),
+        }
+    }
+
+    fn apply<'w>(
+        cache: Option<&Self::Cache>,
+        (entity, container_type): QueryItem<Self::Components>,
+        _asset_server: &AssetServer,
+        commands: &mut Commands,
+    ) {
+        let Some(cache) = cache.copied() else {
+            return;
+        };
+
+        match container_type {
+            // Avoids triggering change detection, which would evaluate all container queries again.
+            Some(mut container_type) => {
+                container_type.set_if_neq(cache);
+            }
+            None => {
+                commands.entity(entity).insert(cache);
+            }
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use std::any::Any;
 
 use bevy::{
-    ecs::query::{QueryData, QueryFilter, QueryItem},
+    ecs::{
+        query::{QueryData, QueryFilter, QueryItem},
+        system::SystemParam,
+    },
     log::{error, trace},
     prelude::{
-        AssetId, AssetServer, Assets, Color, Commands, Deref, DerefMut, Entity, Local, Query, Res,
-        Resource,
+        AssetId, AssetServer, Assets, Children, Color, Commands, Deref, DerefMut, Entity, Local,
+        Parent, Query, Res, Resource,
     },
     ui::{
         BorderRadius, GridPlacement, GridTrack, GridTrackRepetition, MaxTrackSizingFunction,
@@ -17,8 +20,14 @@ use bevy::{
 use cssparser::Token;
 use smallvec::SmallVec;
 
-use crate::{parser::ParsedToken, selector::Selector, EcssError, SelectorElement, StyleSheetAsset};
+use self::calc::{CalcExpr, CalcUnit};
+use crate::{
+    container::ContainerResult,
+    parser::{self, ParsedToken},
+    EcssError, SelectorElement, StyleSheetAsset,
+};
 
+pub mod calc;
 mod colors;
 pub mod impls;
 
@@ -27,11 +36,13 @@ pub mod impls;
 pub enum PropertyToken {
     /// A value which was parsed percent value, like `100%` or `73.23%`.
     Percentage(f32),
-    /// A value which was parsed dimension value, like `10px` or `35em.
-    ///
-    /// Currently there is no distinction between [`length-values`](https://developer.mozilla.org/en-US/docs/Web/CSS/length)
-    /// except for `vmin`, `vmax`, `vh` and `vw`.
+    /// An absolute [`length-value`](https://developer.mozilla.org/en-US/docs/Web/CSS/length), like `10px` or `2cm`,
+    /// converted to pixels.
     Dimension(f32),
+    /// A value relative to the inherited font size, like `2em`.
+    Em(f32),
+    /// A value relative to the root font size, like `2rem`.
+    Rem(f32),
     /// A minimum viewport axis value like `10vmin`
     VMin(f32),
     /// A maximum viewport axis value like `10vmax`
@@ -50,17 +61,252 @@ pub enum PropertyToken {
     Hash(String),
     /// A quoted string, like `"some value"`.
     String(String),
-    /// A Function name
+    /// An angle value, like `90deg` or `0.5turn`, converted to radians.
+    Angle(f32),
+    /// A time value, like `2s` or `300ms`, converted to seconds.
+    Time(f32),
+    /// An url, either quoted or not, like `url("image.png")` or `url(image.png)`.
+    Url(String),
+    /// A Function name and it's arguments, which may include nested functions and commas.
     Function(String, Vec<PropertyToken>),
     /// A Literal `/`
     Slash,
+    /// A Literal `,`
+    Comma,
+    /// A math expression, like `calc(100% - 40px)` or `clamp(12px, 2vw, 24px)`.
+    Calc(CalcExpr),
+}
+
+/// A length value, which is either a [`Val`] or a math expression which must be resolved at runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthValue {
+    /// A value which can be applied directly.
+    Val(Val),
+    /// A math expression mixing units, like `calc(100% - 40px)`, which is resolved by [`CalcValues`](calc::CalcValues).
+    Calc(CalcExpr),
+}
+
+impl Default for LengthValue {
+    fn default() -> Self {
+        Self::Val(Val::default())
+    }
+}
+
+/// The [`LengthValue`] of each side of a rect, like `margin: 10px calc(100% - 2em)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthRect {
+    pub left: LengthValue,
+    pub right: LengthValue,
+    pub top: LengthValue,
+    pub bottom: LengthValue,
+}
+
+impl Default for LengthRect {
+    /// All sides are zero, the same as [`UiRect::default`].
+    fn default() -> Self {
+        let zero = LengthValue::Val(Val::ZERO);
+        Self {
+            left: zero.clone(),
+            right: zero.clone(),
+            top: zero.clone(),
+            bottom: zero,
+        }
+    }
+}
+
+impl PropertyToken {
+    /// Converts this token into a [`Val`], if it's a length which can be represented by it.
+    fn to_val(&self) -> Option<Val> {
+        match self {
+            PropertyToken::Calc(expr) => expr.to_val(),
+            PropertyToken::Percentage(val) => Some(Val::Percent(*val)),
+            PropertyToken::Number(val) if *val == 0.0 => Some(Val::Px(0.0)),
+            PropertyToken::Dimension(val) => Some(Val::Px(*val)),
+            PropertyToken::VMin(val) => Some(Val::VMin(*val)),
+            PropertyToken::VMax(val) => Some(Val::VMax(*val)),
+            PropertyToken::Vh(val) => Some(Val::Vh(*val)),
+            PropertyToken::Vw(val) => Some(Val::Vw(*val)),
+            PropertyToken::Identifier(val) if val == "auto" => Some(Val::Auto),
+            _ => None,
+        }
+    }
+
+    /// Converts this token into a [`LengthValue`], if it's a length.
+    fn to_length(&self) -> Option<LengthValue> {
+        match self {
+            PropertyToken::Em(val) => Some(LengthValue::Calc(CalcExpr::Value(*val, CalcUnit::Em))),
+            PropertyToken::Rem(val) => {
+                Some(LengthValue::Calc(CalcExpr::Value(*val, CalcUnit::Rem)))
+            }
+            PropertyToken::Calc(expr) if expr.has_variables() => None,
+            PropertyToken::Calc(expr) => Some(
+                expr.to_val()
+                    .map(LengthValue::Val)
+                    .unwrap_or_else(|| LengthValue::Calc(expr.clone())),
+            ),
+            token => token.to_val().map(LengthValue::Val),
+        }
+    }
 }
 
-/// A list of [`PropertyToken`] which was parsed from a single property.
+/// A list of [`PropertyToken`] which was parsed from a single property and whether it was declared
+/// as `!important`.
 #[derive(Debug, Default, Clone, Deref)]
-pub struct PropertyValues(pub(crate) SmallVec<[PropertyToken; 8]>);
+pub struct PropertyValues(
+    #[deref] pub(crate) SmallVec<[PropertyToken; 8]>,
+    pub(crate) bool,
+);
+
+/// Max number of nested `var()` substitutions, to avoid cyclic custom properties.
+const MAX_VAR_DEPTH: usize = 16;
 
 impl PropertyValues {
+    /// Checks if the property was declared with `!important` flag, which makes it win over any
+    /// normal declaration, regardless of it's selector specificity.
+    pub fn is_important(&self) -> bool {
+        self.1
+    }
+
+    /// Checks if any value is a `var()` function, which depends on custom properties of each entity.
+    pub fn has_var(&self) -> bool {
+        self.has_function("var")
+    }
+
+    /// Checks if any value is an `env()` function, which depends on [`EcssVariables`] resource.
+    pub fn has_env(&self) -> bool {
+        self.has_function("env")
+    }
+
+    /// Checks if any value is a `currentColor` keyword, which depends on the `color` property of each entity.
+    pub fn has_current_color(&self) -> bool {
+        fn has_current_color(tokens: &[PropertyToken]) -> bool {
+            tokens.iter().any(|token| match token {
+                PropertyToken::Identifier(name) => name.eq_ignore_ascii_case("currentcolor"),
+                PropertyToken::Function(_, args) => has_current_color(args),
+                _ => false,
+            })
+        }
+
+        has_current_color(&self.0)
+    }
+
+    /// Replaces all `currentColor` keywords by the given `color` property value.
+    pub fn substitute_current_color(&self, color: &PropertyValues) -> PropertyValues {
+        PropertyValues(
+            self.0
+                .iter()
+                .flat_map(|token| match token {
+                    PropertyToken::Identifier(name)
+                        if name.eq_ignore_ascii_case("currentcolor") =>
+                    {
+                        color.0.to_vec()
+                    }
+                    PropertyToken::Function(name, args) => {
+                        let args = PropertyValues(args.iter().cloned().collect(), false);
+                        let args = args.substitute_current_color(color).0.into_vec();
+                        vec![PropertyToken::Function(name.clone(), args)]
+                    }
+                    token => vec![token.clone()],
+                })
+                .collect(),
+            self.1,
+        )
+    }
+
+    /// Checks if any value is a function with the given name, including nested functions.
+    fn has_function(&self, function: &str) -> bool {
+        fn has_function(tokens: &[PropertyToken], function: &str) -> bool {
+            tokens.iter().any(|token| match token {
+                PropertyToken::Function(name, _) if name == function => true,
+                PropertyToken::Function(_, args) => has_function(args, function),
+                _ => false,
+            })
+        }
+
+        has_function(&self.0, function)
+    }
+
+    /// Replaces all `var(--name, fallback)` functions by the value of the given custom properties.
+    ///
+    /// If a custom property isn't found, the fallback values are used instead. If there are no
+    /// fallback values or custom properties are cyclic, [`None`] is returned.
+    pub fn substitute_vars(&self, custom_properties: &CustomProperties) -> Option<PropertyValues> {
+        let mut values = self.clone();
+
+        for _ in 0..MAX_VAR_DEPTH {
+            if !values.has_var() {
+                return Some(values);
+            }
+
+            values = values.substitute("var", &|name| {
+                name.starts_with("--")
+                    .then(|| custom_properties.get(name))
+                    .flatten()
+            })?;
+        }
+
+        None
+    }
+
+    /// Replaces all `env(name, fallback)` functions by the value of the given [`EcssVariables`].
+    ///
+    /// If a variable isn't found, the fallback values are used instead. If there are no
+    /// fallback values, [`None`] is returned.
+    pub fn substitute_env(&self, variables: &EcssVariables) -> Option<PropertyValues> {
+        if !self.has_env() {
+            return Some(self.clone());
+        }
+
+        self.substitute("env", &|name| variables.get(name))
+    }
+
+    /// Replaces all functions with the given name by the values returned by `lookup`, or by the
+    /// function fallback values, which are all arguments after the first comma.
+    ///
+    /// Functions nested inside other functions, like `repeat(var(--columns), 1fr)`, are replaced too.
+    fn substitute<'a>(
+        &self,
+        function: &str,
+        lookup: &impl Fn(&str) -> Option<&'a PropertyValues>,
+    ) -> Option<PropertyValues> {
+        let mut tokens = SmallVec::new();
+        for token in &self.0 {
+            match token {
+                PropertyToken::Function(name, args) if name == function => {
+                    let (name, fallback) = match args.as_slice() {
+                        [PropertyToken::Identifier(name)] => (name, &[][..]),
+                        [PropertyToken::Identifier(name), PropertyToken::Comma, fallback @ ..] => {
+                            (name, fallback)
+                        }
+                        _ => return None,
+                    };
+
+                    match lookup(name) {
+                        Some(value) => tokens.extend(value.0.iter().cloned()),
+                        None if !fallback.is_empty() => tokens.extend(fallback.iter().cloned()),
+                        None => return None,
+                    }
+                }
+                PropertyToken::Function(name, args) => {
+                    let args = PropertyValues(args.iter().cloned().collect(), false);
+                    let args = args.substitute(function, lookup)?;
+                    tokens.push(PropertyToken::Function(name.clone(), args.0.into_vec()));
+                }
+                token => tokens.push(token.clone()),
+            }
+        }
+
+        Some(PropertyValues(tokens, self.1))
+    }
+
+    /// Splits the current values on each [`Comma`](PropertyToken::Comma), like the families of
+    /// `font-family: "Fira Sans", sans-serif`.
+    pub fn comma_separated(&self) -> impl Iterator<Item = &[PropertyToken]> {
+        self.0
+            .split(|token| matches!(token, PropertyToken::Comma))
+            .filter(|_| !self.0.is_empty())
+    }
+
     /// Tries to parses the current values as a single [`String`].
     pub fn string(&self) -> Option<String> {
         self.0.iter().find_map(|token| match token {
@@ -75,21 +321,24 @@ impl PropertyValues {
         })
     }
 
+    /// Tries to parses the current values as a single url, like `url("image.png")`.
+    pub fn url(&self) -> Option<&str> {
+        self.0.iter().find_map(|token| match token {
+            PropertyToken::Url(url) => Some(url.as_str()),
+            _ => None,
+        })
+    }
+
     /// Tries to parses the current values as a single [`Color`].
     ///
-    /// Currently only [named colors](https://developer.mozilla.org/en-US/docs/Web/CSS/named-color)
-    /// and [hex-colors](https://developer.mozilla.org/en-US/docs/Web/CSS/hex-color) are supported.
+    /// Supports [named colors](https://developer.mozilla.org/en-US/docs/Web/CSS/named-color),
+    /// [hex-colors](https://developer.mozilla.org/en-US/docs/Web/CSS/hex-color), the `rgb()`, `hsl()`, `hwb()`,
+    /// `lab()`, `lch()`, `oklab()`, `oklch()` and `color()` functions, including relative colors like
+    /// `oklch(from red l c calc(h + 180))`, and `color-mix()`. Each color is created on it's matching [`Color`] space.
     pub fn color(&self) -> Option<Color> {
-        if self.0.len() == 1 {
-            match &self.0[0] {
-                PropertyToken::Identifier(name) => colors::parse_named_color(name.as_str()),
-                PropertyToken::Hash(hash) => colors::parse_hex_color(hash.as_str()),
-                _ => None,
-            }
-        } else {
-            // TODO: Implement color function like rgba(255, 255, 255, 255)
-            // https://developer.mozilla.org/en-US/docs/Web/CSS/color_value
-            None
+        match self.0.as_slice() {
+            [token] => colors::parse_color(token),
+            _ => None,
         }
     }
 
@@ -111,18 +360,18 @@ impl PropertyValues {
     ///
     /// Only [`Percentage`](PropertyToken::Percentage) and [`Dimension`](PropertyToken::Dimension`) are considered valid values,
     /// where former is converted to [`Val::Percent`] and latter is converted to [`Val::Px`].
+    ///
+    /// A [`Calc`](PropertyToken::Calc) is only valid if it can be simplified into a single unit, like `calc(100% / 3)`.
     pub fn val(&self) -> Option<Val> {
-        self.0.iter().find_map(|token| match token {
-            PropertyToken::Percentage(val) => Some(Val::Percent(*val)),
-            PropertyToken::Number(val) if *val == 0.0 => Some(Val::Px(0.0)),
-            PropertyToken::Dimension(val) => Some(Val::Px(*val)),
-            PropertyToken::VMin(val) => Some(Val::VMin(*val)),
-            PropertyToken::VMax(val) => Some(Val::VMax(*val)),
-            PropertyToken::Vh(val) => Some(Val::Vh(*val)),
-            PropertyToken::Vw(val) => Some(Val::Vw(*val)),
-            PropertyToken::Identifier(val) if val == "auto" => Some(Val::Auto),
-            _ => None,
-        })
+        self.0.iter().find_map(PropertyToken::to_val)
+    }
+
+    /// Tries to parses the current values as a single [`LengthValue`].
+    ///
+    /// Works like [`val`](PropertyValues::val), but font relative values, like `2em`, and any [`Calc`](PropertyToken::Calc)
+    /// which can't be simplified into a single unit, like `calc(100% - 40px)`, are returned as [`LengthValue::Calc`].
+    pub fn length(&self) -> Option<LengthValue> {
+        self.0.iter().find_map(PropertyToken::to_length)
     }
 
     pub fn grid_template(&self) -> Option<Vec<RepeatedGridTrack>> {
@@ -135,13 +384,13 @@ impl PropertyValues {
                     PropertyToken::Fr(val) => Some(GridTrack::fr(*val)),
                     PropertyToken::Identifier(val) if val == "auto" => Some(GridTrack::auto()),
                     PropertyToken::Function(fun, args) if fun == "repeat" => {
-                        if args.len() != 2 {
+                        let Some([count, track]) = single_args(args) else {
                             error!("Expected 2 arguments to repeat");
                             return None;
-                        }
-                        let repeat = GridTrackRepetition::try_from(&args[0]).ok()?;
+                        };
+                        let repeat = GridTrackRepetition::try_from(count).ok()?;
 
-                        match &args[1] {
+                        match track {
                             PropertyToken::Percentage(val) => {
                                 Some(RepeatedGridTrack::percent(repeat, *val))
                             }
@@ -175,11 +424,11 @@ impl PropertyValues {
                         }
                     }
                     PropertyToken::Function(fun, args) if fun == "fit-content" => {
-                        if args.len() != 1 {
+                        let Some([arg]) = single_args(args) else {
                             error!("Expected 1 arguments to fit-content");
                             return None;
-                        }
-                        match &args[0] {
+                        };
+                        match arg {
                             PropertyToken::Dimension(val) => Some(GridTrack::fit_content_px(*val)),
                             PropertyToken::Percentage(val) => {
                                 Some(GridTrack::fit_content_percent(*val))
@@ -191,13 +440,13 @@ impl PropertyValues {
                         }
                     }
                     PropertyToken::Function(fun, args) if fun == "minmax" => {
-                        if args.len() != 2 {
+                        let Some([min, max]) = single_args(args) else {
                             error!("Expected 2 arguments to minmax");
                             return None;
-                        }
+                        };
                         Some(GridTrack::minmax(
-                            MinTrackSizingFunction::try_from(&args[0]).ok()?,
-                            MaxTrackSizingFunction::try_from(&args[1]).ok()?,
+                            MinTrackSizingFunction::try_from(min).ok()?,
+                            MaxTrackSizingFunction::try_from(max).ok()?,
                         ))
                     }
                     _ => None,
@@ -242,8 +491,11 @@ impl PropertyValues {
     ///
     /// Only [`Percentage`](PropertyToken::Percentage), [`Dimension`](PropertyToken::Dimension`) and [`Number`](PropertyToken::Number`)
     /// are considered valid values.
+    ///
+    /// A [`Calc`](PropertyToken::Calc) is only valid if it can be simplified into pixels, like `calc(12px * 2)`.
     pub fn f32(&self) -> Option<f32> {
         self.0.iter().find_map(|token| match token {
+            PropertyToken::Calc(expr) => expr.to_f32(),
             PropertyToken::Percentage(val)
             | PropertyToken::Dimension(val)
             | PropertyToken::Number(val) => Some(*val),
@@ -318,16 +570,8 @@ impl PropertyValues {
         self.0
             .iter()
             .fold((None, 0), |(rect, idx), token| {
-                let val = match token {
-                    PropertyToken::Percentage(val) => Val::Percent(*val),
-                    PropertyToken::Dimension(val) => Val::Px(*val),
-                    PropertyToken::Number(num) if *num == 0.0 => Val::Px(0.0),
-                    PropertyToken::VMin(val) => Val::VMin(*val),
-                    PropertyToken::VMax(val) => Val::VMax(*val),
-                    PropertyToken::Vh(val) => Val::Vh(*val),
-                    PropertyToken::Vw(val) => Val::Vw(*val),
-                    PropertyToken::Identifier(val) if val == "auto" => Val::Auto,
-                    _ => return (rect, idx),
+                let Some(val) = token.to_val() else {
+                    return (rect, idx);
                 };
                 let mut rect: UiRect = rect.unwrap_or_default();
 
@@ -346,21 +590,45 @@ impl PropertyValues {
             .0
     }
 
+    /// Tries to parses the current values as a [`LengthRect`].
+    ///
+    /// Works like [`rect`](PropertyValues::rect), but each side is parsed as a [`LengthValue`].
+    pub fn length_rect(&self) -> Option<LengthRect> {
+        self.0
+            .iter()
+            .filter_map(PropertyToken::to_length)
+            .enumerate()
+            .fold(None, |rect, (idx, val)| {
+                let mut rect: LengthRect = rect.unwrap_or_default();
+
+                match idx {
+                    0 => {
+                        rect = LengthRect {
+                            left: val.clone(),
+                            right: val.clone(),
+                            top: val.clone(),
+                            bottom: val,
+                        }
+                    }
+                    1 => {
+                        rect.left = val.clone();
+                        rect.right = val;
+                    }
+                    2 => rect.bottom = val,
+                    3 => rect.left = val,
+                    _ => (),
+                }
+                Some(rect)
+            })
+    }
+
     /// Tries to parses the current values as a single [`Option<BorderRadius>`].
     pub fn border_radius(&self) -> Option<BorderRadius> {
         self.0
             .iter()
             .fold((None, 0), |(rect, idx), token| {
-                let val = match token {
-                    PropertyToken::Percentage(val) => Val::Percent(*val),
-                    PropertyToken::Dimension(val) => Val::Px(*val),
-                    PropertyToken::Number(num) if *num == 0.0 => Val::Px(0.0),
-                    PropertyToken::VMin(val) => Val::VMin(*val),
-                    PropertyToken::VMax(val) => Val::VMax(*val),
-                    PropertyToken::Vh(val) => Val::Vh(*val),
-                    PropertyToken::Vw(val) => Val::Vw(*val),
-                    PropertyToken::Identifier(val) if val == "auto" => Val::Auto,
-                    _ => return (rect, idx),
+                let Some(val) = token.to_val() else {
+                    return (rect, idx);
                 };
                 let mut border_radius: BorderRadius = rect.unwrap_or_default();
 
@@ -446,12 +714,26 @@ impl TryFrom<&PropertyToken> for MaxTrackSizingFunction {
     }
 }
 
+/// Splits function arguments on each [`Comma`](PropertyToken::Comma), where each argument must be a
+/// single token. Returns [`None`] if there isn't exactly `N` arguments.
+fn single_args<const N: usize>(args: &[PropertyToken]) -> Option<[&PropertyToken; N]> {
+    args.split(|token| matches!(token, PropertyToken::Comma))
+        .map(|arg| match arg {
+            [token] => Some(token),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?
+        .try_into()
+        .ok()
+}
+
 impl<'i> TryFrom<ParsedToken<'i>> for PropertyToken {
     type Error = ();
 
     fn try_from(value: ParsedToken<'i>) -> Result<Self, Self::Error> {
         match value {
             ParsedToken::Single(tok) => tok.try_into(),
+            ParsedToken::Calc(expr) => Ok(PropertyToken::Calc(expr)),
             ParsedToken::Function(name, args) => Ok(PropertyToken::Function(
                 name.to_string(),
                 args.into_iter().filter_map(|t| t.try_into().ok()).collect(),
@@ -471,15 +753,31 @@ impl<'i> TryFrom<Token<'i>> for PropertyToken {
             Token::QuotedString(val) => Ok(Self::String(val.to_string())),
             Token::Number { value, .. } => Ok(Self::Number(value)),
             Token::Percentage { unit_value, .. } => Ok(Self::Percentage(unit_value * 100.0)),
-            Token::Dimension { value, unit, .. } => match unit.as_bytes() {
-                b"vmin" => Ok(Self::VMin(value)),
-                b"vmax" => Ok(Self::VMax(value)),
-                b"vh" => Ok(Self::Vh(value)),
-                b"vw" => Ok(Self::Vw(value)),
-                b"fr" => Ok(Self::Fr(value)),
-                _ => Ok(Self::Dimension(value)),
+            Token::Dimension { value, unit, .. } => match unit.to_ascii_lowercase().as_str() {
+                "vmin" => Ok(Self::VMin(value)),
+                "vmax" => Ok(Self::VMax(value)),
+                "vh" => Ok(Self::Vh(value)),
+                "vw" => Ok(Self::Vw(value)),
+                "fr" => Ok(Self::Fr(value)),
+                "em" => Ok(Self::Em(value)),
+                "rem" => Ok(Self::Rem(value)),
+                "deg" => Ok(Self::Angle(value.to_radians())),
+                "grad" => Ok(Self::Angle(value * std::f32::consts::PI / 200.0)),
+                "rad" => Ok(Self::Angle(value)),
+                "turn" => Ok(Self::Angle(value * std::f32::consts::TAU)),
+                "s" => Ok(Self::Time(value)),
+                "ms" => Ok(Self::Time(value / 1000.0)),
+                other => match calc::absolute_length(value, other) {
+                    Some(px) => Ok(Self::Dimension(px)),
+                    None => {
+                        error!("Unknown unit: {value}{unit}");
+                        Err(())
+                    }
+                },
             },
+            Token::UnquotedUrl(val) => Ok(Self::Url(val.to_string())),
             Token::Delim('/') => Ok(Self::Slash),
+            Token::Comma => Ok(Self::Comma),
             Token::WhiteSpace(_) => Err(()),
             tt => {
                 error!("unmatched TT: {tt:?}");
@@ -499,67 +797,330 @@ pub enum CacheState<T> {
     Ok(T),
     /// Parse was performed but returned an error.
     Error,
+    /// Parse must be performed for each entity, since the values depends on entity custom properties
+    /// or on [`EcssVariables`], which may change at any time.
+    PerEntity,
 }
 
 /// Internal cache map. Used by [`PropertyMeta`] to keep track of which properties was already parsed.
 #[derive(Debug, Default, Deref, DerefMut)]
-pub struct CachedProperties<T>(HashMap<Selector, CacheState<T>>);
+pub struct CachedProperties<T>(HashMap<usize, CacheState<T>>);
 
 /// Internal property cache map. Used by [`Property::apply_system`] to keep track of which properties was already parsed.
 #[derive(Debug, Default, Deref, DerefMut)]
-pub struct PropertyMeta<T: Property>(HashMap<u64, CachedProperties<T::Cache>>);
+pub struct PropertyMeta<T: Property> {
+    #[deref]
+    cache: HashMap<u64, CachedProperties<T::Cache>>,
+    /// The winning rule of each entity which declares an [`inherited`](Property::inherited) property,
+    /// kept across runs since sheets are applied independently of each other.
+    declared: HashMap<Entity, (AssetId<StyleSheetAsset>, usize)>,
+}
 
 impl<T: Property> PropertyMeta<T> {
     /// Gets a cached property value or try to parse.
     ///
     /// If there are some error while parsing, a [`CacheState::Error`] is stored to avoid trying to parse again on next try.
-    fn get_or_parse(
-        &mut self,
+    fn get_or_parse(&mut self, rules: &StyleSheetAsset, rule: usize) -> &CacheState<T::Cache> {
+        self.entry(rules.hash())
+            .or_default()
+            .entry(rule)
+            .or_insert_with(|| {
+                rules
+                    .get(rule)
+                    .and_then(|rule| rule.properties.get(T::name()))
+                    .map(|values| {
+                        if values.has_var() || values.has_env() || values.has_current_color() {
+                            return CacheState::PerEntity;
+                        }
+
+                        match T::parse(values) {
+                            Ok(cache) => CacheState::Ok(cache),
+                            Err(err) => {
+                                error!("Failed to parse property {}. Error: {}", T::name(), err);
+                                // TODO: Clear cache state when the asset is reloaded, since values may be changed.
+                                CacheState::Error
+                            }
+                        }
+                    })
+                    .unwrap_or(CacheState::None)
+            })
+    }
+
+    /// Parses a property which depends on the given entity custom properties or on [`EcssVariables`].
+    ///
+    /// This is used when the cache state is [`CacheState::PerEntity`] and it's never cached.
+    fn parse_for_entity(
         rules: &StyleSheetAsset,
-        selector: &Selector,
-    ) -> &CacheState<T::Cache> {
-        let cached_properties = self.entry(rules.hash()).or_default();
-
-        // Avoid using HashMap::entry since it requires ownership of key
-        if cached_properties.contains_key(selector) {
-            cached_properties.get(selector).unwrap()
-        } else {
-            let new_cache = rules
-                .get_properties(selector, T::name())
-                .map(|values| match T::parse(values) {
-                    Ok(cache) => CacheState::Ok(cache),
-                    Err(err) => {
-                        error!("Failed to parse property {}. Error: {}", T::name(), err);
-                        // TODO: Clear cache state when the asset is reloaded, since values may be changed.
-                        CacheState::Error
-                    }
-                })
-                .unwrap_or(CacheState::None);
+        rule: usize,
+        custom_properties: Option<&CustomProperties>,
+        variables: &EcssVariables,
+    ) -> Option<T::Cache> {
+        let empty = CustomProperties::default();
+        let custom_properties = custom_properties.unwrap_or(&empty);
+        let values = rules
+            .get(rule)?
+            .properties
+            .get(T::name())?
+            .substitute_vars(custom_properties)
+            .and_then(|values| values.substitute_env(variables));
+
+        let Some(mut values) = values else {
+            error!("Failed to resolve var() or env() on property {}", T::name());
+            return None;
+        };
+
+        if values.has_current_color() {
+            match custom_properties.get(CURRENT_COLOR) {
+                Some(color) => values = values.substitute_current_color(color),
+                None => {
+                    error!("Failed to resolve currentColor on property {}", T::name());
+                    return None;
+                }
+            }
+        }
 
-            cached_properties.insert(selector.clone(), new_cache);
-            cached_properties.get(selector).unwrap()
+        match T::parse(&values) {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                error!("Failed to parse property {}. Error: {}", T::name(), err);
+                None
+            }
         }
     }
 }
 
+/// Key used to keep the `color` property on [`CustomProperties`], so it's inherited like any custom
+/// property and can be used to resolve `currentColor`.
+pub(crate) const CURRENT_COLOR: &str = "color";
+
+/// Custom properties, like `--accent`, using the property name as key.
+#[derive(Debug, Clone, Default, Deref, DerefMut)]
+pub struct CustomProperties(pub(crate) HashMap<String, PropertyValues>);
+
+/// Runtime variables which can be referenced by any style sheet with `env()` function, like
+/// `background-color: env(accent, white)`.
+///
+/// Whenever this resource is changed, all [`StyleSheet`](crate::StyleSheet) which uses `env()` are applied again.
+///
+/// # Examples
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecss::prelude::*;
+/// fn system(mut variables: ResMut<EcssVariables>) {
+///     variables.set("accent", "#ff03ab").unwrap();
+///     variables.set("ui-scale", "1.5").unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Resource)]
+pub struct EcssVariables(HashMap<String, PropertyValues>);
+
+impl EcssVariables {
+    /// Parses the given CSS value, like `10px` or `#ff03ab`, and sets it as the variable value.
+    pub fn set(&mut self, name: impl Into<String>, value: &str) -> Result<(), EcssError> {
+        let values = parser::parse_property_values(value)?;
+        self.0.insert(name.into(), values);
+        Ok(())
+    }
+
+    /// Sets the variable value to the given already parsed [`PropertyValues`].
+    pub fn set_values(&mut self, name: impl Into<String>, values: PropertyValues) {
+        self.0.insert(name.into(), values);
+    }
+
+    /// Returns the value of the given variable, if any.
+    pub fn get(&self, name: &str) -> Option<&PropertyValues> {
+        self.0.get(name)
+    }
+
+    /// Removes the given variable, returning it's value, if any.
+    pub fn remove(&mut self, name: &str) -> Option<PropertyValues> {
+        self.0.remove(name)
+    }
+}
+
+/// Resources used to resolve property values which depends on each entity, like `var()` and `env()`,
+/// or on it's ancestors, like [`inherited`](Property::inherited) properties.
+#[derive(SystemParam)]
+pub struct PropertyContext<'w, 's> {
+    custom_properties: Res<'w, CustomPropertiesState>,
+    variables: Res<'w, EcssVariables>,
+    parents: Query<'w, 's, &'static Parent>,
+    children: Query<'w, 's, &'static Children>,
+}
+
+/// Custom properties declared on or inherited by each styled entity.
+#[derive(Debug, Clone, Default, Resource, Deref, DerefMut)]
+pub struct CustomPropertiesState(pub(crate) HashMap<Entity, CustomProperties>);
+
 #[derive(Debug, Clone, Default, Deref, DerefMut)]
 pub struct TrackedEntities(HashMap<SelectorElement, SmallVec<[Entity; 8]>>);
 
-/// Maps which entities was selected by a [`Selector`]
+/// Maps which entities was selected by each rule, identified by it's [`StyleSheetAsset`] and it's index on that sheet.
+#[allow(clippy::type_complexity)]
 #[derive(Debug, Clone, Default, Deref, DerefMut)]
-pub struct SelectedEntities(SmallVec<[(Selector, SmallVec<[Entity; 8]>); 8]>);
+pub struct SelectedEntities(
+    SmallVec<[(AssetId<StyleSheetAsset>, usize, SmallVec<[Entity; 8]>); 8]>,
+);
 
-/// Maps sheets for each [`StyleSheetAsset`].
-#[derive(Debug, Clone, Default, Resource, Deref, DerefMut)]
-pub struct StyleSheetState(Vec<(AssetId<StyleSheetAsset>, TrackedEntities, SelectedEntities)>);
+/// Tracked and selected entities of every [`StyleSheetAsset`] being applied.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct StyleSheetState {
+    tracked: Vec<(AssetId<StyleSheetAsset>, TrackedEntities)>,
+    selected: SelectedEntities,
+    /// Indices of `selected` on the cascade order of important declarations.
+    important: Vec<usize>,
+    /// Results of media queries of each [`StyleSheet`](crate::StyleSheet) root, as yielded by [`evaluate_media`](crate::media::evaluate_media).
+    pub(crate) media: Vec<(Entity, SmallVec<[bool; 8]>)>,
+    /// Results of container queries of each [`StyleSheet`](crate::StyleSheet) root, for each selected entity.
+    pub(crate) containers: Vec<(Entity, SmallVec<[ContainerResult; 8]>)>,
+    /// Each [`StyleSheet`](crate::StyleSheet) root being applied.
+    pub(crate) roots: SmallVec<[Entity; 8]>,
+    /// The roots being applied and their descendants, except the ones of nested roots which aren't being applied.
+    pub(crate) scope: HashSet<Entity>,
+}
 
 impl StyleSheetState {
+    /// Entities tracked by each sheet, which causes the sheet to be applied again when changed.
+    pub fn tracked(&self) -> &[(AssetId<StyleSheetAsset>, TrackedEntities)] {
+        &self.tracked
+    }
+
+    /// Entities selected by each rule, sorted on cascade order of normal declarations, so rules
+    /// later on the list overrides earlier ones.
+    ///
+    /// Rules are sorted by their cascade layer, then by their [`Specificity`](crate::Specificity),
+    /// then by the sheet order on [`StyleSheet`](crate::StyleSheet) and finally by their order on the sheet.
+    /// Rules outside of any layer comes after all layered ones.
+    pub fn selected(&self) -> &SelectedEntities {
+        &self.selected
+    }
+
+    /// Iterates over the entities selected by each rule, on cascade order of normal or
+    /// [`important`](PropertyValues::is_important) declarations.
+    ///
+    /// Important declarations must be applied after all normal ones and uses the reverse layer
+    /// order, so earlier layers wins and rules outside of any layer comes first.
+    pub fn cascade(
+        &self,
+        important: bool,
+    ) -> impl Iterator<Item = &(AssetId<StyleSheetAsset>, usize, SmallVec<[Entity; 8]>)> {
+        let order = important.then_some(&self.important);
+        (0..self.selected.len()).map(move |i| &self.selected[order.map_or(i, |order| order[i])])
+    }
+
+    /// Adds the entities tracked and selected by rules of the given sheet.
+    ///
+    /// Sheets must be pushed on the order they should be applied.
+    pub(crate) fn push(
+        &mut self,
+        asset_id: AssetId<StyleSheetAsset>,
+        tracked: TrackedEntities,
+        selected: impl IntoIterator<Item = (usize, SmallVec<[Entity; 8]>)>,
+    ) {
+        self.tracked.push((asset_id, tracked));
+        self.selected.extend(
+            selected
+                .into_iter()
+                .map(|(rule, entities)| (asset_id, rule, entities)),
+        );
+    }
+
+    /// Sorts the selected rules on cascade order.
+    ///
+    /// Since sheets and rules are pushed on source order, a stable sort by layer and specificity is enough.
+    pub(crate) fn sort(&mut self, assets: &Assets<StyleSheetAsset>) {
+        // Layers are ordered by their first declaration among all sheets.
+        let mut layers = HashMap::<String, usize>::default();
+        for (asset_id, _) in &self.tracked {
+            for name in assets
+                .get(*asset_id)
+                .into_iter()
+                .flat_map(|sheet| sheet.layers())
+            {
+                let len = layers.len();
+                layers.entry(layer_id(*asset_id, name)).or_insert(len);
+            }
+        }
+
+        // Each layer is sorted by it's path on the layer tree, where child layers comes before their
+        // parent, so rules directly inside a layer wins over rules of it's child layers.
+        let layer_key = |asset_id: AssetId<StyleSheetAsset>, layer: Option<&String>| {
+            let mut key = SmallVec::<[usize; 4]>::new();
+            if let Some(layer) = layer {
+                let id = layer_id(asset_id, layer);
+                key.extend(
+                    id.match_indices('.')
+                        .map(|(i, _)| &id[..i])
+                        .chain([id.as_str()])
+                        .map(|name| layers.get(name).copied().unwrap_or_default()),
+                );
+            }
+            key.push(usize::MAX);
+            key
+        };
+
+        let keys = self
+            .selected
+            .iter()
+            .map(|(asset_id, rule, _)| {
+                let rule = assets.get(*asset_id).and_then(|sheet| sheet.get(*rule));
+                (
+                    layer_key(*asset_id, rule.and_then(|rule| rule.layer.as_ref())),
+                    rule.map(|rule| rule.selector.specificity())
+                        .unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = (0..self.selected.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+        let mut important = order.clone();
+        important.sort_by(|&a, &b| {
+            keys[b]
+                .0
+                .cmp(&keys[a].0)
+                .then_with(|| keys[a].1.cmp(&keys[b].1))
+                .then_with(|| a.cmp(&b))
+        });
+
+        // Positions of each entry on the new `selected` order.
+        let mut position = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            position[old] = new;
+        }
+        self.important = important.into_iter().map(|old| position[old]).collect();
+
+        let mut selected = std::mem::take(&mut self.selected.0)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.selected.0 = order
+            .into_iter()
+            .filter_map(|old| selected[old].take())
+            .collect();
+    }
+
     pub(crate) fn has_any_selected_entities(&self) -> bool {
-        self.iter().any(|(_, _, v)| !v.is_empty())
+        !self.selected.is_empty()
     }
 
     pub(crate) fn clear_selected_entities(&mut self) {
-        self.iter_mut().for_each(|(_, _, v)| v.clear());
+        self.selected.clear();
+        self.important.clear();
+        self.roots.clear();
+        self.scope.clear();
+    }
+}
+
+/// Identifies a cascade layer among all sheets. Anonymous layers, which are named starting with `@`,
+/// and their child layers are unique to the sheet which declares them.
+fn layer_id(asset_id: AssetId<StyleSheetAsset>, name: &str) -> String {
+    if name.starts_with('@') {
+        format!("{asset_id:?}/{name}")
+    } else {
+        name.to_string()
     }
 }
 
@@ -570,24 +1131,25 @@ impl StyleSheetState {
 ///
 /// These are the associated types that must by specified by implementors:
 /// - [`Cache`](Property::Cache) is a cached value to be applied by this trait.
-/// On the first time the `system` runs it'll call [`parse`](`Property::parse`) and cache the value.
-/// Subsequential runs will only fetch the cached value.
+///   On the first time the `system` runs it'll call [`parse`](`Property::parse`) and cache the value.
+///   Subsequential runs will only fetch the cached value.
 /// - [`Components`](Property::Components) is which components will be send to [`apply`](`Property::apply`) function whenever a
-/// valid cache exists and a matching property was found on any sheet rule. Check [`QueryData`] for more.
+///   valid cache exists and a matching property was found on any sheet rule. Check [`QueryData`] for more.
 /// - [`Filters`](Property::Filters) is used to filter which entities will be applied the property modification.
-/// Entities are first filtered by [`selectors`](`Selector`), but it can be useful to also ensure some behavior for safety reasons,
-/// like only inserting [`JustifyText`](bevy::prelude::JustifyText) if the entity also has a [`Text`](bevy::prelude::Text) component.
-///  Check [`WorldQuery`] for more.
+///   Entities are first filtered by [`selectors`](`crate::Selector`), but it can be useful to also ensure some behavior for safety reasons,
+///   like only inserting [`JustifyText`](bevy::prelude::JustifyText) if the entity also has a [`Text`](bevy::prelude::Text) component.
+///   Check [`WorldQuery`] for more.
 ///
 /// These are tree functions required to be implemented:
 /// - [`name`](Property::name) indicates which property name should matched for.
 /// - [`parse`](Property::parse) parses the [`PropertyValues`] into the [`Cache`](Property::Cache) value to be reused across multiple entities.
 /// - [`apply`](Property::apply) applies on the given [`Components`](Property::Components) the [`Cache`](Property::Cache) value.
-/// Additionally, an [`AssetServer`] and [`Commands`] parameters are provided for more complex use cases.
+///   Additionally, an [`AssetServer`] and [`Commands`] parameters are provided for more complex use cases.
 ///
-/// Also, there one function which have default implementations:
+/// Also, there are some functions which have default implementations:
+/// - [`inherited`](Property::inherited) indicates if the property value propagates to descendants, like `color`.
 /// - [`apply_system`](Property::apply_system) is a [`system`](https://docs.rs/bevy_ecs/latest/bevy_ecs/system/index.html) which interacts with
-/// [ecs world](`bevy::prelude::World`) and call the [`apply`](Property::apply) function on every matched entity.
+///   [ecs world](`bevy::prelude::World`) and call the [`apply`](Property::apply) function on every matched entity.
 pub trait Property: Default + Sized + Send + Sync + 'static {
     /// The cached value type to be applied by property.
     type Cache: Default + Any + Send + Sync;
@@ -618,6 +1180,15 @@ pub trait Property: Default + Sized + Send + Sync + 'static {
         commands: &mut Commands,
     );
 
+    /// Indicates if this property is inherited, like `color` or `font-size`. The value of inherited properties
+    /// propagates from the nearest ancestor which declares it to all descendants which have no value of their own,
+    /// even if the ancestor itself doesn't match [`Components`](Property::Components).
+    ///
+    /// By default properties aren't inherited.
+    fn inherited() -> bool {
+        false
+    }
+
     /// The [`system`](https://docs.rs/bevy_ecs/latest/bevy_ecs/system/index.html) which interacts with
     /// [ecs world](`bevy::prelude::World`) and call [`apply`](Property::apply) function on every matched entity.
     ///
@@ -626,20 +1197,50 @@ pub trait Property: Default + Sized + Send + Sync + 'static {
         mut local: Local<PropertyMeta<Self>>,
         assets: Res<Assets<StyleSheetAsset>>,
         apply_sheets: Res<StyleSheetState>,
-        mut q_nodes: Query<Self::Components, Self::Filters>,
+        context: PropertyContext,
+        mut q_nodes: Query<(Entity, Self::Components), Self::Filters>,
         asset_server: Res<AssetServer>,
         mut commands: Commands,
     ) {
         let mut entities_set = HashSet::new();
-        for (asset_id, _, selected) in apply_sheets.iter() {
-            if let Some(rules) = assets.get(*asset_id) {
-                for (selector, entities) in selected.iter() {
-                    let cached = match local.get_or_parse(rules, selector) {
-                        CacheState::Ok(cached) => Some(cached),
-                        _ => None,
-                    };
+        // The winning rule of each entity which declares an inherited property.
+        let mut declared = HashMap::new();
+        // Important declarations are applied after all normal ones, so they always win.
+        for important in [false, true] {
+            for (asset_id, rule, entities) in apply_sheets.cascade(important) {
+                if let Some(rules) = assets.get(*asset_id) {
+                    let is_important = rules
+                        .get(*rule)
+                        .and_then(|rule| rule.properties.get(Self::name()))
+                        .is_some_and(PropertyValues::is_important);
+                    if is_important != important {
+                        continue;
+                    }
+
+                    let state = local.get_or_parse(rules, *rule);
                     for entity in entities {
-                        if let Ok(components) = q_nodes.get_mut(*entity) {
+                        let parsed;
+                        let cached = match state {
+                            CacheState::Ok(cached) => Some(cached),
+                            CacheState::PerEntity => {
+                                parsed = PropertyMeta::<Self>::parse_for_entity(
+                                    rules,
+                                    *rule,
+                                    context.custom_properties.get(entity),
+                                    &context.variables,
+                                );
+                                parsed.as_ref()
+                            }
+                            _ => None,
+                        };
+                        // Entities with invalid values still have their own value, so they don't inherit.
+                        if Self::inherited()
+                            && !matches!(state, CacheState::None)
+                            && (cached.is_some() || !declared.contains_key(entity))
+                        {
+                            declared.insert(*entity, (*asset_id, *rule));
+                        }
+                        if let Ok((_, components)) = q_nodes.get_mut(*entity) {
                             if cached.is_some() || !entities_set.contains(entity) {
                                 entities_set.insert(entity);
                                 if cached.is_some() {
@@ -647,7 +1248,10 @@ pub trait Property: Default + Sized + Send + Sync + 'static {
                                         r#"Applying property "{}" from sheet "{}" ({})"#,
                                         Self::name(),
                                         rules.path(),
-                                        selector
+                                        rules
+                                            .get(*rule)
+                                            .map(|rule| rule.selector.to_string())
+                                            .unwrap_or_default()
                                     );
                                 }
                                 Self::apply(cached, components, &asset_server, &mut commands);
@@ -657,5 +1261,85 @@ pub trait Property: Default + Sized + Send + Sync + 'static {
                 }
             }
         }
+
+        if !Self::inherited() || apply_sheets.roots.is_empty() {
+            return;
+        }
+
+        // Entities outside of the applied scope keeps their own values, which may come from other sheets.
+        local
+            .declared
+            .retain(|entity, _| !apply_sheets.scope.contains(entity));
+        local
+            .declared
+            .extend(declared.iter().map(|(e, v)| (*e, *v)));
+
+        if local.declared.is_empty() {
+            return;
+        }
+
+        for &root in &apply_sheets.roots {
+            let mut ancestor = root;
+            let mut inherited = None;
+            while let Ok(parent) = context.parents.get(ancestor) {
+                ancestor = parent.get();
+                if let Some(value) = local.declared.get(&ancestor) {
+                    inherited = Some((ancestor, *value));
+                    break;
+                }
+            }
+
+            let mut stack = vec![(root, inherited)];
+            while let Some((entity, inherited)) = stack.pop() {
+                let inherited = match local.declared.get(&entity) {
+                    // Values declared by sheets which aren't being applied are left as is, alongside their descendants.
+                    Some(_) if !declared.contains_key(&entity) => continue,
+                    Some(value) => Some((entity, *value)),
+                    None => {
+                        if let (Some((ancestor, (asset_id, rule))), Ok((_, components))) =
+                            (inherited, q_nodes.get_mut(entity))
+                        {
+                            if let Some(rules) = assets.get(asset_id) {
+                                let parsed;
+                                let cached = match local.get_or_parse(rules, rule) {
+                                    CacheState::Ok(cached) => Some(cached),
+                                    CacheState::PerEntity => {
+                                        // Inherited values are resolved on the ancestor which declares them.
+                                        parsed = PropertyMeta::<Self>::parse_for_entity(
+                                            rules,
+                                            rule,
+                                            context.custom_properties.get(&ancestor),
+                                            &context.variables,
+                                        );
+                                        parsed.as_ref()
+                                    }
+                                    _ => None,
+                                };
+
+                                if cached.is_some() {
+                                    trace!(
+                                        r#"Applying property "{}" inherited from {:?}"#,
+                                        Self::name(),
+                                        ancestor
+                                    );
+                                    Self::apply(cached, components, &asset_server, &mut commands);
+                                }
+                            }
+                        }
+                        inherited
+                    }
+                };
+
+                // Nested roots being applied are visited on their own.
+                if let Ok(children) = context.children.get(entity) {
+                    stack.extend(
+                        children
+                            .iter()
+                            .filter(|child| !apply_sheets.roots.contains(*child))
+                            .map(|child| (*child, inherited)),
+                    );
+                }
+            }
+        }
     }
 }
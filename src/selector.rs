@@ -17,29 +17,225 @@ pub enum SelectorElement {
     Class(String),
     /// Indicates a parent-child relation between previous elements and next elements, like `window .border`
     Child,
+    /// Indicates a direct parent-child relation between previous elements and next elements, like `window > .border`
+    DirectChild,
+    /// Indicates the next elements must immediately follow previous elements on their parent [`Children`](bevy::prelude::Children), like `.tab + .tab`
+    NextSibling,
+    /// Indicates the next elements must follow previous elements, but not necessarily immediately, on their parent [`Children`](bevy::prelude::Children), like `.divider ~ .item`
+    SubsequentSibling,
     /// A keyword added to a selector that specifies a special state of the selected element(s), like `button:hover`
     PseudoClass(PseudoClassElement),
+    /// An attribute selector element, like `[key=value]` or `[Health.current<20]`.
+    Attribute(AttributeElement),
     /// Selects any component, like `*` on CSS.
     Any,
 }
 
+impl SelectorElement {
+    /// Checks if this element is a combinator, which relates the elements before it with the elements after it.
+    pub fn is_combinator(&self) -> bool {
+        matches!(
+            self,
+            SelectorElement::Child
+                | SelectorElement::DirectChild
+                | SelectorElement::NextSibling
+                | SelectorElement::SubsequentSibling
+        )
+    }
+}
+
+/// Represents an [attribute selector](https://developer.mozilla.org/en-US/docs/Web/CSS/Attribute_selectors).
+///
+/// Attributes are read from [`CssAttributes`](crate::CssAttributes) component, unless the name has a `.`, in which
+/// case, it's a path to a reflected component field, like `Health.current`, where `Health` is the component
+/// type name and `current` is the field path.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct AttributeElement {
+    /// The attribute name, like `key` on `[key=value]`, or the component field path, like `Health.current`.
+    pub name: String,
+    /// How the attribute value is matched.
+    pub matcher: AttributeMatcher,
+}
+
+/// Determines how an [`AttributeElement`] value is matched.
+///
+/// Numeric comparisons, like `[Health.current<20]`, only matches when both values are numbers.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum AttributeMatcher {
+    /// Matches when the attribute exists, like `[key]`.
+    Exists,
+    /// Matches when the attribute is equals to the value, like `[key=value]`.
+    /// Numbers are compared numerically, so `[key=1]` also matches `1.0`.
+    Equals(String),
+    /// Matches when the attribute is a whitespace separated list which contains the value, like `[key~=value]`.
+    Includes(String),
+    /// Matches when the attribute starts with the value, like `[key^=value]`.
+    Prefix(String),
+    /// Matches when the attribute ends with the value, like `[key$=value]`.
+    Suffix(String),
+    /// Matches when the attribute contains the value, like `[key*=value]`.
+    Substring(String),
+    /// Matches when the attribute is less than the value, like `[key<10]`.
+    Less(String),
+    /// Matches when the attribute is less than or equals to the value, like `[key<=10]`.
+    LessOrEqual(String),
+    /// Matches when the attribute is greater than the value, like `[key>10]`.
+    Greater(String),
+    /// Matches when the attribute is greater than or equals to the value, like `[key>=10]`.
+    GreaterOrEqual(String),
+}
+
+impl AttributeElement {
+    /// Returns the component type name and field path, if this attribute is a reflected component field.
+    pub fn reflect_path(&self) -> Option<(&str, &str)> {
+        self.name.split_once('.')
+    }
+
+    /// Checks if the given attribute value, if any, is matched by this element.
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+
+        let compare = |expected: &str| {
+            value
+                .parse::<f64>()
+                .ok()
+                .zip(expected.parse::<f64>().ok())
+                .and_then(|(value, expected)| value.partial_cmp(&expected))
+        };
+
+        use std::cmp::Ordering::*;
+        match &self.matcher {
+            AttributeMatcher::Exists => true,
+            AttributeMatcher::Equals(expected) => {
+                value == expected || compare(expected) == Some(Equal)
+            }
+            AttributeMatcher::Includes(expected) => {
+                value.split_ascii_whitespace().any(|v| v == expected)
+            }
+            AttributeMatcher::Prefix(expected) => value.starts_with(expected.as_str()),
+            AttributeMatcher::Suffix(expected) => value.ends_with(expected.as_str()),
+            AttributeMatcher::Substring(expected) => value.contains(expected.as_str()),
+            AttributeMatcher::Less(expected) => compare(expected) == Some(Less),
+            AttributeMatcher::LessOrEqual(expected) => {
+                matches!(compare(expected), Some(Less | Equal))
+            }
+            AttributeMatcher::Greater(expected) => compare(expected) == Some(Greater),
+            AttributeMatcher::GreaterOrEqual(expected) => {
+                matches!(compare(expected), Some(Greater | Equal))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AttributeElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (operator, value) = match &self.matcher {
+            AttributeMatcher::Exists => return write!(f, "[{}]", self.name),
+            AttributeMatcher::Equals(v) => ("=", v),
+            AttributeMatcher::Includes(v) => ("~=", v),
+            AttributeMatcher::Prefix(v) => ("^=", v),
+            AttributeMatcher::Suffix(v) => ("$=", v),
+            AttributeMatcher::Substring(v) => ("*=", v),
+            AttributeMatcher::Less(v) => ("<", v),
+            AttributeMatcher::LessOrEqual(v) => ("<=", v),
+            AttributeMatcher::Greater(v) => (">", v),
+            AttributeMatcher::GreaterOrEqual(v) => (">=", v),
+        };
+        if value.parse::<f64>().is_ok() {
+            write!(f, "[{}{}{}]", self.name, operator, value)
+        } else {
+            write!(f, "[{}{}{:?}]", self.name, operator, value)
+        }
+    }
+}
+
 /// Represents a pseudo-class as per (mdn docs)[https://developer.mozilla.org/en-US/docs/Web/CSS/Pseudo-classes]
-/// Not all pseudo-classes are supported, in which case, an `Unsupported` variant will be used.
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+/// Any pseudo-class which isn't built-in will be a `Custom` variant, which must be registered by app
+/// using [`RegisterPseudoClass`](crate::RegisterPseudoClass).
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum PseudoClassElement {
     Hover,
     Active,
-    Unsupported,
+    /// Matches entities with [`Disabled`](crate::Disabled) component, like `:disabled`.
+    Disabled,
+    /// Matches entities with [`Checked`](crate::Checked) component, like `:checked`.
+    Checked,
+    /// Matches entities with [`Selected`](crate::Selected) component, like `:selected`.
+    Selected,
+    /// Matches entities with [`Focused`](crate::Focused) component, like `:focus`.
+    Focus,
+    /// Matches entities with [`FocusVisible`](crate::FocusVisible) component, like `:focus-visible`.
+    FocusVisible,
+    /// Matches entities with [`Focused`](crate::Focused) component or which have a focused descendant,
+    /// like `:focus-within`.
+    FocusWithin,
+    /// Matches the first entity among it's siblings, like `:first-child`.
+    FirstChild,
+    /// Matches the last entity among it's siblings, like `:last-child`.
+    LastChild,
+    /// Matches entities based on their position among it's siblings, like `:nth-child(2n+1)`.
+    /// The values are `a` and `b` on the `an+b` notation.
+    NthChild(i32, i32),
+    /// Matches an entity without any siblings, like `:only-child`.
+    OnlyChild,
+    /// Matches an entity without any children, like `:empty`.
+    Empty,
+    /// Matches an entity without any parent, like `:root`.
+    Root,
+    /// Matches entities which are matched by any of the given selectors, like `:is(.a, .b)`.
+    Is(Vec<Selector>),
+    /// Matches entities which are matched by any of the given selectors, like `:where(.a, .b)`.
+    /// Unlike [`PseudoClassElement::Is`], this pseudo-class doesn't add any specificity.
+    Where(Vec<Selector>),
+    /// Matches entities which aren't matched by any of the given selectors, like `:not(.a, .b)`.
+    Not(Vec<Selector>),
+    /// Matches entities which have at least one entity matched by any of the given relative selectors,
+    /// like `:has(.item)` or `:has(> .item, + .divider)`.
+    ///
+    /// Relative selectors always starts with a combinator, which relates the matched entity with the
+    /// next selector elements.
+    Has(Vec<Selector>),
+    /// An app-defined pseudo-class, like `:dragging`, which is matched by a predicate registered
+    /// with [`RegisterPseudoClass`](crate::RegisterPseudoClass).
+    Custom(String),
 }
 
 impl PseudoClassElement {
-    /// Computes a weight value for the pseudo-element.
+    /// Checks if the given name is a built-in pseudo-class, including functional ones, like `nth-child`.
+    pub(crate) fn is_built_in(name: &str) -> bool {
+        matches!(name, "nth-child" | "is" | "where" | "not" | "has")
+            || !matches!(
+                PseudoClassElement::from(&CowRcStr::from(name)),
+                PseudoClassElement::Custom(_)
+            )
+    }
+
+    /// Computes the [`Specificity`] of the pseudo-class.
     /// This is based on [Specifity](https://developer.mozilla.org/en-US/docs/Web/CSS/Specificity).
-    fn weight(&self) -> u32 {
+    fn specificity(&self) -> Specificity {
         match self {
-            PseudoClassElement::Hover => 10,
-            PseudoClassElement::Active => 10,
-            PseudoClassElement::Unsupported => 0,
+            // Those takes the specificity of their most specific argument.
+            PseudoClassElement::Is(selectors)
+            | PseudoClassElement::Not(selectors)
+            | PseudoClassElement::Has(selectors) => selectors
+                .iter()
+                .map(|selector| selector.specificity)
+                .max()
+                .unwrap_or_default(),
+            PseudoClassElement::Where(_) => Specificity::default(),
+            _ => Specificity(0, 1, 0),
+        }
+    }
+
+    /// Checks if the given position, starting at `1`, matches the `an+b` notation of [`PseudoClassElement::NthChild`].
+    pub(crate) fn matches_nth(a: i32, b: i32, position: i32) -> bool {
+        if a == 0 {
+            position == b
+        } else {
+            let n = position - b;
+            n % a == 0 && n / a >= 0
         }
     }
 }
@@ -49,8 +245,42 @@ impl std::fmt::Display for PseudoClassElement {
         match self {
             PseudoClassElement::Hover => write!(f, "hover"),
             PseudoClassElement::Active => write!(f, "active"),
-            PseudoClassElement::Unsupported => write!(f, "unsupported"),
+            PseudoClassElement::Disabled => write!(f, "disabled"),
+            PseudoClassElement::Checked => write!(f, "checked"),
+            PseudoClassElement::Selected => write!(f, "selected"),
+            PseudoClassElement::Focus => write!(f, "focus"),
+            PseudoClassElement::FocusVisible => write!(f, "focus-visible"),
+            PseudoClassElement::FocusWithin => write!(f, "focus-within"),
+            PseudoClassElement::FirstChild => write!(f, "first-child"),
+            PseudoClassElement::LastChild => write!(f, "last-child"),
+            PseudoClassElement::NthChild(a, b) => write!(f, "nth-child({a}n{b:+})"),
+            PseudoClassElement::OnlyChild => write!(f, "only-child"),
+            PseudoClassElement::Empty => write!(f, "empty"),
+            PseudoClassElement::Root => write!(f, "root"),
+            PseudoClassElement::Is(selectors) => write!(f, "is({})", SelectorList(selectors)),
+            PseudoClassElement::Where(selectors) => {
+                write!(f, "where({})", SelectorList(selectors))
+            }
+            PseudoClassElement::Not(selectors) => write!(f, "not({})", SelectorList(selectors)),
+            PseudoClassElement::Has(selectors) => write!(f, "has({})", SelectorList(selectors)),
+            PseudoClassElement::Custom(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Helper to display a list of selectors separated by commas.
+struct SelectorList<'a>(&'a [Selector]);
+
+impl std::fmt::Display for SelectorList<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, selector) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            // Relative selectors starts with a combinator, which may be a white space.
+            write!(f, "{}", selector.to_string().trim_start())?;
         }
+        Ok(())
     }
 }
 
@@ -59,17 +289,45 @@ impl<'a> From<&'a CowRcStr<'a>> for PseudoClassElement {
         match value.as_ref() {
             "hover" => PseudoClassElement::Hover,
             "active" => PseudoClassElement::Active,
-            _ => PseudoClassElement::Unsupported,
+            "disabled" => PseudoClassElement::Disabled,
+            "checked" => PseudoClassElement::Checked,
+            "selected" => PseudoClassElement::Selected,
+            "focus" => PseudoClassElement::Focus,
+            "focus-visible" => PseudoClassElement::FocusVisible,
+            "focus-within" => PseudoClassElement::FocusWithin,
+            "first-child" => PseudoClassElement::FirstChild,
+            "last-child" => PseudoClassElement::LastChild,
+            "only-child" => PseudoClassElement::OnlyChild,
+            "empty" => PseudoClassElement::Empty,
+            "root" => PseudoClassElement::Root,
+            name => PseudoClassElement::Custom(name.to_string()),
         }
     }
 }
 
+/// The [specificity](https://developer.mozilla.org/en-US/docs/Web/CSS/Specificity) of a [`Selector`],
+/// as the number of `(id, class, type)` elements it has.
+///
+/// Ids are `#name`, classes includes `.class`, attributes and pseudo-classes, while types are components
+/// names, like `button`. Specificities are compared column by column, so a single id always wins
+/// over any number of classes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+
+impl std::ops::Add for Specificity {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
 /// A selector parsed from a `css` rule. Each selector has a internal hash used to differentiate between many rules in the same sheet.
 #[derive(Debug, Default, Clone)]
 pub struct Selector {
     hash: u64,
     elements: SmallVec<[SelectorElement; 8]>,
-    pub(crate) weight: u32,
+    specificity: Specificity,
 }
 
 impl Selector {
@@ -83,27 +341,31 @@ impl Selector {
         });
 
         let hash = hasher.finish();
-        let weight = Self::weight(&elements);
+        let specificity = Self::compute_specificity(&elements);
 
         Self {
             elements,
             hash,
-            weight,
+            specificity,
         }
     }
 
+    /// The [`Specificity`] of this selector, used on precedence order when applying styles.
+    pub fn specificity(&self) -> Specificity {
+        self.specificity
+    }
+
     /// Builds a selector tree for this selector.
     /// Each node in the tree is composed of many elements, also each node is parent of the next one.
     pub fn get_parent_tree(&self) -> SmallVec<[SmallVec<[&SelectorElement; 8]>; 8]> {
         let mut tree = SmallVec::new();
         let mut current_level = SmallVec::new();
         for element in &self.elements {
-            match element {
-                SelectorElement::Child => {
-                    tree.push(current_level);
-                    current_level = SmallVec::new();
-                }
-                _ => current_level.push(element),
+            if element.is_combinator() {
+                tree.push(current_level);
+                current_level = SmallVec::new();
+            } else {
+                current_level.push(element);
             }
         }
         tree.push(current_level);
@@ -111,21 +373,52 @@ impl Selector {
         tree
     }
 
-    /// Computes a weight value for this selector, to be used on precedence order when applying styles.
+    /// Builds a selector tree for this selector, just like [`get_parent_tree`](Selector::get_parent_tree),
+    /// but also returns the combinator which relates each node with the previous one.
+    ///
+    /// The first node has no previous node, so it's combinator is always `None`.
+    #[allow(clippy::type_complexity)]
+    pub fn get_combinator_tree(
+        &self,
+    ) -> SmallVec<[(Option<&SelectorElement>, SmallVec<[&SelectorElement; 8]>); 8]> {
+        let mut tree = SmallVec::new();
+        let mut current_combinator = None;
+        let mut current_level = SmallVec::new();
+        for element in &self.elements {
+            if element.is_combinator() {
+                tree.push((current_combinator, current_level));
+                current_combinator = Some(element);
+                current_level = SmallVec::new();
+            } else {
+                current_level.push(element);
+            }
+        }
+        tree.push((current_combinator, current_level));
+
+        tree
+    }
+
+    /// Computes the [`Specificity`] of this selector, to be used on precedence order when applying styles.
     ///
     /// This is based on [Specifity](https://developer.mozilla.org/en-US/docs/Web/CSS/Specificity).
-    fn weight(elements: &SmallVec<[SelectorElement; 8]>) -> u32 {
-        elements.iter().fold(0, |acc, element| {
-            let element_weight = match element {
-                SelectorElement::Name(_) => 100,
-                SelectorElement::Component(_) => 1,
-                SelectorElement::Class(_) => 10,
-                SelectorElement::Child => 0,
-                SelectorElement::PseudoClass(pseudo_class) => pseudo_class.weight(),
-                SelectorElement::Any => 0,
-            };
-            acc + element_weight
-        })
+    fn compute_specificity(elements: &SmallVec<[SelectorElement; 8]>) -> Specificity {
+        elements
+            .iter()
+            .fold(Specificity::default(), |acc, element| {
+                let element_specificity = match element {
+                    SelectorElement::Name(_) => Specificity(1, 0, 0),
+                    SelectorElement::Component(_) => Specificity(0, 0, 1),
+                    SelectorElement::Class(_) => Specificity(0, 1, 0),
+                    SelectorElement::Child
+                    | SelectorElement::DirectChild
+                    | SelectorElement::NextSibling
+                    | SelectorElement::SubsequentSibling => Specificity::default(),
+                    SelectorElement::PseudoClass(pseudo_class) => pseudo_class.specificity(),
+                    SelectorElement::Attribute(_) => Specificity(0, 1, 0),
+                    SelectorElement::Any => Specificity::default(),
+                };
+                acc + element_specificity
+            })
     }
 }
 
@@ -145,10 +438,14 @@ impl std::fmt::Display for Selector {
                     result.push_str(c);
                 }
                 SelectorElement::Child => result.push(' '),
+                SelectorElement::DirectChild => result.push_str(" > "),
+                SelectorElement::NextSibling => result.push_str(" + "),
+                SelectorElement::SubsequentSibling => result.push_str(" ~ "),
                 SelectorElement::PseudoClass(c) => {
                     result.push(':');
                     result.push_str(&c.to_string());
                 }
+                SelectorElement::Attribute(attribute) => result.push_str(&attribute.to_string()),
                 SelectorElement::Any => result.push('*'),
             }
         }
@@ -1,20 +1,33 @@
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "sass")]
 use std::{
     ffi::OsStr,
-    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
 };
 
+#[cfg(feature = "sass")]
+use bevy::{asset::AssetPath, utils::HashSet};
 use bevy::{
-    asset::{io::Reader, AssetLoader, AsyncReadExt},
+    asset::{
+        io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadDirectError, ParseAssetPathError,
+    },
     log::warn,
     prelude::Asset,
     reflect::TypePath,
     utils::{AHasher, HashMap},
 };
+#[cfg(feature = "sass")]
 use grass::InputSyntax;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use thiserror::Error;
 
-use crate::{parser::StyleSheetParser, property::PropertyValues, selector::Selector};
+use crate::{
+    media::{MediaCondition, MediaQueryList},
+    parser::{ImportRule, ParsedSheet, StyleSheetParser},
+    property::PropertyValues,
+    selector::Selector,
+};
 
 #[derive(Debug, TypePath, Asset)]
 /// A cascading style sheet (`css`) asset file.
@@ -26,6 +39,7 @@ pub struct StyleSheetAsset {
     path: String,
     hash: u64,
     rules: SmallVec<[StyleRule; 8]>,
+    layers: Vec<String>,
 }
 
 impl StyleSheetAsset {
@@ -33,24 +47,99 @@ impl StyleSheetAsset {
     ///
     /// This used by internal asset loader to keep track of where each asset came from.
     /// If you are creating this struct by hand, you can safely supply an  empty string as path.
+    ///
+    /// `@import` rules are only resolved when loaded by the [`AssetServer`](bevy::prelude::AssetServer), so they are ignored here.
     pub fn parse(path: &str, content: &str) -> Self {
+        let parsed = StyleSheetParser::parse_sheet(content);
+        if !parsed.imports.is_empty() {
+            warn!("Ignoring @import rules of {path}, since it wasn't loaded by the asset server");
+        }
+
+        Self::with_imports(path, content, parsed, vec![])
+    }
+
+    /// Builds a sheet from it's parsed content and the sheets imported by it's `@import` rules.
+    ///
+    /// Imported rules are placed before the sheet own rules, inside the import layer and media query list, if any.
+    fn with_imports(
+        path: &str,
+        content: &str,
+        parsed: ParsedSheet,
+        imported: Vec<(ImportRule, StyleSheetAsset)>,
+    ) -> Self {
         let mut hasher = AHasher::default();
         content.hash(&mut hasher);
+        imported
+            .iter()
+            .for_each(|(_, sheet)| sheet.hash.hash(&mut hasher));
         let hash = hasher.finish();
 
+        let mut rules = SmallVec::new();
+        let mut layers = Vec::new();
+        let declare = |layers: &mut Vec<String>, layer: String| {
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
+        };
+
+        // Imported layers are declared right after the layers declared before the `@import` rule.
+        let mut own_layers = parsed.layers.into_iter().enumerate().peekable();
+        for (index, (import, sheet)) in imported.into_iter().enumerate() {
+            while let Some((_, layer)) = own_layers.next_if(|(i, _)| *i < import.layers_before) {
+                declare(&mut layers, layer);
+            }
+
+            let import_layer = |layer: &str| {
+                let layer = rename_anonymous_layers(layer, index);
+                match &import.layer {
+                    Some(parent) => format!("{parent}.{layer}"),
+                    None => layer,
+                }
+            };
+
+            for layer in &sheet.layers {
+                declare(&mut layers, import_layer(layer));
+            }
+
+            rules.extend(sheet.rules.into_iter().map(|mut rule| {
+                rule.layer = match rule.layer.take() {
+                    Some(layer) => Some(import_layer(&layer)),
+                    None => import.layer.clone(),
+                };
+                if !import.media.0.is_empty() {
+                    rule.media.insert(0, import.media.clone());
+                }
+                rule
+            }));
+        }
+
+        for (_, layer) in own_layers {
+            declare(&mut layers, layer);
+        }
+        rules.extend(parsed.rules);
+
         Self {
             path: path.to_string(),
             hash,
-            rules: StyleSheetParser::parse(content),
+            rules,
+            layers,
         }
     }
 
     /// Returns the [`PropertyValues`] on the given [`Selector`] with the given name.
+    ///
+    /// If there are many rules with the same selector, the last one which declares the property wins.
     pub fn get_properties(&self, selector: &Selector, name: &str) -> Option<&PropertyValues> {
         self.rules
             .iter()
-            .find(|&rule| &rule.selector == selector)
-            .and_then(|rule| rule.properties.get(name))
+            .rev()
+            .filter(|&rule| &rule.selector == selector)
+            .find_map(|rule| rule.properties.get(name))
+    }
+
+    /// Returns the rule at the given index, which is the order it was declared on the sheet.
+    pub fn get(&self, index: usize) -> Option<&StyleRule> {
+        self.rules.get(index)
     }
 
     /// Iterates over all existing rules
@@ -58,6 +147,12 @@ impl StyleSheetAsset {
         self.rules.iter()
     }
 
+    /// Full name of all cascade layers declared with `@layer`, like `widgets.buttons`, on the order
+    /// they were first declared.
+    pub fn layers(&self) -> &[String] {
+        &self.layers
+    }
+
     /// Internal hash computed from content and used for equality and ordering comparison
     pub fn hash(&self) -> u64 {
         self.hash
@@ -80,11 +175,37 @@ pub struct StyleRule {
     pub selector: Selector,
     /// Properties values to be applied on selected entities.
     pub properties: HashMap<String, PropertyValues>,
+    /// Full name of the cascade layer this rule belongs to, like `widgets.buttons`, if any.
+    pub layer: Option<String>,
+    /// Media query lists of all `@media` blocks this rule is inside, which must all match for the rule to be applied.
+    pub media: Vec<MediaQueryList>,
+    /// Conditions of all `@container` blocks this rule is inside, which must all match against the
+    /// nearest ancestor container of each entity for the rule to be applied on it.
+    pub containers: Vec<MediaCondition>,
+}
+
+/// Renames the anonymous layers of an imported sheet, so they are unique among all imported sheets.
+fn rename_anonymous_layers(layer: &str, import_index: usize) -> String {
+    layer
+        .split('.')
+        .map(|segment| match segment.strip_prefix('@') {
+            Some(name) => format!("@{import_index}-{name}"),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
 }
 
 #[derive(Default)]
 pub(crate) struct StyleSheetLoader;
 
+/// Settings of style sheet loaders, which are used to detect `@import` cycles.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StyleSheetLoaderSettings {
+    /// Path of all sheets which are importing the sheet being loaded, from the outermost one.
+    pub imported_by: Vec<String>,
+}
+
 #[derive(Debug, Error)]
 pub enum StyleSheetLoaderError {
     #[error("File not found: {0}")]
@@ -94,25 +215,71 @@ pub enum StyleSheetLoaderError {
     #[cfg(feature = "sass")]
     #[error("Could not compile sass: {0}")]
     SASSError(#[from] Box<grass::Error>),
+    #[error("Invalid import path: {0}")]
+    ImportPath(#[from] ParseAssetPathError),
+    #[error("Import cycle: {0}")]
+    ImportCycle(String),
+    #[error("Could not import sheet: {0}")]
+    Import(#[from] Box<LoadDirectError>),
+}
+
+/// Parses the given `css` and loads all sheets imported by it's `@import` rules, relative to the sheet being loaded.
+///
+/// Imported sheets are loaded directly, so they are registered as loader dependencies and the sheet is loaded
+/// again whenever any imported sheet changes.
+async fn load_with_imports(
+    content: &str,
+    settings: &StyleSheetLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<StyleSheetAsset, StyleSheetLoaderError> {
+    let path = load_context.asset_path().to_string();
+    let parsed = StyleSheetParser::parse_sheet(content);
+
+    let mut imported = Vec::with_capacity(parsed.imports.len());
+    for import in &parsed.imports {
+        let import_path = load_context.asset_path().resolve_embed(&import.path)?;
+
+        let mut imported_by = settings.imported_by.clone();
+        imported_by.push(path.clone());
+        let import_path_str = import_path.to_string();
+        if imported_by.contains(&import_path_str) {
+            imported_by.push(import_path_str);
+            return Err(StyleSheetLoaderError::ImportCycle(imported_by.join(" -> ")));
+        }
+
+        let sheet = load_context
+            .loader()
+            .with_settings(move |settings: &mut StyleSheetLoaderSettings| {
+                settings.imported_by.clone_from(&imported_by);
+            })
+            .direct()
+            .load::<StyleSheetAsset>(import_path)
+            .await
+            .map_err(Box::new)?
+            .take();
+        imported.push((import.clone(), sheet));
+    }
+
+    Ok(StyleSheetAsset::with_imports(
+        &path, content, parsed, imported,
+    ))
 }
 
 impl AssetLoader for StyleSheetLoader {
     type Asset = StyleSheetAsset;
-    type Settings = ();
+    type Settings = StyleSheetLoaderSettings;
     type Error = StyleSheetLoaderError;
 
     async fn load<'a>(
         &'a self,
         reader: &'a mut Reader<'_>,
-        _settings: &'a (),
-        load_context: &'a mut bevy::asset::LoadContext<'_>,
+        settings: &'a StyleSheetLoaderSettings,
+        load_context: &'a mut LoadContext<'_>,
     ) -> Result<StyleSheetAsset, StyleSheetLoaderError> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
         let content = std::str::from_utf8(&bytes)?;
-        let stylesheet =
-            StyleSheetAsset::parse(load_context.path().to_str().unwrap_or_default(), content);
-        Ok(stylesheet)
+        load_with_imports(content, settings, load_context).await
     }
 
     fn extensions(&self) -> &[&str] {
@@ -127,18 +294,17 @@ pub(crate) struct SCSSLoader;
 #[cfg(feature = "sass")]
 impl AssetLoader for SCSSLoader {
     type Asset = StyleSheetAsset;
-    type Settings = ();
+    type Settings = StyleSheetLoaderSettings;
     type Error = StyleSheetLoaderError;
 
     async fn load<'a>(
         &'a self,
         reader: &'a mut Reader<'_>,
-        _settings: &'a Self::Settings,
-        load_context: &'a mut bevy::asset::LoadContext<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
     ) -> Result<StyleSheetAsset, StyleSheetLoaderError> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
-        let content = std::str::from_utf8(&bytes)?;
         let input_syntax = match load_context.path().extension().and_then(OsStr::to_str) {
             Some("scss") => InputSyntax::Scss,
             Some("sass") => InputSyntax::Sass,
@@ -151,16 +317,422 @@ impl AssetLoader for SCSSLoader {
                 InputSyntax::Scss
             }
         };
-        let css = grass::from_string(
-            content,
-            &grass::Options::default().input_syntax(input_syntax),
+
+        let path = load_context.path().to_path_buf();
+        let fs = SassAssetFs::load(&path, bytes, load_context).await;
+        let css = grass::from_path(
+            &path,
+            &grass::Options::default().input_syntax(input_syntax).fs(&fs),
         )?;
-        let stylesheet =
-            StyleSheetAsset::parse(load_context.path().to_str().unwrap_or_default(), &css);
-        Ok(stylesheet)
+        load_with_imports(&css, settings, load_context).await
     }
 
     fn extensions(&self) -> &[&str] {
         &["scss", "sass"]
     }
 }
+
+/// An in-memory file system used by `grass` to resolve Sass `@use`, `@forward` and `@import` rules,
+/// which holds all files that may be imported, read ahead through the asset source of the sheet being loaded.
+#[derive(Debug, Default)]
+#[cfg(feature = "sass")]
+struct SassAssetFs(HashMap<PathBuf, Vec<u8>>);
+
+#[cfg(feature = "sass")]
+impl SassAssetFs {
+    /// Reads the sheet at the given path and all files it may import, recursively, from the same asset source.
+    ///
+    /// Files are read as loader dependencies, so the sheet is loaded again whenever any of them changes.
+    async fn load(path: &Path, content: Vec<u8>, load_context: &mut LoadContext<'_>) -> Self {
+        let source = load_context.asset_path().source().clone_owned();
+        let mut fs = Self::default();
+        let path = normalize_path(path);
+        let mut visited = HashSet::from([path.clone()]);
+        let mut pending = vec![(path, content)];
+
+        while let Some((path, content)) = pending.pop() {
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            for url in sass_import_urls(&String::from_utf8_lossy(&content)) {
+                for candidate in sass_import_candidates(&dir.join(url)) {
+                    if !visited.insert(candidate.clone()) {
+                        continue;
+                    }
+
+                    let asset_path = AssetPath::from_path(&candidate).with_source(source.clone());
+                    // Most candidates doesn't exist, since only one is imported.
+                    if let Ok(bytes) = load_context.read_asset_bytes(asset_path).await {
+                        pending.push((candidate, bytes));
+                    }
+                }
+            }
+            fs.0.insert(path, content);
+        }
+
+        fs
+    }
+}
+
+#[cfg(feature = "sass")]
+impl grass::Fs for SassAssetFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        let path = normalize_path(path);
+        self.0
+            .keys()
+            .any(|file| file != &path && file.starts_with(&path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.0.contains_key(&normalize_path(path))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.0
+            .get(&normalize_path(path))
+            .cloned()
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+}
+
+/// Returns the urls of all Sass `@use`, `@forward` and `@import` rules, ignoring built-in modules,
+/// like `sass:math`, and remote urls.
+#[cfg(feature = "sass")]
+fn sass_import_urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut chars = content.char_indices().peekable();
+
+    // Reads a quoted string, if the next non-whitespace character is a quote.
+    let quoted = |chars: &mut std::iter::Peekable<std::str::CharIndices>| {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let (_, quote) = chars.next_if(|(_, c)| *c == '"' || *c == '\'')?;
+        let mut url = String::new();
+        for (_, c) in chars.by_ref() {
+            if c == quote {
+                return Some(url);
+            }
+            url.push(c);
+        }
+        None
+    };
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '/' if chars.next_if(|(_, c)| *c == '/').is_some() => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '/' if chars.next_if(|(_, c)| *c == '*').is_some() => {
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut escaped = false;
+                for (_, next) in chars.by_ref() {
+                    if next == c && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+            }
+            '@' => {
+                let rest = &content[index + 1..];
+                let Some(rule) = ["use", "forward", "import"].into_iter().find(|rule| {
+                    rest.starts_with(rule)
+                        && rest[rule.len()..].starts_with(|c: char| c.is_whitespace())
+                }) else {
+                    continue;
+                };
+                for _ in 0..rule.len() {
+                    chars.next();
+                }
+
+                // Only `@import` accepts many comma-separated urls.
+                while let Some(url) = quoted(&mut chars) {
+                    if !url.starts_with("sass:") && !url.contains("://") {
+                        urls.push(url);
+                    }
+                    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                    if rule != "import" || chars.next_if(|(_, c)| *c == ',').is_none() {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    urls
+}
+
+/// Returns all paths which `grass` may resolve the given import to, like `_variables.scss` or `variables/index.sass`.
+#[cfg(feature = "sass")]
+fn sass_import_candidates(path: &Path) -> Vec<PathBuf> {
+    let partial = |path: &Path| {
+        let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+        path.with_file_name(format!("_{file_name}"))
+    };
+
+    let paths = match path.extension().and_then(OsStr::to_str) {
+        Some("scss" | "sass" | "css") => vec![path.to_path_buf()],
+        _ => ["sass", "scss", "css"]
+            .into_iter()
+            .flat_map(|extension| {
+                [
+                    path.with_extension(extension),
+                    path.join("index").with_extension(extension),
+                ]
+            })
+            .collect(),
+    };
+
+    paths
+        .into_iter()
+        .flat_map(|path| [partial(&path), path])
+        .map(|path| normalize_path(&path))
+        .collect()
+}
+
+/// Removes all `.` and `..` segments of the given path, without accessing the file system.
+#[cfg(feature = "sass")]
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AssetPlugin, AssetServer, LoadState},
+        prelude::{App, Assets, Handle, MinimalPlugins},
+    };
+
+    use super::*;
+
+    fn rules(sheet: &StyleSheetAsset) -> Vec<(String, Option<&str>, usize)> {
+        sheet
+            .iter()
+            .map(|rule| {
+                (
+                    rule.selector.to_string(),
+                    rule.layer.as_deref(),
+                    rule.media.len(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_imported_sheets() {
+        let base = StyleSheetAsset::parse("", "@layer reset; base {} @layer { anonymous {} }");
+        let buttons = StyleSheetAsset::parse("", "@layer hover { hover {} } button {}");
+        let parsed = StyleSheetParser::parse_sheet(
+            r#"
+            @layer theme;
+            @import "base.css";
+            @import "buttons.css" layer(widgets) (min-width: 800px);
+            @layer { own {} }
+            "#,
+        );
+        let imported = parsed
+            .imports
+            .iter()
+            .cloned()
+            .zip([base, buttons])
+            .collect();
+
+        let sheet = StyleSheetAsset::with_imports("", "", parsed, imported);
+        assert_eq!(
+            rules(&sheet),
+            vec![
+                ("base".to_string(), None, 0),
+                ("anonymous".to_string(), Some("@0-1"), 0),
+                ("hover".to_string(), Some("widgets.hover"), 1),
+                ("button".to_string(), Some("widgets"), 1),
+                ("own".to_string(), Some("@1"), 0),
+            ]
+        );
+        assert_eq!(
+            sheet.layers(),
+            &["theme", "reset", "@0-1", "widgets", "widgets.hover", "@1"]
+        );
+    }
+
+    #[test]
+    fn load_imported_sheets() {
+        let dir = std::env::temp_dir().join(format!("bevy_ecss_imports_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("widgets")).unwrap();
+        for (file, content) in [
+            ("main.css", r#"@import "widgets/button.css"; main {}"#),
+            ("widgets/button.css", r#"@import "../base.css"; button {}"#),
+            ("base.css", "base {}"),
+            ("cycle.css", r#"@import "widgets/cycle.css";"#),
+            ("widgets/cycle.css", r#"@import "/cycle.css";"#),
+            ("missing.css", r#"@import "nothing.css";"#),
+        ] {
+            std::fs::write(dir.join(file), content).unwrap();
+        }
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: dir.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            crate::EcssPlugin,
+        ));
+
+        let load = |app: &mut App, path: &str| {
+            let handle: Handle<StyleSheetAsset> =
+                app.world().resource::<AssetServer>().load(path.to_string());
+            for _ in 0..1000 {
+                app.update();
+                match app.world().resource::<AssetServer>().load_state(&handle) {
+                    LoadState::Loaded => return Ok(handle),
+                    LoadState::Failed(err) => return Err(err.to_string()),
+                    _ => std::thread::sleep(std::time::Duration::from_millis(1)),
+                }
+            }
+            panic!("Timed out while loading {path}");
+        };
+
+        let main = load(&mut app, "main.css").expect("Should load with all imports");
+        let assets = app.world().resource::<Assets<StyleSheetAsset>>();
+        assert_eq!(
+            rules(assets.get(&main).unwrap()),
+            vec![
+                ("base".to_string(), None, 0),
+                ("button".to_string(), None, 0),
+                ("main".to_string(), None, 0),
+            ]
+        );
+
+        let err = load(&mut app, "cycle.css").expect_err("Should fail on import cycles");
+        assert!(
+            err.contains("Import cycle: cycle.css -> widgets/cycle.css -> cycle.css"),
+            "{err}"
+        );
+
+        load(&mut app, "missing.css").expect_err("Should fail on missing imports");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "sass")]
+    fn scan_sass_imports() {
+        let urls = sass_import_urls(
+            r#"
+            @use "sass:math";
+            @use 'variables' as v;
+            @forward "theme/colors" show $accent;
+            @import "mixins", 'widgets/buttons';
+            @import url("https://example.com/remote.css");
+            // @use "commented";
+            /* @import "commented"; */
+            a { content: "@use 'quoted'"; }
+            @include mixins.big;
+            "#,
+        );
+        assert_eq!(
+            urls,
+            vec!["variables", "theme/colors", "mixins", "widgets/buttons"]
+        );
+
+        let candidates = sass_import_candidates(Path::new("sheets/./widgets/../variables"));
+        assert_eq!(
+            candidates[..2],
+            [
+                PathBuf::from("sheets/_variables.sass"),
+                PathBuf::from("sheets/variables.sass"),
+            ]
+        );
+        assert!(candidates.contains(&PathBuf::from("sheets/variables/_index.scss")));
+        assert_eq!(candidates.len(), 12);
+
+        assert_eq!(
+            sass_import_candidates(Path::new("sheets/base.css")),
+            vec![
+                PathBuf::from("sheets/_base.css"),
+                PathBuf::from("sheets/base.css")
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sass")]
+    fn load_sass_imports() {
+        let dir = std::env::temp_dir().join(format!("bevy_ecss_sass_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sheets/widgets")).unwrap();
+        std::fs::create_dir_all(dir.join("sheets/theme")).unwrap();
+        for (file, content) in [
+            (
+                "sheets/main.scss",
+                r#"@use "theme"; @import "widgets/buttons"; #title { color: theme.$accent; }"#,
+            ),
+            ("sheets/theme/_index.scss", r#"@forward "../variables";"#),
+            ("sheets/_variables.scss", "$accent: red;"),
+            (
+                "sheets/widgets/_buttons.scss",
+                r#"@import "../mixins"; button { @include big; }"#,
+            ),
+            ("sheets/_mixins.sass", "@mixin big\n  width: 10px\n"),
+        ] {
+            std::fs::write(dir.join(file), content).unwrap();
+        }
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: dir.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            crate::EcssPlugin,
+        ));
+
+        let handle: Handle<StyleSheetAsset> = app
+            .world()
+            .resource::<AssetServer>()
+            .load("sheets/main.scss");
+        for _ in 0..1000 {
+            app.update();
+            match app.world().resource::<AssetServer>().load_state(&handle) {
+                LoadState::Loaded => break,
+                LoadState::Failed(err) => panic!("Should load all partials: {err}"),
+                _ => std::thread::sleep(std::time::Duration::from_millis(1)),
+            }
+        }
+
+        let assets = app.world().resource::<Assets<StyleSheetAsset>>();
+        let sheet = assets.get(&handle).expect("Should be loaded");
+        assert_eq!(
+            sheet
+                .iter()
+                .map(|rule| rule.selector.to_string())
+                .collect::<Vec<_>>(),
+            vec!["button", "#title"]
+        );
+        assert_eq!(
+            sheet
+                .get(1)
+                .and_then(|rule| rule.properties.get("color"))
+                .and_then(PropertyValues::identifier),
+            Some("red")
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
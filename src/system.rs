@@ -1,22 +1,36 @@
+use std::{marker::PhantomData, sync::Mutex};
+
 use bevy::{
     ecs::{
-        component::ComponentTicks,
+        component::{ComponentId, ComponentTicks, Tick},
         system::{SystemParam, SystemState},
+        world::EntityRef,
     },
     log::{debug, error, trace},
     prelude::{
-        AssetEvent, AssetId, Assets, Changed, Children, Component, Deref, DerefMut, Entity,
-        EventReader, Mut, Name, Query, Res, ResMut, Resource, With, World,
+        AppTypeRegistry, AssetEvent, AssetId, Assets, Changed, Children, Component, Deref,
+        DerefMut, DetectChanges, Entity, EventReader, Mut, Name, Parent, Query, ReflectComponent,
+        Res, ResMut, Resource, With, World,
     },
+    reflect::{GetPath, ReflectRef},
     ui::{Interaction, Node},
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use smallvec::SmallVec;
 
 use crate::{
-    component::{Class, MatchSelectorElement, StyleSheet},
-    property::{SelectedEntities, StyleSheetState, TrackedEntities},
-    selector::{PseudoClassElement, Selector, SelectorElement},
+    component::{
+        Checked, Class, CssAttributes, Disabled, Focused, MatchSelectorElement, Selected,
+        StyleSheet,
+    },
+    container::{ContainerParam, ContainerQueryState, ContainerResult},
+    focus::FocusVisible,
+    media::{evaluate_media, MediaParam, MediaQueryState},
+    property::{
+        CustomProperties, CustomPropertiesState, EcssVariables, PropertyValues, StyleSheetState,
+        TrackedEntities, CURRENT_COLOR,
+    },
+    selector::{AttributeElement, PseudoClassElement, Selector, SelectorElement},
     StyleSheetAsset,
 };
 
@@ -48,6 +62,96 @@ pub(crate) struct ComponentFilterRegistry(
     pub HashMap<&'static str, Box<dyn ComponentFilter + Send + Sync>>,
 );
 
+/// Change ticks of the component watched by a custom pseudo-class, which are given to it's predicate
+/// registered with [`RegisterPseudoClass`](crate::RegisterPseudoClass).
+#[derive(Debug, Clone, Copy)]
+pub struct PseudoClassTicks {
+    ticks: ComponentTicks,
+    last_run: Tick,
+    this_run: Tick,
+}
+
+impl PseudoClassTicks {
+    /// Returns `true` if the watched component was added since the style sheet was last applied.
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.last_run, self.this_run)
+    }
+
+    /// Returns `true` if the watched component was added or changed since the style sheet was last applied.
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.last_run, self.this_run)
+    }
+
+    /// Returns the raw change ticks of the watched component.
+    pub fn component_ticks(&self) -> ComponentTicks {
+        self.ticks
+    }
+}
+
+/// Utility trait which helps to deal with app-defined pseudo-classes.
+/// Each trait is implemented for a predicate which watches a single `[Component]`.
+pub(crate) trait PseudoClassFilter {
+    /// Checks if the given entity has the watched component and matches the predicate.
+    fn matches(&self, entity: EntityRef, last_run: Tick, this_run: Tick) -> bool;
+
+    /// Return the change ticks of the watched component on the given entity.
+    fn get_change_ticks(&self, entity: EntityRef) -> Option<ComponentTicks>;
+
+    /// Checks if any entity on the given list has the watched component removed.
+    fn any_removed(&self, world: &World, entities: &SmallVec<[Entity; 8]>) -> bool;
+}
+
+/// A [`PseudoClassFilter`] which watches the component `T`.
+pub(crate) struct PseudoClassPredicate<T, F> {
+    predicate: F,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, F> PseudoClassPredicate<T, F> {
+    pub fn new(predicate: F) -> Self {
+        Self {
+            predicate,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F> PseudoClassFilter for PseudoClassPredicate<T, F>
+where
+    T: Component,
+    F: Fn(EntityRef, PseudoClassTicks) -> bool,
+{
+    fn matches(&self, entity: EntityRef, last_run: Tick, this_run: Tick) -> bool {
+        entity.get_change_ticks::<T>().is_some_and(|ticks| {
+            (self.predicate)(
+                entity,
+                PseudoClassTicks {
+                    ticks,
+                    last_run,
+                    this_run,
+                },
+            )
+        })
+    }
+
+    fn get_change_ticks(&self, entity: EntityRef) -> Option<ComponentTicks> {
+        entity.get_change_ticks::<T>()
+    }
+
+    fn any_removed(&self, world: &World, entities: &SmallVec<[Entity; 8]>) -> bool {
+        any_component_removed::<T>(world, entities)
+    }
+}
+
+/// Holds the registered [`PseudoClassFilter`] using the pseudo-class name as key.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub(crate) struct PseudoClassRegistry {
+    #[deref]
+    pub filters: HashMap<&'static str, Box<dyn PseudoClassFilter + Send + Sync>>,
+    /// Names of unregistered pseudo-classes already reported, so each one is logged only once.
+    pub unregistered: Mutex<HashSet<String>>,
+}
+
 /// An utility [`SystemParam`] query which is used in [`prepare`] system.
 #[derive(SystemParam)]
 pub(crate) struct CssQueryParam<'w, 's> {
@@ -61,7 +165,10 @@ pub(crate) struct CssQueryParam<'w, 's> {
     names: Query<'w, 's, (Entity, &'static Name)>,
     classes: Query<'w, 's, (Entity, &'static Class)>,
     children: Query<'w, 's, &'static Children, With<Node>>,
+    parents: Query<'w, 's, &'static Parent, With<Node>>,
     any: Query<'w, 's, Entity, With<Node>>,
+    media: MediaParam<'w, 's>,
+    containers: ContainerParam<'w, 's>,
 }
 
 /// Holds an previous prepared [`CssQueryParam`];
@@ -79,19 +186,216 @@ pub(crate) fn prepare(world: &mut World) {
     world.resource_scope(|world, mut params: Mut<PrepareParams>| {
         world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
             let css_query = params.get(world);
-            let state = prepare_state(world, css_query, &mut registry);
+            let mut state = prepare_state(world, css_query, &mut registry);
+
+            let containers = std::mem::take(&mut state.containers);
+            if let Some(mut container_state) = world.get_resource_mut::<ContainerQueryState>() {
+                for (root, results) in containers {
+                    if results.is_empty() {
+                        container_state.0.remove(&root);
+                    } else {
+                        container_state.0.insert(root, results);
+                    }
+                }
+            }
+
+            let media = std::mem::take(&mut state.media);
+            if let Some(mut media_state) = world.get_resource_mut::<MediaQueryState>() {
+                for (root, results) in media {
+                    if results.is_empty() {
+                        media_state.0.remove(&root);
+                    } else {
+                        media_state.0.insert(root, results);
+                    }
+                }
+            }
 
             if state.has_any_selected_entities() {
+                let custom_properties = prepare_custom_properties(
+                    world,
+                    &state,
+                    world.get_resource::<CustomPropertiesState>(),
+                );
+
                 let mut state_res = world
                     .get_resource_mut::<StyleSheetState>()
                     .expect("Should be added by plugin");
 
                 *state_res = state;
+
+                world.insert_resource(custom_properties);
             }
         });
     });
 }
 
+/// Computes the custom properties, like `--accent`, of each entity selected on the given state.
+///
+/// Each entity inherits all custom properties of it's parent and overrides them with the ones declared by
+/// rules which matches it, following the cascade order of [`StyleSheetState::cascade`], where `!important`
+/// declarations wins over normal ones.
+///
+/// All entities on the scope of the roots being applied are computed again, since rules which matched them
+/// before may not match anymore. Entities outside of it, including the scope of nested [`StyleSheet`] roots
+/// which aren't being applied, keeps their previous computed custom properties.
+pub(crate) fn prepare_custom_properties(
+    world: &World,
+    state: &StyleSheetState,
+    previous: Option<&CustomPropertiesState>,
+) -> CustomPropertiesState {
+    let scope = &state.scope;
+    let previous = previous.map(|previous| {
+        CustomPropertiesState(
+            previous
+                .iter()
+                .filter(|(e, _)| world.get_entity(**e).is_some() && !scope.contains(*e))
+                .map(|(e, properties)| (*e, properties.clone()))
+                .collect(),
+        )
+    });
+    let previous = previous.as_ref();
+
+    let mut declared = HashMap::<Entity, CustomProperties>::default();
+
+    if let Some(assets) = world.get_resource::<Assets<StyleSheetAsset>>() {
+        // Important declarations are applied after all normal ones, so they always win.
+        for important in [false, true] {
+            for (asset_id, rule, entities) in state.cascade(important) {
+                let Some(rule) = assets.get(*asset_id).and_then(|sheet| sheet.get(*rule)) else {
+                    continue;
+                };
+
+                for entity in entities {
+                    let declared = declared.entry(*entity).or_default();
+                    for (name, values) in &rule.properties {
+                        if values.is_important() == important
+                            && (name.starts_with("--") || name == CURRENT_COLOR)
+                        {
+                            declared.insert(name.clone(), values.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut computed = HashMap::default();
+    for &entity in declared.keys().chain(scope) {
+        compute_custom_properties(world, entity, &declared, previous, &mut computed);
+    }
+
+    let mut custom_properties = previous
+        .map(|previous| {
+            CustomPropertiesState(
+                previous
+                    .iter()
+                    .filter(|(e, _)| !declared.contains_key(*e))
+                    .map(|(e, properties)| (*e, properties.clone()))
+                    .collect(),
+            )
+        })
+        .unwrap_or_default();
+
+    custom_properties.extend(
+        computed
+            .into_iter()
+            .filter(|(_, properties)| !properties.is_empty()),
+    );
+
+    custom_properties
+}
+
+/// Returns the given [`StyleSheet`] roots and all their descendants, skipping the descendants of nested
+/// [`StyleSheet`] roots which aren't on the given list.
+pub(crate) fn get_applied_scope(world: &World, roots: &[Entity]) -> HashSet<Entity> {
+    let mut scope = HashSet::default();
+    let mut stack = roots.to_vec();
+    while let Some(entity) = stack.pop() {
+        if !scope.insert(entity) {
+            continue;
+        }
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().filter(|child| {
+                world.get::<StyleSheet>(**child).is_none() || roots.contains(*child)
+            }));
+        }
+    }
+    scope
+}
+
+/// Computes the custom properties of the given entity, by inheriting it's parent ones.
+fn compute_custom_properties(
+    world: &World,
+    entity: Entity,
+    declared: &HashMap<Entity, CustomProperties>,
+    previous: Option<&CustomPropertiesState>,
+    computed: &mut HashMap<Entity, CustomProperties>,
+) -> CustomProperties {
+    if let Some(properties) = computed.get(&entity) {
+        return properties.clone();
+    }
+
+    let properties = match declared.get(&entity) {
+        Some(own) => {
+            let inherited = world
+                .get::<Parent>(entity)
+                .map(|parent| {
+                    compute_custom_properties(world, parent.get(), declared, previous, computed)
+                })
+                .unwrap_or_default();
+
+            let mut properties = inherited.clone();
+            properties.extend(own.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+            // Custom properties may reference each other, so they are resolved after all of them are known.
+            for name in own.keys() {
+                match properties[name].substitute_vars(&properties) {
+                    Some(values) => {
+                        properties.insert(name.clone(), values);
+                    }
+                    None => match inherited.get(name) {
+                        Some(values) => {
+                            properties.insert(name.clone(), values.clone());
+                        }
+                        None => {
+                            properties.remove(name);
+                        }
+                    },
+                }
+            }
+
+            // `currentColor` on `color` property itself refers to the inherited color.
+            if let Some(color) = properties.get(CURRENT_COLOR).cloned() {
+                if color.has_current_color() {
+                    match inherited.get(CURRENT_COLOR) {
+                        Some(inherited) => {
+                            let color = color.substitute_current_color(inherited);
+                            properties.insert(CURRENT_COLOR.to_string(), color);
+                        }
+                        None => {
+                            properties.remove(CURRENT_COLOR);
+                        }
+                    }
+                }
+            }
+
+            properties
+        }
+        None => match previous.and_then(|previous| previous.get(&entity)) {
+            Some(properties) => properties.clone(),
+            None => world
+                .get::<Parent>(entity)
+                .map(|parent| {
+                    compute_custom_properties(world, parent.get(), declared, previous, computed)
+                })
+                .unwrap_or_default(),
+        },
+    };
+
+    computed.insert(entity, properties.clone());
+    properties
+}
+
 /// Prepare state to be used by [`Property`](crate::Property) systems
 pub(crate) fn prepare_state(
     world: &World,
@@ -102,23 +406,48 @@ pub(crate) fn prepare_state(
 
     for (root, maybe_children, sheet_handle) in &css_query.nodes {
         bevy::log::info!("STYLE SHEET: {sheet_handle:?}");
+        state.roots.push(root);
+        let media = css_query.media.context(root);
+        state.media.push((
+            root,
+            evaluate_media(&media, sheet_handle.handles(), &css_query.assets),
+        ));
+        let mut containers = SmallVec::new();
+
         for id in sheet_handle.handles().iter().map(|h| h.id()) {
             bevy::log::info!("Checking sheet id: {id:?}");
             if let Some(sheet) = css_query.assets.get(id) {
                 let mut tracked_entities = TrackedEntities::default();
-                let mut selected_entities = SelectedEntities::default();
+                let mut selected_entities = SmallVec::<[_; 8]>::new();
                 debug!("Applying style {}", sheet.path());
 
-                for rule in sheet.iter() {
-                    let entities = select_entities(
-                        root,
-                        maybe_children,
-                        &rule.selector,
-                        world,
-                        &css_query,
-                        registry,
-                        &mut tracked_entities,
-                    );
+                let scope = SelectionScope {
+                    root,
+                    maybe_children,
+                    world,
+                    css_query: &css_query,
+                };
+
+                for (index, rule) in sheet.iter().enumerate() {
+                    if !rule.media.iter().all(|list| list.matches(&media)) {
+                        continue;
+                    }
+
+                    let mut entities =
+                        select_entities(&scope, &rule.selector, registry, &mut tracked_entities);
+
+                    if !rule.containers.is_empty() {
+                        entities.retain(|entity| {
+                            let matches = css_query.containers.matches(*entity, sheet, index);
+                            containers.push(ContainerResult {
+                                entity: *entity,
+                                sheet: id,
+                                rule: index,
+                                matches,
+                            });
+                            matches
+                        });
+                    }
 
                     bevy::log::info!(
                         "Applying rule ({}) on {} entities",
@@ -126,72 +455,149 @@ pub(crate) fn prepare_state(
                         entities.len()
                     );
 
-                    selected_entities.push((rule.selector.clone(), entities));
+                    selected_entities.push((index, entities));
                 }
 
-                selected_entities.sort_by(|(a, _), (b, _)| a.weight.cmp(&b.weight));
-                state.push((id, tracked_entities, selected_entities));
+                state.push(id, tracked_entities, selected_entities);
             }
         }
+
+        state.containers.push((root, containers));
     }
 
+    state.scope = get_applied_scope(world, &state.roots);
+    state.sort(&css_query.assets);
     state
 }
 
+/// The scope where selectors are matched, which is composed of an entity with a [`StyleSheet`]
+/// component and all it's descendants.
+struct SelectionScope<'a, 'w, 's> {
+    root: Entity,
+    maybe_children: Option<&'a Children>,
+    world: &'a World,
+    css_query: &'a CssQueryParam<'w, 's>,
+}
+
 /// Select all entities using the given [`Selector`](crate::Selector).
 ///
 /// If no [`Children`] is supplied, then the selector is applied only on root entity.
 fn select_entities(
-    root: Entity,
-    maybe_children: Option<&Children>,
+    scope: &SelectionScope,
     selector: &Selector,
-    world: &World,
-    css_query: &CssQueryParam,
     registry: &mut ComponentFilterRegistry,
     tracked_entities: &mut TrackedEntities,
 ) -> SmallVec<[Entity; 8]> {
-    let mut parent_tree = selector.get_parent_tree();
-
-    if parent_tree.is_empty() {
-        return SmallVec::new();
-    }
-
     // Build an entity tree with all entities that may be selected.
     // This tree is composed of the entity root and all descendants entities.
-    let mut entity_tree = std::iter::once(root)
+    let entity_tree = std::iter::once(scope.root)
         .chain(
-            maybe_children
-                .map(|children| get_children_recursively(children, &css_query.children))
+            scope
+                .maybe_children
+                .map(|children| get_children_recursively(children, &scope.css_query.children))
                 .unwrap_or_default(),
         )
         .collect::<SmallVec<_>>();
 
+    select_entities_from(scope, selector, entity_tree, registry, tracked_entities)
+}
+
+/// Select all entities using the given [`Selector`](crate::Selector), starting at the given entity tree.
+///
+/// The first selector node is matched against the given entity tree, and every next node is matched
+/// against entities related to the previous matched ones, accordingly to their combinator.
+fn select_entities_from(
+    scope: &SelectionScope,
+    selector: &Selector,
+    mut entity_tree: SmallVec<[Entity; 8]>,
+    registry: &mut ComponentFilterRegistry,
+    tracked_entities: &mut TrackedEntities,
+) -> SmallVec<[Entity; 8]> {
+    let mut combinator_tree = selector.get_combinator_tree();
+
+    if combinator_tree.is_empty() {
+        return SmallVec::new();
+    }
+
     loop {
-        // TODO: Rework this to use a index to avoid recreating parent_tree every time the systems runs.
+        // TODO: Rework this to use a index to avoid recreating combinator_tree every time the systems runs.
         // This is has little to no impact on performance, since this system doesn't runs often.
-        let node = parent_tree.remove(0);
-
-        let entities = select_entities_node(
-            node,
-            world,
-            css_query,
-            registry,
-            entity_tree.clone(),
-            tracked_entities,
-        );
+        let (_, node) = combinator_tree.remove(0);
 
-        if parent_tree.is_empty() {
+        let entities =
+            select_entities_node(node, scope, registry, entity_tree.clone(), tracked_entities);
+
+        if combinator_tree.is_empty() || entities.is_empty() {
             break entities;
         } else {
-            entity_tree = entities
-                .into_iter()
-                .filter_map(|e| css_query.children.get(e).ok())
-                .flat_map(|children| get_children_recursively(children, &css_query.children))
-                .collect();
+            let combinator = combinator_tree[0]
+                .0
+                .expect("Only the first node has no combinator");
+            entity_tree = get_related_entities(combinator, scope.root, entities, scope.css_query);
+        }
+    }
+}
+
+/// Returns all entities which are related to the given ones by the given combinator.
+/// For instance, [`SelectorElement::Child`] returns all descendants of given entities.
+///
+/// Siblings of the `root` entity are never returned, since they aren't affected by it's style sheet.
+fn get_related_entities(
+    combinator: &SelectorElement,
+    root: Entity,
+    entities: SmallVec<[Entity; 8]>,
+    css_query: &CssQueryParam,
+) -> SmallVec<[Entity; 8]> {
+    match combinator {
+        SelectorElement::Child => entities
+            .into_iter()
+            .filter_map(|e| css_query.children.get(e).ok())
+            .flat_map(|children| get_children_recursively(children, &css_query.children))
+            .collect(),
+        SelectorElement::DirectChild => entities
+            .into_iter()
+            .filter_map(|e| css_query.children.get(e).ok())
+            .flat_map(|children| children.iter().copied())
+            .collect(),
+        SelectorElement::NextSibling => entities
+            .into_iter()
+            .filter(|&e| e != root)
+            .filter_map(|e| get_next_siblings(e, css_query).next())
+            .collect(),
+        SelectorElement::SubsequentSibling => {
+            let mut siblings = SmallVec::<[Entity; 8]>::new();
+            for e in entities.into_iter().filter(|&e| e != root) {
+                for sibling in get_next_siblings(e, css_query) {
+                    // Many entities may share the same siblings, so avoid duplicating those.
+                    if !siblings.contains(&sibling) {
+                        siblings.push(sibling);
+                    }
+                }
+            }
+            siblings
         }
+        _ => unreachable!("{combinator:?} isn't a combinator"),
     }
 }
 
+/// Returns all siblings which comes after the given entity on it's parent [`Children`] list.
+fn get_next_siblings<'a>(
+    entity: Entity,
+    css_query: &'a CssQueryParam,
+) -> impl Iterator<Item = Entity> + 'a {
+    css_query
+        .parents
+        .get(entity)
+        .ok()
+        .and_then(|parent| css_query.children.get(parent.get()).ok())
+        .map(|children| children.as_ref())
+        .unwrap_or_default()
+        .iter()
+        .skip_while(move |&&e| e != entity)
+        .skip(1)
+        .copied()
+}
+
 #[derive(Debug, Default, Clone, Deref, DerefMut)]
 struct FilteredEntities(SmallVec<[Entity; 8]>);
 
@@ -199,15 +605,18 @@ struct FilteredEntities(SmallVec<[Entity; 8]>);
 struct MatchedEntities(SmallVec<[Entity; 8]>);
 
 /// Filter entities matching the given selectors.
-/// This function is called once per node on tree returned by [`get_parent_tree`](Selector::get_parent_tree)
+/// This function is called once per node on tree returned by [`get_combinator_tree`](Selector::get_combinator_tree)
 fn select_entities_node(
     node: SmallVec<[&SelectorElement; 8]>,
-    world: &World,
-    css_query: &CssQueryParam,
+    scope: &SelectionScope,
     registry: &mut ComponentFilterRegistry,
     entities: SmallVec<[Entity; 8]>,
     tracked_entities: &mut TrackedEntities,
 ) -> SmallVec<[Entity; 8]> {
+    let SelectionScope {
+        world, css_query, ..
+    } = scope;
+
     node.into_iter().fold(entities, |entities, element| {
         let (filtered, matched) = match element {
             SelectorElement::Name(name) => {
@@ -219,12 +628,37 @@ fn select_entities_node(
             SelectorElement::Component(component) => {
                 get_entities_with_component(component.as_str(), world, registry, entities)
             }
+            SelectorElement::PseudoClass(
+                PseudoClassElement::Is(selectors) | PseudoClassElement::Where(selectors),
+            ) => {
+                let selected = select_entities_list(scope, selectors, registry, tracked_entities);
+                get_entities_in(entities, &selected, true)
+            }
+            SelectorElement::PseudoClass(PseudoClassElement::Not(selectors)) => {
+                let selected = select_entities_list(scope, selectors, registry, tracked_entities);
+                get_entities_in(entities, &selected, false)
+            }
+            SelectorElement::PseudoClass(PseudoClassElement::Has(selectors)) => {
+                get_entities_with_pseudo_class_has(
+                    scope,
+                    selectors,
+                    entities,
+                    registry,
+                    tracked_entities,
+                )
+            }
             SelectorElement::PseudoClass(pseudo_class) => {
-                get_entities_with_pseudo_class(world, *pseudo_class, entities.clone())
+                get_entities_with_pseudo_class(world, pseudo_class, entities.clone())
+            }
+            SelectorElement::Attribute(attribute) => {
+                get_entities_with_attribute(world, attribute, entities)
             }
             SelectorElement::Any => get_entities_with_any_component(&css_query.any, entities),
-            // All child elements are filtered by [`get_parent_tree`](Selector::get_parent_tree)
-            SelectorElement::Child => unreachable!(),
+            // All combinators are filtered by [`get_combinator_tree`](Selector::get_combinator_tree)
+            SelectorElement::Child
+            | SelectorElement::DirectChild
+            | SelectorElement::NextSibling
+            | SelectorElement::SubsequentSibling => unreachable!(),
         };
 
         if !matched.is_empty() {
@@ -267,11 +701,98 @@ where
     )
 }
 
+/// Select all entities matched by any of the given selectors, without duplicates.
+///
+/// This is used by pseudo-classes which accepts a selector list, like `:is(.a, .b)`.
+fn select_entities_list(
+    scope: &SelectionScope,
+    selectors: &[Selector],
+    registry: &mut ComponentFilterRegistry,
+    tracked_entities: &mut TrackedEntities,
+) -> SmallVec<[Entity; 8]> {
+    let mut selected = SmallVec::<[Entity; 8]>::new();
+    for selector in selectors {
+        for entity in select_entities(scope, selector, registry, tracked_entities) {
+            if !selected.contains(&entity) {
+                selected.push(entity);
+            }
+        }
+    }
+    selected
+}
+
+/// Utility function to filter any entities which are, or aren't, on the given `selected` list.
+///
+/// Nothing is returned as matched, since entities matched by nested selectors are tracked
+/// by it's own elements when calling [`select_entities`].
+fn get_entities_in(
+    entities: SmallVec<[Entity; 8]>,
+    selected: &[Entity],
+    contains: bool,
+) -> (FilteredEntities, MatchedEntities) {
+    let filtered = entities
+        .into_iter()
+        .filter(|e| selected.contains(e) == contains)
+        .collect();
+
+    (FilteredEntities(filtered), Default::default())
+}
+
+/// Utility function to filter any entities matching a [`PseudoClassElement::Has`].
+/// Each entity is used as the anchor of the given relative selectors, like `> .item` on `.slot:has(> .item)`,
+/// and it's matched if any relative selector selects at least one entity.
+///
+/// Returns a list with entities which matches the pseudo-class and a list with all entities which may
+/// change the result, which are the entities themselves, their descendants and their next siblings.
+fn get_entities_with_pseudo_class_has(
+    scope: &SelectionScope,
+    selectors: &[Selector],
+    entities: SmallVec<[Entity; 8]>,
+    registry: &mut ComponentFilterRegistry,
+    tracked_entities: &mut TrackedEntities,
+) -> (FilteredEntities, MatchedEntities) {
+    let mut filtered = SmallVec::new();
+    let mut matched = SmallVec::<[Entity; 8]>::new();
+
+    for entity in entities {
+        let anchor = smallvec::smallvec![entity];
+        if selectors.iter().any(|selector| {
+            !select_entities_from(scope, selector, anchor.clone(), registry, tracked_entities)
+                .is_empty()
+        }) {
+            filtered.push(entity);
+        }
+
+        // Whenever a relative entity appears, disappears or changes, the anchor must be matched again.
+        let relatives = get_related_entities(
+            &SelectorElement::Child,
+            scope.root,
+            anchor.clone(),
+            scope.css_query,
+        )
+        .into_iter()
+        .chain(get_related_entities(
+            &SelectorElement::SubsequentSibling,
+            scope.root,
+            anchor,
+            scope.css_query,
+        ));
+
+        for e in std::iter::once(entity).chain(relatives) {
+            if !matched.contains(&e) {
+                matched.push(e);
+            }
+        }
+    }
+
+    (FilteredEntities(filtered), MatchedEntities(matched))
+}
+
 /// Utility function to filter any entities matching a [`PseudoClassElement`]
 /// Returns new filtered list of entities and a list of entities matched by the query.
 fn get_entities_with_pseudo_class(
     world: &World,
-    pseudo_class: PseudoClassElement,
+    pseudo_class: &PseudoClassElement,
     entities: SmallVec<[Entity; 8]>,
 ) -> (FilteredEntities, MatchedEntities) {
     match pseudo_class {
@@ -281,14 +802,97 @@ fn get_entities_with_pseudo_class(
         PseudoClassElement::Active => {
             get_entities_with_pseudo_class_interaction(world, entities, &Interaction::Pressed)
         }
-        PseudoClassElement::Unsupported => (FilteredEntities(entities), Default::default()),
+        PseudoClassElement::Disabled => {
+            get_entities_with_pseudo_class_marker::<Disabled>(world, entities)
+        }
+        PseudoClassElement::Checked => {
+            get_entities_with_pseudo_class_marker::<Checked>(world, entities)
+        }
+        PseudoClassElement::Selected => {
+            get_entities_with_pseudo_class_marker::<Selected>(world, entities)
+        }
+        PseudoClassElement::Focus => {
+            get_entities_with_pseudo_class_marker::<Focused>(world, entities)
+        }
+        PseudoClassElement::FocusVisible => {
+            get_entities_with_pseudo_class_marker::<FocusVisible>(world, entities)
+        }
+        PseudoClassElement::FocusWithin => {
+            get_entities_with_pseudo_class_focus_within(world, entities)
+        }
+        PseudoClassElement::FirstChild
+        | PseudoClassElement::LastChild
+        | PseudoClassElement::NthChild(_, _)
+        | PseudoClassElement::OnlyChild
+        | PseudoClassElement::Empty
+        | PseudoClassElement::Root => {
+            get_entities_with_pseudo_class_structural(world, entities, pseudo_class)
+        }
+        // Pseudo-classes with nested selectors are handled by [`select_entities_node`]
+        PseudoClassElement::Is(_)
+        | PseudoClassElement::Where(_)
+        | PseudoClassElement::Not(_)
+        | PseudoClassElement::Has(_) => unreachable!(),
+        PseudoClassElement::Custom(name) => {
+            get_entities_with_pseudo_class_custom(world, entities, name)
+        }
     }
 }
 
+/// Utility function to filter any entities matching a structural [`PseudoClassElement`], like
+/// [`PseudoClassElement::FirstChild`] or [`PseudoClassElement::Empty`].
+/// This function looks for the entity position on it's [`Parent`] [`Children`] list or, in case of
+/// [`PseudoClassElement::Empty`], for the entity own [`Children`].
+/// Returns a list with entities which matches the pseudo-class and a list of entities which where matched.
+fn get_entities_with_pseudo_class_structural(
+    world: &World,
+    entities: SmallVec<[Entity; 8]>,
+    pseudo_class: &PseudoClassElement,
+) -> (FilteredEntities, MatchedEntities) {
+    let filtered = entities
+        .iter()
+        .copied()
+        .filter(|&e| {
+            let (position, count) = get_sibling_position(world, e);
+            match pseudo_class {
+                PseudoClassElement::FirstChild => position == 0,
+                PseudoClassElement::LastChild => position + 1 == count,
+                PseudoClassElement::NthChild(a, b) => {
+                    PseudoClassElement::matches_nth(*a, *b, position as i32 + 1)
+                }
+                PseudoClassElement::OnlyChild => count == 1,
+                PseudoClassElement::Empty => world
+                    .get::<Children>(e)
+                    .is_none_or(|children| children.is_empty()),
+                PseudoClassElement::Root => world.get::<Parent>(e).is_none(),
+                _ => unreachable!("{pseudo_class} isn't a structural pseudo-class"),
+            }
+        })
+        .collect::<SmallVec<_>>();
+
+    (FilteredEntities(filtered), MatchedEntities(entities))
+}
+
+/// Returns the position of the given entity among it's siblings and how many siblings there are, including itself.
+///
+/// Entities without a [`Parent`] are considered the only child.
+fn get_sibling_position(world: &World, entity: Entity) -> (usize, usize) {
+    world
+        .get::<Parent>(entity)
+        .and_then(|parent| world.get::<Children>(parent.get()))
+        .and_then(|children| {
+            children
+                .iter()
+                .position(|&e| e == entity)
+                .map(|position| (position, children.len()))
+        })
+        .unwrap_or((0, 1))
+}
+
 /// Utility function to filter any entities matching a [`PseudoClassElement::Hover`] or
 /// [`PseudoClassElement::Active`] variant
 /// This function looks for [`Interaction`] component with [`Interaction::Hovered`] or
-/// [`Interaction::Pressed`] variant.
+/// [`Interaction::Pressed`] variant. Entities with [`Disabled`] component are never hovered nor pressed.
 /// Returns a list with entities which are hovered or pressed and a list of entities which where matched.
 fn get_entities_with_pseudo_class_interaction(
     world: &World,
@@ -301,6 +905,7 @@ fn get_entities_with_pseudo_class_interaction(
         .filter(|&e| {
             world
                 .get_entity(e)
+                .filter(|e| !e.contains::<Disabled>())
                 .and_then(|e| e.get::<Interaction>())
                 .is_some_and(|i| i == interaction)
         })
@@ -309,57 +914,207 @@ fn get_entities_with_pseudo_class_interaction(
     (FilteredEntities(filtered), MatchedEntities(entities))
 }
 
-/// Filters entities which have the components specified on selector, like "a" or "button".
-///
-/// The component must be registered on [`ComponentFilterRegistry`]
-fn get_entities_with_component(
-    name: &str,
+/// Utility function to filter any entities matching a widget state [`PseudoClassElement`], like
+/// [`PseudoClassElement::Disabled`] or [`PseudoClassElement::Checked`].
+/// This function looks for the marker component `T`, like [`Disabled`] or [`Checked`].
+/// Returns a list with entities which has the marker component and a list of entities which where matched.
+fn get_entities_with_pseudo_class_marker<T: Component>(
     world: &World,
-    components: &mut ComponentFilterRegistry,
     entities: SmallVec<[Entity; 8]>,
 ) -> (FilteredEntities, MatchedEntities) {
-    if let Some(query) = components.0.get_mut(name) {
-        let filtered = query
-            .filter(world)
-            .into_iter()
-            .filter(|e| entities.contains(e))
-            .collect::<SmallVec<_>>();
+    let filtered = entities
+        .iter()
+        .copied()
+        .filter(|&e| world.get::<T>(e).is_some())
+        .collect::<SmallVec<_>>();
 
-        (
-            FilteredEntities(filtered.clone()),
-            MatchedEntities(filtered),
-        )
-    } else {
-        error!("Unregistered component selector {}", name);
-        Default::default()
-    }
+    (FilteredEntities(filtered), MatchedEntities(entities))
 }
 
-/// Filters entities which have a [`Node`] component.
-/// This is to mimic the "*" selector on CSS.
-fn get_entities_with_any_component(
-    query: &Query<Entity, With<Node>>,
+/// Utility function to filter any entities matching a [`PseudoClassElement::FocusWithin`] variant.
+/// This function looks for [`Focused`] component on the entity itself or on any of it's descendants.
+/// Returns a list with entities which matches the pseudo-class and a list of entities which where matched.
+fn get_entities_with_pseudo_class_focus_within(
+    world: &World,
     entities: SmallVec<[Entity; 8]>,
 ) -> (FilteredEntities, MatchedEntities) {
-    let filtered = query
+    fn is_focus_within(world: &World, entity: Entity) -> bool {
+        world.get::<Focused>(entity).is_some()
+            || world
+                .get::<Children>(entity)
+                .is_some_and(|children| children.iter().any(|&e| is_focus_within(world, e)))
+    }
+
+    let filtered = entities
         .iter()
-        .filter(|e| entities.contains(e))
+        .copied()
+        .filter(|&e| is_focus_within(world, e))
         .collect::<SmallVec<_>>();
 
-    (
-        FilteredEntities(filtered.clone()),
-        MatchedEntities(filtered),
-    )
+    (FilteredEntities(filtered), MatchedEntities(entities))
 }
 
-/// Traverse the children hierarchy three and returns all entities.
-fn get_children_recursively(
-    children: &Children,
-    q_childs: &Query<&Children, With<Node>>,
-) -> SmallVec<[Entity; 8]> {
-    children
-        .iter()
-        .flat_map(|&e| {
+/// Utility function to filter any entities matching a [`PseudoClassElement::Custom`] variant.
+/// This function uses the predicate registered on [`PseudoClassRegistry`] with the same name.
+/// Unregistered pseudo-classes doesn't match any entity.
+/// Returns a list with entities which matches the predicate and a list of entities which where matched.
+fn get_entities_with_pseudo_class_custom(
+    world: &World,
+    entities: SmallVec<[Entity; 8]>,
+    name: &str,
+) -> (FilteredEntities, MatchedEntities) {
+    let Some(registry) = world.get_resource::<PseudoClassRegistry>() else {
+        return (FilteredEntities::default(), MatchedEntities(entities));
+    };
+    let Some(filter) = registry.get(name) else {
+        let mut unregistered = registry.unregistered.lock().unwrap();
+        if !unregistered.contains(name) {
+            error!("Unregistered pseudo-class {}", name);
+            unregistered.insert(name.to_string());
+        }
+        return (FilteredEntities::default(), MatchedEntities(entities));
+    };
+
+    let this_run = world.read_change_tick();
+    let last_run = world.last_change_tick();
+    let filtered = entities
+        .iter()
+        .copied()
+        .filter(|&e| {
+            world
+                .get_entity(e)
+                .is_some_and(|e| filter.matches(e, last_run, this_run))
+        })
+        .collect::<SmallVec<_>>();
+
+    (FilteredEntities(filtered), MatchedEntities(entities))
+}
+
+/// Utility function to filter any entities matching an [`AttributeElement`].
+/// Attribute values are read from [`CssAttributes`] component or from reflected component fields.
+/// Returns a list with entities which matches the attribute and a list of entities which where matched.
+fn get_entities_with_attribute(
+    world: &World,
+    attribute: &AttributeElement,
+    entities: SmallVec<[Entity; 8]>,
+) -> (FilteredEntities, MatchedEntities) {
+    let filtered = entities
+        .iter()
+        .copied()
+        .filter(|&e| {
+            let Some(entity) = world.get_entity(e) else {
+                return false;
+            };
+
+            match attribute.reflect_path() {
+                Some((type_name, path)) => attribute
+                    .matches(get_reflected_attribute(world, entity, type_name, path).as_deref()),
+                None => attribute.matches(
+                    entity
+                        .get::<CssAttributes>()
+                        .and_then(|attributes| attributes.get(&attribute.name)),
+                ),
+            }
+        })
+        .collect::<SmallVec<_>>();
+
+    (FilteredEntities(filtered), MatchedEntities(entities))
+}
+
+/// Reads a field value of a reflected component, which must be registered on [`AppTypeRegistry`].
+fn get_reflected_attribute(
+    world: &World,
+    entity: EntityRef,
+    type_name: &str,
+    path: &str,
+) -> Option<String> {
+    let registry = world.get_resource::<AppTypeRegistry>()?.read();
+    let Some(registration) = registry.get_with_short_type_path(type_name) else {
+        error!("Unregistered or ambiguous component type {}", type_name);
+        return None;
+    };
+    let component = registration.data::<ReflectComponent>()?.reflect(entity)?;
+    let value = component.reflect_path(path).ok()?;
+
+    macro_rules! downcast_to_string {
+        ($($ty:ty),+) => {
+            $(
+                if let Some(value) = value.downcast_ref::<$ty>() {
+                    return Some(value.to_string());
+                }
+            )+
+        };
+    }
+
+    downcast_to_string!(
+        f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, bool, String, Name
+    );
+
+    // Unit enums, like `State::Idle`, are matched by their variant name.
+    match value.reflect_ref() {
+        ReflectRef::Enum(value) => Some(value.variant_name().to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the [`ComponentId`] of a reflected component, which must be registered on [`AppTypeRegistry`].
+fn get_reflected_component_id(world: &World, type_name: &str) -> Option<ComponentId> {
+    let registry = world.get_resource::<AppTypeRegistry>()?.read();
+    let type_id = registry.get_with_short_type_path(type_name)?.type_id();
+    world.components().get_id(type_id)
+}
+
+/// Filters entities which have the components specified on selector, like "a" or "button".
+///
+/// The component must be registered on [`ComponentFilterRegistry`]
+fn get_entities_with_component(
+    name: &str,
+    world: &World,
+    components: &mut ComponentFilterRegistry,
+    entities: SmallVec<[Entity; 8]>,
+) -> (FilteredEntities, MatchedEntities) {
+    if let Some(query) = components.0.get_mut(name) {
+        let filtered = query
+            .filter(world)
+            .into_iter()
+            .filter(|e| entities.contains(e))
+            .collect::<SmallVec<_>>();
+
+        (
+            FilteredEntities(filtered.clone()),
+            MatchedEntities(filtered),
+        )
+    } else {
+        error!("Unregistered component selector {}", name);
+        Default::default()
+    }
+}
+
+/// Filters entities which have a [`Node`] component.
+/// This is to mimic the "*" selector on CSS.
+fn get_entities_with_any_component(
+    query: &Query<Entity, With<Node>>,
+    entities: SmallVec<[Entity; 8]>,
+) -> (FilteredEntities, MatchedEntities) {
+    let filtered = query
+        .iter()
+        .filter(|e| entities.contains(e))
+        .collect::<SmallVec<_>>();
+
+    (
+        FilteredEntities(filtered.clone()),
+        MatchedEntities(filtered),
+    )
+}
+
+/// Traverse the children hierarchy three and returns all entities.
+fn get_children_recursively(
+    children: &Children,
+    q_childs: &Query<&Children, With<Node>>,
+) -> SmallVec<[Entity; 8]> {
+    children
+        .iter()
+        .flat_map(|&e| {
             std::iter::once(e).chain(
                 q_childs
                     .get(e)
@@ -392,6 +1147,32 @@ pub(crate) fn reload_style_sheets(
     }
 }
 
+/// Reapply all style sheets which uses `env()` function whenever [`EcssVariables`] changes.
+pub(crate) fn watch_variables(
+    variables: Res<EcssVariables>,
+    assets: Res<Assets<StyleSheetAsset>>,
+    mut q_sheets: Query<&mut StyleSheet>,
+) {
+    if !variables.is_changed() || variables.is_added() {
+        return;
+    }
+
+    let uses_env = |id: AssetId<StyleSheetAsset>| {
+        assets.get(id).is_some_and(|sheet| {
+            sheet
+                .iter()
+                .any(|rule| rule.properties.values().any(PropertyValues::has_env))
+        })
+    };
+
+    for mut sheet in q_sheets.iter_mut() {
+        if sheet.handles().iter().any(|h| uses_env(h.id())) {
+            debug!("Refreshing sheet {:?} due to changed variables", sheet);
+            sheet.refresh();
+        }
+    }
+}
+
 /// Clear selected entities, but keep tracked ones.
 pub(crate) fn clear_state(mut sheet_rule: ResMut<StyleSheetState>) {
     if sheet_rule.has_any_selected_entities() {
@@ -439,7 +1220,7 @@ fn check_for_changed_assets(
     world: &World,
 ) -> Vec<AssetId<StyleSheetAsset>> {
     let mut changed_assets = vec![];
-    for (asset_id, tracked_entities, _) in state.iter() {
+    for (asset_id, tracked_entities) in state.tracked() {
         for (element, entities) in tracked_entities.iter() {
             if entities.is_empty() {
                 continue;
@@ -450,7 +1231,10 @@ fn check_for_changed_assets(
                 SelectorElement::Component(c) => any_component_changed_by_name(world, entities, c),
                 SelectorElement::Class(_) => any_component::<Class>(world, entities),
                 SelectorElement::PseudoClass(pseudo_class) => {
-                    any_component_changed_by_pseudo_class(world, entities, *pseudo_class)
+                    any_component_changed_by_pseudo_class(world, entities, pseudo_class)
+                }
+                SelectorElement::Attribute(attribute) => {
+                    any_attribute_changed(world, entities, attribute)
                 }
                 SelectorElement::Any => any_component::<Node>(world, entities),
                 _ => unreachable!(),
@@ -511,12 +1295,1187 @@ fn any_component_changed_by_name(
 fn any_component_changed_by_pseudo_class(
     world: &World,
     entities: &SmallVec<[Entity; 8]>,
-    pseudo_class: PseudoClassElement,
+    pseudo_class: &PseudoClassElement,
 ) -> bool {
     match pseudo_class {
+        // Disabled entities are never hovered nor pressed.
         PseudoClassElement::Hover | PseudoClassElement::Active => {
             any_component::<Interaction>(world, entities)
+                || any_component_added_or_removed::<Disabled>(world, entities)
+        }
+        PseudoClassElement::Disabled => any_component_added_or_removed::<Disabled>(world, entities),
+        PseudoClassElement::Checked => any_component_added_or_removed::<Checked>(world, entities),
+        PseudoClassElement::Selected => any_component_added_or_removed::<Selected>(world, entities),
+        PseudoClassElement::Focus => any_component_added_or_removed::<Focused>(world, entities),
+        PseudoClassElement::FocusVisible => {
+            any_component_added_or_removed::<FocusVisible>(world, entities)
+        }
+        PseudoClassElement::FocusWithin => {
+            any_component::<Focused>(world, entities)
+                || any_descendant_unfocused(world, entities)
+                || any_descendant_focused(world, entities)
+        }
+        PseudoClassElement::FirstChild
+        | PseudoClassElement::LastChild
+        | PseudoClassElement::NthChild(_, _)
+        | PseudoClassElement::OnlyChild => any_sibling_order_changed(world, entities),
+        PseudoClassElement::Empty => {
+            any_component::<Children>(world, entities)
+                || any_component_removed::<Children>(world, entities)
+        }
+        PseudoClassElement::Root => {
+            any_component::<Parent>(world, entities)
+                || any_component_removed::<Parent>(world, entities)
+        }
+        // Relative entities may appear, disappear or have it's classes changed.
+        PseudoClassElement::Has(_) => {
+            any_component::<Children>(world, entities)
+                || any_component_removed::<Children>(world, entities)
+                || any_component::<Class>(world, entities)
+        }
+        // Nested selectors are tracked by it's own elements.
+        PseudoClassElement::Is(_) | PseudoClassElement::Where(_) | PseudoClassElement::Not(_) => {
+            false
+        }
+        PseudoClassElement::Custom(name) => {
+            any_component_changed_by_custom_pseudo_class(world, entities, name)
+        }
+    }
+}
+
+/// Checks if any entity on the given list has the component watched by a custom pseudo-class changed or removed.
+fn any_component_changed_by_custom_pseudo_class(
+    world: &World,
+    entities: &SmallVec<[Entity; 8]>,
+    name: &str,
+) -> bool {
+    let this_run = world.read_change_tick();
+    let last_run = world.last_change_tick();
+
+    let Some(filter) = world
+        .get_resource::<PseudoClassRegistry>()
+        .and_then(|registry| registry.get(name))
+    else {
+        return false;
+    };
+
+    entities.iter().any(|e| {
+        world
+            .get_entity(*e)
+            .and_then(|e| filter.get_change_ticks(e))
+            .is_some_and(|ticks| ticks.is_changed(last_run, this_run))
+    }) || filter.any_removed(world, entities)
+}
+
+/// Checks if any entity on the given list has it's attribute changed, which is either the
+/// [`CssAttributes`] component or the reflected component.
+fn any_attribute_changed(
+    world: &World,
+    entities: &SmallVec<[Entity; 8]>,
+    attribute: &AttributeElement,
+) -> bool {
+    match attribute.reflect_path() {
+        Some((type_name, _)) => {
+            let Some(component_id) = get_reflected_component_id(world, type_name) else {
+                return false;
+            };
+
+            let this_run = world.read_change_tick();
+            let last_run = world.last_change_tick();
+            entities.iter().any(|e| {
+                world
+                    .get_entity(*e)
+                    .and_then(|e| e.get_change_ticks_by_id(component_id))
+                    .is_some_and(|ticks| ticks.is_changed(last_run, this_run))
+            }) || world
+                .removed_with_id(component_id)
+                .any(|e| entities.contains(&e))
+        }
+        None => {
+            any_component::<CssAttributes>(world, entities)
+                || any_component_removed::<CssAttributes>(world, entities)
+        }
+    }
+}
+
+/// Checks if any entity on the given list has it's marker [`Component`] added or removed.
+fn any_component_added_or_removed<T: Component>(
+    world: &World,
+    entities: &SmallVec<[Entity; 8]>,
+) -> bool {
+    any_component::<T>(world, entities) || any_component_removed::<T>(world, entities)
+}
+
+/// Checks if any descendant of the entities on the given list has gained the [`Focused`] component.
+fn any_descendant_focused(world: &World, entities: &SmallVec<[Entity; 8]>) -> bool {
+    let descendants = entities
+        .iter()
+        .filter_map(|&e| world.get::<Children>(e))
+        .flat_map(|children| get_descendants(world, children))
+        .collect();
+
+    any_component::<Focused>(world, &descendants)
+}
+
+/// Checks if any entity on the given list, or any of it's descendants, has lost the [`Focused`] component.
+/// Entities which lost focus because they were despawned are considered to be descendants, since their hierarchy is gone.
+fn any_descendant_unfocused(world: &World, entities: &SmallVec<[Entity; 8]>) -> bool {
+    world.removed::<Focused>().any(|unfocused| {
+        if world.get_entity(unfocused).is_none() {
+            return true;
+        }
+
+        let mut current = Some(unfocused);
+        while let Some(entity) = current {
+            if entities.contains(&entity) {
+                return true;
+            }
+            current = world.get::<Parent>(entity).map(Parent::get);
+        }
+        false
+    })
+}
+
+/// Returns all descendants of the given [`Children`] list, using only the [`World`].
+fn get_descendants(world: &World, children: &Children) -> SmallVec<[Entity; 8]> {
+    children
+        .iter()
+        .flat_map(|&e| {
+            std::iter::once(e).chain(
+                world
+                    .get::<Children>(e)
+                    .map_or(SmallVec::new(), |gc| get_descendants(world, gc)),
+            )
+        })
+        .collect()
+}
+
+/// Checks if any entity on the given list has it's [`Component`] removed.
+fn any_component_removed<T: Component>(world: &World, entities: &SmallVec<[Entity; 8]>) -> bool {
+    world.removed::<T>().any(|e| entities.contains(&e))
+}
+
+/// Checks if any entity on the given list has changed it's position among it's siblings.
+/// This happens when the entity changes it's [`Parent`] or when it's [`Parent`] [`Children`] list is changed.
+fn any_sibling_order_changed(world: &World, entities: &SmallVec<[Entity; 8]>) -> bool {
+    if any_component::<Parent>(world, entities) || any_component_removed::<Parent>(world, entities)
+    {
+        return true;
+    }
+
+    let parents = entities
+        .iter()
+        .filter_map(|&e| world.get::<Parent>(e).map(Parent::get))
+        .collect();
+
+    any_component::<Children>(world, &parents)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{App, BuildWorldChildren, Name, Reflect};
+
+    use crate::RegisterPseudoClass;
+
+    use super::*;
+
+    fn setup() -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<StyleSheetAsset>>();
+        world.init_resource::<ComponentFilterRegistry>();
+        world
+    }
+
+    fn spawn(world: &mut World, parent: Option<Entity>, name: &'static str) -> Entity {
+        let entity = world.spawn((Node::default(), Name::new(name))).id();
+        if let Some(parent) = parent {
+            world.entity_mut(parent).add_child(entity);
+        }
+        entity
+    }
+
+    /// Selects all entities matched by the given selector, using `root` as the style sheet root.
+    fn select(world: &mut World, root: Entity, selector: &str) -> SmallVec<[Entity; 8]> {
+        let handle = world
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse("", &format!("{selector} {{}}")));
+        world.entity_mut(root).insert(StyleSheet::new(handle));
+
+        let mut params = PrepareParams::new(world);
+        world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            let state = prepare_state(world, params.get(world), &mut registry);
+            let (_, _, entities) = state.selected().first().expect("Should have a single rule");
+            // Selection order doesn't matter, so use spawn order to ease assertions.
+            let mut entities = entities.clone();
+            entities.sort();
+            entities
+        })
+    }
+
+    #[test]
+    fn select_descendant_and_direct_child() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let button = spawn(&mut world, Some(menu), "button");
+        let panel = spawn(&mut world, Some(menu), "panel");
+        let nested = spawn(&mut world, Some(panel), "button");
+
+        let selected = select(&mut world, menu, "#menu #button");
+        assert_eq!(selected.as_slice(), &[button, nested]);
+
+        let selected = select(&mut world, menu, "#menu > #button");
+        assert_eq!(selected.as_slice(), &[button]);
+
+        let selected = select(&mut world, menu, "#menu > #panel > #button");
+        assert_eq!(selected.as_slice(), &[nested]);
+
+        let selected = select(&mut world, menu, "#menu > #panel #button");
+        assert_eq!(selected.as_slice(), &[nested]);
+    }
+
+    #[test]
+    fn select_siblings() {
+        let mut world = setup();
+        let tabs = spawn(&mut world, None, "tabs");
+        spawn(&mut world, Some(tabs), "tab");
+        let divider = spawn(&mut world, Some(tabs), "divider");
+        let second = spawn(&mut world, Some(tabs), "tab");
+        let third = spawn(&mut world, Some(tabs), "tab");
+
+        let selected = select(&mut world, tabs, "#divider + #tab");
+        assert_eq!(selected.as_slice(), &[second]);
+
+        let selected = select(&mut world, tabs, "#divider ~ #tab");
+        assert_eq!(selected.as_slice(), &[second, third]);
+
+        let selected = select(&mut world, tabs, "#tab + #tab");
+        assert_eq!(selected.as_slice(), &[third]);
+
+        let selected = select(&mut world, tabs, "#tab ~ #tab");
+        assert_eq!(selected.as_slice(), &[second, third]);
+
+        let selected = select(&mut world, tabs, "#tab + #divider");
+        assert_eq!(selected.as_slice(), &[divider]);
+
+        let selected = select(&mut world, tabs, "#tabs ~ #tab");
+        assert!(selected.is_empty(), "Root siblings are out of scope");
+
+        let selected = select(&mut world, tabs, "#tabs > #tab ~ #tab");
+        assert_eq!(selected.as_slice(), &[second, third]);
+    }
+
+    #[test]
+    fn select_structural_pseudo_classes() {
+        let mut world = setup();
+        let table = spawn(&mut world, None, "table");
+        let rows = (0..5)
+            .map(|_| spawn(&mut world, Some(table), "row"))
+            .collect::<Vec<_>>();
+        let cell = spawn(&mut world, Some(rows[0]), "cell");
+
+        let selected = select(&mut world, table, "#row:first-child");
+        assert_eq!(selected.as_slice(), &[rows[0]]);
+
+        let selected = select(&mut world, table, "#row:last-child");
+        assert_eq!(selected.as_slice(), &[rows[4]]);
+
+        let selected = select(&mut world, table, "#row:nth-child(odd)");
+        assert_eq!(selected.as_slice(), &[rows[0], rows[2], rows[4]]);
+
+        let selected = select(&mut world, table, "#row:nth-child(-n+2)");
+        assert_eq!(selected.as_slice(), &[rows[0], rows[1]]);
+
+        let selected = select(&mut world, table, "#row:nth-child(3)");
+        assert_eq!(selected.as_slice(), &[rows[2]]);
+
+        let selected = select(&mut world, table, ":only-child");
+        assert_eq!(selected.as_slice(), &[table, cell]);
+
+        let selected = select(&mut world, table, "#row:empty");
+        assert_eq!(selected.as_slice(), &rows[1..]);
+
+        let selected = select(&mut world, table, ":root");
+        assert_eq!(selected.as_slice(), &[table]);
+    }
+
+    #[test]
+    fn select_logical_pseudo_classes() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let play = spawn(&mut world, Some(menu), "play");
+        let quit = spawn(&mut world, Some(menu), "quit");
+        let options = spawn(&mut world, Some(menu), "options");
+        world.entity_mut(quit).insert(Class::new("disabled"));
+
+        let selected = select(&mut world, menu, "#menu > :not(.disabled)");
+        assert_eq!(selected.as_slice(), &[play, options]);
+
+        let selected = select(&mut world, menu, "#menu > :not(.disabled, #play)");
+        assert_eq!(selected.as_slice(), &[options]);
+
+        let selected = select(&mut world, menu, ":is(#play, .disabled)");
+        assert_eq!(selected.as_slice(), &[play, quit]);
+
+        let selected = select(&mut world, menu, ":where(#menu) > :is(#play + *)");
+        assert_eq!(selected.as_slice(), &[quit]);
+    }
+
+    #[test]
+    fn select_has_pseudo_class() {
+        let mut world = setup();
+        let inventory = spawn(&mut world, None, "inventory");
+        let slots = (0..3)
+            .map(|_| spawn(&mut world, Some(inventory), "slot"))
+            .collect::<Vec<_>>();
+        let rare = spawn(&mut world, Some(slots[0]), "item");
+        world.entity_mut(rare).insert(Class::new("item-rare"));
+        let common = spawn(&mut world, Some(slots[1]), "item");
+        let icon = spawn(&mut world, Some(common), "icon");
+        world.entity_mut(icon).insert(Class::new("item-rare"));
+
+        let selected = select(&mut world, inventory, "#slot:has(.item-rare)");
+        assert_eq!(selected.as_slice(), &slots[..2]);
+
+        let selected = select(&mut world, inventory, "#slot:has(> .item-rare)");
+        assert_eq!(selected.as_slice(), &[slots[0]]);
+
+        let selected = select(&mut world, inventory, "#slot:has(+ #slot > #item)");
+        assert_eq!(selected.as_slice(), &[slots[0]]);
+
+        let selected = select(&mut world, inventory, "#slot:has(~ #slot:empty)");
+        assert_eq!(selected.as_slice(), &slots[..2]);
+
+        let selected = select(&mut world, inventory, "#slot:not(:has(#item))");
+        assert_eq!(selected.as_slice(), &[slots[2]]);
+    }
+
+    #[test]
+    fn select_attributes() {
+        #[derive(Component, Reflect, Default)]
+        #[reflect(Component)]
+        struct Health {
+            current: f32,
         }
-        PseudoClassElement::Unsupported => false,
+
+        let mut world = setup();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Health>();
+
+        let hud = spawn(&mut world, None, "hud");
+        let epic = spawn(&mut world, Some(hud), "item");
+        let common = spawn(&mut world, Some(hud), "item");
+        world.entity_mut(epic).insert((
+            CssAttributes::default()
+                .with("rarity", "epic")
+                .with("tags", "sword two-handed"),
+            Health { current: 15.0 },
+        ));
+        world.entity_mut(common).insert((
+            CssAttributes::default().with("rarity", "common"),
+            Health { current: 20.0 },
+        ));
+
+        let selected = select(&mut world, hud, "[rarity]");
+        assert_eq!(selected.as_slice(), &[epic, common]);
+
+        let selected = select(&mut world, hud, "[rarity=epic]");
+        assert_eq!(selected.as_slice(), &[epic]);
+
+        let selected = select(&mut world, hud, "[rarity^=com]");
+        assert_eq!(selected.as_slice(), &[common]);
+
+        let selected = select(&mut world, hud, "[rarity*=pi]");
+        assert_eq!(selected.as_slice(), &[epic]);
+
+        let selected = select(&mut world, hud, "[tags~=sword]");
+        assert_eq!(selected.as_slice(), &[epic]);
+
+        let selected = select(&mut world, hud, "[Health.current<20]");
+        assert_eq!(selected.as_slice(), &[epic]);
+
+        let selected = select(&mut world, hud, "[Health.current>=20]");
+        assert_eq!(selected.as_slice(), &[common]);
+
+        let selected = select(&mut world, hud, "[Health.current=20]");
+        assert_eq!(selected.as_slice(), &[common]);
+
+        let selected = select(&mut world, hud, "[Health.missing]");
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn select_custom_pseudo_classes() {
+        #[derive(Component)]
+        struct Health(f32);
+
+        let mut app = App::new();
+        app.register_pseudo_class::<Health>("low-health", |entity, _| {
+            entity.get::<Health>().is_some_and(|health| health.0 < 20.0)
+        })
+        .register_pseudo_class::<Health>("selected", |_, _| true);
+        assert!(
+            !app.world()
+                .resource::<PseudoClassRegistry>()
+                .contains_key("selected"),
+            "Built-in pseudo-classes can't be registered"
+        );
+
+        let world = app.world_mut();
+        world.init_resource::<Assets<StyleSheetAsset>>();
+        world.init_resource::<ComponentFilterRegistry>();
+
+        let hud = spawn(world, None, "hud");
+        let low = spawn(world, Some(hud), "portrait");
+        let high = spawn(world, Some(hud), "portrait");
+        let _none = spawn(world, Some(hud), "portrait");
+        world.entity_mut(low).insert(Health(10.0));
+        world.entity_mut(high).insert(Health(80.0));
+
+        let selected = select(world, hud, "#portrait:low-health");
+        assert_eq!(selected.as_slice(), &[low]);
+
+        let selected = select(world, hud, "#portrait:not(:low-health)");
+        assert_eq!(selected.len(), 2);
+        assert!(!selected.contains(&low));
+
+        let selected = select(world, hud, "#portrait:dragging");
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn select_widget_state_pseudo_classes() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let play = spawn(&mut world, Some(menu), "button");
+        let quit = spawn(&mut world, Some(menu), "button");
+        let sound = spawn(&mut world, Some(menu), "toggle");
+        world
+            .entity_mut(play)
+            .insert((Interaction::Hovered, Selected, Focused));
+        world
+            .entity_mut(quit)
+            .insert((Interaction::Hovered, Disabled));
+        world.entity_mut(sound).insert(Checked);
+
+        let selected = select(&mut world, menu, "#button:hover");
+        assert_eq!(selected.as_slice(), &[play]);
+
+        let selected = select(&mut world, menu, "#button:disabled");
+        assert_eq!(selected.as_slice(), &[quit]);
+
+        let selected = select(&mut world, menu, ":checked");
+        assert_eq!(selected.as_slice(), &[sound]);
+
+        let selected = select(&mut world, menu, ":selected:focus");
+        assert_eq!(selected.as_slice(), &[play]);
+
+        let selected = select(&mut world, menu, "#button:not(:disabled)");
+        assert_eq!(selected.as_slice(), &[play]);
+    }
+
+    #[test]
+    fn select_focus_pseudo_classes() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let panel = spawn(&mut world, Some(menu), "panel");
+        let play = spawn(&mut world, Some(panel), "button");
+        let quit = spawn(&mut world, Some(menu), "button");
+        world.entity_mut(play).insert((Focused, FocusVisible));
+
+        let selected = select(&mut world, menu, ":focus-within");
+        assert_eq!(selected.as_slice(), &[menu, panel, play]);
+
+        let selected = select(&mut world, menu, "#button:focus-visible");
+        assert_eq!(selected.as_slice(), &[play]);
+
+        world.entity_mut(play).remove::<FocusVisible>();
+        let selected = select(&mut world, menu, "#button:focus-visible");
+        assert!(selected.is_empty());
+
+        world.entity_mut(play).remove::<Focused>();
+        world.entity_mut(quit).insert(Focused);
+        let selected = select(&mut world, menu, ":focus-within");
+        assert_eq!(selected.as_slice(), &[menu, quit]);
+    }
+
+    #[test]
+    fn detect_focus_within_changes() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let play = spawn(&mut world, Some(menu), "button");
+        let hud = spawn(&mut world, None, "hud");
+        let health = spawn(&mut world, Some(hud), "health");
+        world.entity_mut(play).insert(Focused);
+        world.clear_trackers();
+
+        world.entity_mut(play).remove::<Focused>();
+        let changed = |world: &World, entities: &[Entity]| {
+            any_component_changed_by_pseudo_class(
+                world,
+                &entities.iter().copied().collect(),
+                &PseudoClassElement::FocusWithin,
+            )
+        };
+        assert!(changed(&world, &[menu]));
+        assert!(changed(&world, &[play]));
+        assert!(
+            !changed(&world, &[hud, health]),
+            "Only ancestors of the unfocused entity should be refreshed"
+        );
+
+        world.clear_trackers();
+        world.entity_mut(play).insert(Focused);
+        assert!(changed(&world, &[menu]));
+        assert!(!changed(&world, &[hud, health]));
+    }
+
+    #[test]
+    fn prepare_inherited_custom_properties() {
+        use crate::PropertyToken::*;
+
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let panel = spawn(&mut world, Some(menu), "panel");
+        let button = spawn(&mut world, Some(panel), "button");
+
+        let handle = world
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #menu { --accent: red; --gap: 4px; }
+                #panel { --accent: var(--highlight, blue); --size: var(--gap); }
+                #button { --cycle: var(--cycle); width: var(--size); }
+                "#,
+            ));
+        world
+            .entity_mut(menu)
+            .insert(StyleSheet::new(handle.clone()));
+
+        let mut params = PrepareParams::new(&mut world);
+        let state = world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            prepare_state(world, params.get(world), &mut registry)
+        });
+        let custom_properties = prepare_custom_properties(&world, &state, None);
+
+        let get = |entity: Entity, name: &str| {
+            custom_properties
+                .get(&entity)
+                .and_then(|properties| properties.get(name))
+                .map(|values| values.to_vec())
+        };
+
+        assert_eq!(
+            get(menu, "--accent"),
+            Some(vec![Identifier("red".to_string())])
+        );
+        assert_eq!(
+            get(panel, "--accent"),
+            Some(vec![Identifier("blue".to_string())])
+        );
+        assert_eq!(get(panel, "--size"), Some(vec![Dimension(4.0)]));
+        assert_eq!(
+            get(button, "--accent"),
+            Some(vec![Identifier("blue".to_string())])
+        );
+        assert_eq!(get(button, "--gap"), Some(vec![Dimension(4.0)]));
+        assert_eq!(get(button, "--cycle"), None);
+
+        let assets = world.resource::<Assets<StyleSheetAsset>>();
+        let sheet = assets.get(&handle).unwrap();
+        let rule = sheet.iter().last().unwrap();
+        let width = rule.properties["width"]
+            .substitute_vars(&custom_properties[&button])
+            .unwrap();
+        assert_eq!(width.to_vec(), vec![Dimension(4.0)]);
+        assert!(rule.properties["width"]
+            .substitute_vars(&CustomProperties::default())
+            .is_none());
+    }
+
+    #[test]
+    fn prepare_custom_properties_again() {
+        use crate::PropertyToken::*;
+
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let panel = spawn(&mut world, Some(menu), "panel");
+        let label = spawn(&mut world, Some(panel), "label");
+        world.entity_mut(panel).insert(Class::new("dark"));
+
+        let handle = world
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #panel.dark { --accent: blue; }
+                #label { width: var(--accent, 1px); }
+                "#,
+            ));
+        world.entity_mut(menu).insert(StyleSheet::new(handle));
+
+        let mut params = PrepareParams::new(&mut world);
+        let mut prepare = |world: &mut World, previous: Option<&CustomPropertiesState>| {
+            let state =
+                world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+                    prepare_state(world, params.get(world), &mut registry)
+                });
+            prepare_custom_properties(world, &state, previous)
+        };
+
+        let custom_properties = prepare(&mut world, None);
+        assert_eq!(
+            custom_properties[&label]["--accent"].to_vec(),
+            vec![Identifier("blue".to_string())]
+        );
+
+        world.entity_mut(panel).remove::<Class>();
+        world.get_mut::<StyleSheet>(menu).unwrap().refresh();
+        let custom_properties = prepare(&mut world, Some(&custom_properties));
+        assert!(
+            !custom_properties.contains_key(&panel),
+            "Custom properties of rules which doesn't match anymore should be removed"
+        );
+        assert!(!custom_properties.contains_key(&label));
+    }
+
+    #[test]
+    fn prepare_inherited_current_color() {
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let panel = spawn(&mut world, Some(menu), "panel");
+        let button = spawn(&mut world, Some(panel), "button");
+
+        let handle = world
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #menu { color: red; }
+                #panel { color: color-mix(in srgb, currentColor, blue); }
+                #button { border-color: currentColor; }
+                "#,
+            ));
+        world.entity_mut(menu).insert(StyleSheet::new(handle));
+
+        let mut params = PrepareParams::new(&mut world);
+        let state = world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            prepare_state(world, params.get(world), &mut registry)
+        });
+        let custom_properties = prepare_custom_properties(&world, &state, None);
+
+        let color = |entity: Entity| custom_properties[&entity][CURRENT_COLOR].color();
+        use bevy::prelude::Color;
+
+        let purple = Color::srgb(0.5, 0.0, 0.5);
+
+        assert_eq!(color(menu), Some(Color::srgb(1.0, 0.0, 0.0)));
+        assert_eq!(color(panel), Some(purple));
+        assert_eq!(color(button), Some(purple));
+
+        let border = crate::parser::parse_property_values("currentColor")
+            .unwrap()
+            .substitute_current_color(&custom_properties[&button][CURRENT_COLOR]);
+        assert_eq!(border.color(), Some(purple));
+    }
+
+    #[test]
+    fn prepare_cascade_order() {
+        use crate::PropertyToken::*;
+
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let button = spawn(&mut world, Some(menu), "button");
+        world
+            .entity_mut(button)
+            .insert(Class::new("a b c d e f g h i j k"));
+
+        let mut assets = world.resource_mut::<Assets<StyleSheetAsset>>();
+        let base = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            #button { --id: id; }
+            .a.b.c.d.e.f.g.h.i.j.k { --id: classes; --order: first; --sheet: base; }
+            .a { --duplicated: first; }
+            .b { --order: second; }
+            .a { --other: value; }
+            .a.b.c.d.e.f.g.h.i.j.k { --order: last; }
+            "#,
+        ));
+        let theme = assets.add(StyleSheetAsset::parse(
+            "",
+            ".a.b.c.d.e.f.g.h.i.j.k { --sheet: theme; } .b { --duplicated: theme; }",
+        ));
+        world
+            .entity_mut(menu)
+            .insert(StyleSheet::from_handles(vec![base, theme]));
+
+        let mut params = PrepareParams::new(&mut world);
+        let state = world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            prepare_state(world, params.get(world), &mut registry)
+        });
+        let custom_properties = prepare_custom_properties(&world, &state, None);
+
+        let get = |name: &str| custom_properties[&button][name].to_vec();
+
+        assert_eq!(
+            get("--id"),
+            vec![Identifier("id".to_string())],
+            "An id should win over any number of classes"
+        );
+        assert_eq!(
+            get("--order"),
+            vec![Identifier("last".to_string())],
+            "Later rules with same specificity should win"
+        );
+        assert_eq!(get("--other"), vec![Identifier("value".to_string())]);
+        assert_eq!(
+            get("--sheet"),
+            vec![Identifier("theme".to_string())],
+            "Later sheets should win"
+        );
+        assert_eq!(get("--duplicated"), vec![Identifier("theme".to_string())]);
+    }
+
+    #[test]
+    fn prepare_important_declarations() {
+        use crate::PropertyToken::*;
+
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let button = spawn(&mut world, Some(menu), "button");
+        world.entity_mut(button).insert(Class::new("button"));
+
+        let mut assets = world.resource_mut::<Assets<StyleSheetAsset>>();
+        let base = assets.add(StyleSheetAsset::parse(
+            "",
+            ".button { --accent: base !important; --size: base; }",
+        ));
+        let screen = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            #menu #button { --accent: screen; --size: screen; }
+            .button { --gap: important !important; }
+            .button { --gap: normal; }
+            "#,
+        ));
+        world
+            .entity_mut(menu)
+            .insert(StyleSheet::from_handles(vec![base, screen]));
+
+        let mut params = PrepareParams::new(&mut world);
+        let state = world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            prepare_state(world, params.get(world), &mut registry)
+        });
+        let custom_properties = prepare_custom_properties(&world, &state, None);
+
+        let get = |name: &str| custom_properties[&button][name].to_vec();
+
+        assert_eq!(
+            get("--accent"),
+            vec![Identifier("base".to_string())],
+            "Important declarations should win over more specific ones"
+        );
+        assert_eq!(get("--size"), vec![Identifier("screen".to_string())]);
+        assert_eq!(
+            get("--gap"),
+            vec![Identifier("important".to_string())],
+            "Important declarations should win over later ones"
+        );
+    }
+
+    #[test]
+    fn prepare_layer_order() {
+        use crate::PropertyToken::*;
+
+        let mut world = setup();
+        let menu = spawn(&mut world, None, "menu");
+        let button = spawn(&mut world, Some(menu), "button");
+        world.entity_mut(button).insert(Class::new("button"));
+
+        let mut assets = world.resource_mut::<Assets<StyleSheetAsset>>();
+        let engine = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            @layer engine, widgets, screens;
+            @layer engine {
+                #menu #button { --a: engine; --b: engine !important; }
+            }
+            "#,
+        ));
+        let widgets = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            @layer widgets {
+                .button { --c: widgets; }
+                @layer states { #button { --c: states; --d: states; } }
+            }
+            "#,
+        ));
+        let screens = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            .button { --d: unlayered; }
+            @layer screens {
+                .button { --a: screens; --b: screens !important; --d: screens; }
+            }
+            "#,
+        ));
+        world
+            .entity_mut(menu)
+            .insert(StyleSheet::from_handles(vec![engine, widgets, screens]));
+
+        let mut params = PrepareParams::new(&mut world);
+        let state = world.resource_scope(|world, mut registry: Mut<ComponentFilterRegistry>| {
+            prepare_state(world, params.get(world), &mut registry)
+        });
+        let custom_properties = prepare_custom_properties(&world, &state, None);
+
+        let get = |name: &str| custom_properties[&button][name].to_vec();
+
+        assert_eq!(
+            get("--a"),
+            vec![Identifier("screens".to_string())],
+            "Later layers should win regardless of specificity"
+        );
+        assert_eq!(
+            get("--b"),
+            vec![Identifier("engine".to_string())],
+            "Earlier layers should win on important declarations"
+        );
+        assert_eq!(
+            get("--c"),
+            vec![Identifier("widgets".to_string())],
+            "Rules directly inside a layer should win over it's child layers"
+        );
+        assert_eq!(
+            get("--d"),
+            vec![Identifier("unlayered".to_string())],
+            "Unlayered rules should win over any layer"
+        );
+    }
+
+    #[test]
+    fn apply_inherited_properties() {
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), crate::EcssPlugin));
+
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #dialog { color: red; background-color: blue; }
+                #panel { color: var(--accent); --accent: green; }
+                #title { color: white; }
+                "#,
+            ));
+
+        let text = || TextBundle::from_section("", TextStyle::default());
+        let mut label = Entity::PLACEHOLDER;
+        let mut title = Entity::PLACEHOLDER;
+        let mut nested = Entity::PLACEHOLDER;
+        let mut dialog_bg = Entity::PLACEHOLDER;
+        app.world_mut()
+            .spawn((
+                NodeBundle::default(),
+                Name::new("dialog"),
+                StyleSheet::new(handle),
+            ))
+            .with_children(|dialog| {
+                dialog_bg = dialog.parent_entity();
+                label = dialog.spawn(text()).id();
+                title = dialog.spawn((text(), Name::new("title"))).id();
+                dialog
+                    .spawn((NodeBundle::default(), Name::new("panel")))
+                    .with_children(|panel| {
+                        nested = panel.spawn(text()).id();
+                    });
+            });
+
+        app.update();
+
+        let world = app.world();
+        let color = |entity: Entity| world.get::<Text>(entity).unwrap().sections[0].style.color;
+        assert_eq!(color(label), Color::srgb(1.0, 0.0, 0.0));
+        assert_eq!(color(title), Color::srgb(1.0, 1.0, 1.0));
+        assert_eq!(
+            color(nested),
+            Color::srgb(0.0, 128.0 / 255.0, 0.0),
+            "Should inherit from the nearest ancestor, resolved on it"
+        );
+        assert_eq!(
+            world.get::<BackgroundColor>(label).unwrap().0,
+            BackgroundColor::default().0,
+            "Non inherited properties shouldn't propagate"
+        );
+        assert_eq!(
+            world.get::<BackgroundColor>(dialog_bg).unwrap().0,
+            Color::srgb(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn apply_inherited_properties_from_nested_sheets() {
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), crate::EcssPlugin));
+
+        let mut assets = app.world_mut().resource_mut::<Assets<StyleSheetAsset>>();
+        let dialog_sheet = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            #dialog { color: red; }
+            #dialog.dark { color: blue; }
+            #broken { color: not-a-color; }
+            "#,
+        ));
+        let card_sheet = assets.add(StyleSheetAsset::parse("", "#card { color: green; }"));
+
+        let text = || TextBundle::from_section("", TextStyle::default());
+        let mut dialog = Entity::PLACEHOLDER;
+        let mut label = Entity::PLACEHOLDER;
+        let mut broken = Entity::PLACEHOLDER;
+        let mut card_label = Entity::PLACEHOLDER;
+        app.world_mut()
+            .spawn((
+                NodeBundle::default(),
+                Name::new("dialog"),
+                StyleSheet::new(dialog_sheet),
+            ))
+            .with_children(|parent| {
+                dialog = parent.parent_entity();
+                label = parent.spawn(text()).id();
+                broken = parent
+                    .spawn((text(), Name::new("broken")))
+                    .with_children(|broken| {
+                        broken.spawn(text());
+                    })
+                    .id();
+                parent
+                    .spawn((
+                        NodeBundle::default(),
+                        Name::new("card"),
+                        StyleSheet::new(card_sheet),
+                    ))
+                    .with_children(|card| {
+                        card_label = card.spawn(text()).id();
+                    });
+            });
+
+        app.update();
+
+        let color = |app: &App, entity: Entity| {
+            app.world().get::<Text>(entity).unwrap().sections[0]
+                .style
+                .color
+        };
+        assert_eq!(color(&app, label), Color::srgb(1.0, 0.0, 0.0));
+        assert_eq!(
+            color(&app, card_label),
+            Color::srgb(0.0, 128.0 / 255.0, 0.0)
+        );
+        assert_eq!(
+            color(&app, broken),
+            Color::default(),
+            "Entities with invalid values shouldn't inherit"
+        );
+
+        app.world_mut()
+            .entity_mut(dialog)
+            .insert(Class::new("dark"));
+        app.world_mut()
+            .get_mut::<StyleSheet>(dialog)
+            .unwrap()
+            .refresh();
+        app.update();
+
+        assert_eq!(color(&app, label), Color::srgb(0.0, 0.0, 1.0));
+        assert_eq!(
+            color(&app, card_label),
+            Color::srgb(0.0, 128.0 / 255.0, 0.0),
+            "Values of nested sheets which weren't applied again should be kept"
+        );
+        assert_eq!(color(&app, broken), Color::default());
+    }
+
+    #[test]
+    fn resolve_font_relative_units_without_text() {
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), crate::EcssPlugin));
+
+        let mut assets = app.world_mut().resource_mut::<Assets<StyleSheetAsset>>();
+        let sheet = assets.add(StyleSheetAsset::parse(
+            "",
+            r#"
+            :root { font-size: 10px; }
+            #dialog { font-size: 20px; padding: 1em; }
+            #content { width: calc(2em + 1rem); }
+            "#,
+        ));
+
+        let mut dialog = Entity::PLACEHOLDER;
+        let mut content = Entity::PLACEHOLDER;
+        app.world_mut()
+            .spawn((NodeBundle::default(), StyleSheet::new(sheet)))
+            .with_children(|root| {
+                dialog = root
+                    .spawn((NodeBundle::default(), Name::new("dialog")))
+                    .with_children(|dialog| {
+                        content = dialog
+                            .spawn((NodeBundle::default(), Name::new("content")))
+                            .id();
+                    })
+                    .id();
+            });
+
+        app.update();
+
+        let style = app.world().get::<Style>(dialog).unwrap();
+        assert_eq!(style.padding.left, Val::Px(20.0));
+        assert_eq!(style.padding.top, Val::Px(20.0));
+        assert_eq!(
+            app.world().get::<Style>(content).unwrap().width,
+            Val::Px(50.0),
+            "`em` is inherited from the nearest node with a font size and `rem` from the root"
+        );
+    }
+
+    #[test]
+    fn apply_media_queries() {
+        use crate::{ColorScheme, EcssMedia};
+        use bevy::{prelude::*, window::PrimaryWindow};
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), crate::EcssPlugin));
+
+        let window = app
+            .world_mut()
+            .spawn((Window::default(), PrimaryWindow))
+            .id();
+        app.world_mut()
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set(1280.0, 720.0);
+
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<StyleSheetAsset>>()
+            .add(StyleSheetAsset::parse(
+                "",
+                r#"
+                #panel { background-color: red; }
+                @media (orientation: portrait) {
+                    #panel { background-color: green; }
+                }
+                @media (prefers-color-scheme: dark) and (min-width: 800px) {
+                    #panel { background-color: blue; }
+                }
+                "#,
+            ));
+        let panel = app
+            .world_mut()
+            .spawn((
+                NodeBundle::default(),
+                Name::new("panel"),
+                StyleSheet::new(handle),
+            ))
+            .id();
+
+        let background = |app: &mut App| {
+            app.update();
+            app.update();
+            app.world().get::<BackgroundColor>(panel).unwrap().0
+        };
+
+        assert_eq!(background(&mut app), Color::srgb(1.0, 0.0, 0.0));
+
+        app.world_mut()
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set(720.0, 1280.0);
+        assert_eq!(
+            background(&mut app),
+            Color::srgb(0.0, 128.0 / 255.0, 0.0),
+            "Should apply again when the window is resized"
+        );
+
+        app.world_mut().resource_mut::<EcssMedia>().color_scheme = ColorScheme::Dark;
+        assert_eq!(
+            background(&mut app),
+            Color::srgb(0.0, 128.0 / 255.0, 0.0),
+            "Should only apply when all conditions matches"
+        );
+
+        app.world_mut()
+            .get_mut::<Window>(window)
+            .unwrap()
+            .resolution
+            .set(1280.0, 1280.0);
+        assert_eq!(background(&mut app), Color::srgb(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn refresh_sheets_on_changed_variables() {
+        use bevy::ecs::system::{IntoSystem, System};
+
+        let mut world = setup();
+        world.init_resource::<EcssVariables>();
+
+        let mut assets = world.resource_mut::<Assets<StyleSheetAsset>>();
+        let with_env = assets.add(StyleSheetAsset::parse(
+            "",
+            "button { background-color: env(accent, white); }",
+        ));
+        let without_env = assets.add(StyleSheetAsset::parse(
+            "",
+            "button { background-color: red; }",
+        ));
+        let themed = world.spawn(StyleSheet::new(with_env)).id();
+        let plain = world.spawn(StyleSheet::new(without_env)).id();
+
+        let mut system = IntoSystem::into_system(watch_variables);
+        system.initialize(&mut world);
+        system.run((), &mut world);
+        world.clear_trackers();
+
+        world
+            .resource_mut::<EcssVariables>()
+            .set("accent", "#ff03ab")
+            .unwrap();
+        system.run((), &mut world);
+
+        let is_changed = |entity| {
+            world
+                .entity(entity)
+                .get_ref::<StyleSheet>()
+                .unwrap()
+                .is_changed()
+        };
+        assert!(is_changed(themed));
+        assert!(!is_changed(plain));
+
+        let variables = world.resource::<EcssVariables>();
+        let values = crate::parser::parse_property_values("env(accent) env(missing, 1px)").unwrap();
+        assert_eq!(
+            values.substitute_env(variables).unwrap().to_vec(),
+            vec![
+                crate::PropertyToken::Hash("ff03ab".to_string()),
+                crate::PropertyToken::Dimension(1.0)
+            ]
+        );
+        let values = crate::parser::parse_property_values("env(missing)").unwrap();
+        assert!(values.substitute_env(variables).is_none());
     }
 }